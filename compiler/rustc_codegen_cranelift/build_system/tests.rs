@@ -83,8 +83,41 @@ const fn jit_bin(config: &'static str, source: &'static str, args: &'static str)
     TestCase::build_lib("build.alloc_system", "example/alloc_system.rs", "lib"),
     TestCase::build_bin_and_run("aot.alloc_example", "example/alloc_example.rs", &[]),
     TestCase::jit_bin("jit.std_example", "example/std_example.rs", ""),
-    TestCase::build_bin_and_run("aot.std_example", "example/std_example.rs", &["arg"]),
+    TestCase::custom("aot.std_example", &|runner| {
+        // Cranelift's optimizer rewrites some instruction patterns into ones that may not be
+        // supported by the backend, so build with optimizations enabled. The jit test covers
+        // std_example without optimizations. Also pass a cg_clif option, a shared Cranelift
+        // setting and on x86_64 an isa specific one.
+        let mut llvm_args = "-Cllvm-args=enable_verifier,opt_level=speed_and_size".to_owned();
+        if runner.target_compiler.triple.starts_with("x86_64") {
+            llvm_args.push_str(",has_popcnt=false");
+        }
+        let mut args = vec!["example/std_example.rs", "-Copt-level=3", &llvm_args];
+        if !runner.use_unstable_features {
+            args.extend(["--cfg", "no_unstable_features"]);
+        }
+        runner.run_rustc(args);
+        runner.run_out_command("std_example", &["arg"]);
+    }),
     TestCase::build_bin_and_run("aot.dst_field_align", "example/dst-field-align.rs", &[]),
+    TestCase::custom("aot.unsupported", &|runner| {
+        runner.run_rustc(["example/unsupported.rs", "-Cllvm-args=allow_unsupported=true"]);
+        runner.run_out_command("unsupported", &[]);
+
+        let mut traps = vec![("huge_local", "too big to store on the stack")];
+        if runner.target_compiler.triple.starts_with("x86_64") {
+            traps.push(("vectorcall", "vectorcall call conv not yet implemented"));
+        }
+        for (arg, msg) in traps {
+            let output = runner.out_command("unsupported", &[arg]).output().unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if output.status.code() != Some(1) || !stdout.contains(msg) {
+                eprintln!("{stdout}{}", String::from_utf8_lossy(&output.stderr));
+                eprintln!("[AOT] unsupported: `{arg}` didn't exit with a `{msg}` error");
+                std::process::exit(1);
+            }
+        }
+    }),
     TestCase::build_bin_and_run(
         "aot.subslice-patterns-const-eval",
         "example/subslice-patterns-const-eval.rs",
@@ -99,7 +132,206 @@ const fn jit_bin(config: &'static str, source: &'static str, args: &'static str)
     TestCase::build_bin_and_run("aot.mod_bench", "example/mod_bench.rs", &[]),
     TestCase::build_bin_and_run("aot.issue-72793", "example/issue-72793.rs", &[]),
     TestCase::build_bin("aot.issue-59326", "example/issue-59326.rs"),
+    TestCase::build_bin_and_run("aot.naked-functions", "example/naked-functions.rs", &[]),
     TestCase::build_bin_and_run("aot.neon", "example/neon.rs", &[]),
+    TestCase::custom("aot.c_abi", &|runner| {
+        if !runner.is_native {
+            // Building the C half of the test requires a C compiler for the target.
+            eprintln!("Cross-Compiling: Not running c_abi test");
+            return;
+        }
+
+        let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs);
+        let object = out_dir.join("c_abi.o");
+        let mut cc_cmd = Command::new("cc");
+        cc_cmd.arg("-c").arg("-O2").arg("example/c_abi.c").arg("-o").arg(&object);
+        spawn_and_wait(cc_cmd);
+        let mut ar_cmd = Command::new("ar");
+        ar_cmd.arg("rcs").arg(out_dir.join("libc_abi.a")).arg(&object);
+        spawn_and_wait(ar_cmd);
+
+        runner.run_rustc([
+            "example/c_abi.rs",
+            "-L",
+            &format!("native={}", out_dir.display()),
+            "-l",
+            "static=c_abi",
+        ]);
+        runner.run_out_command("c_abi", &[]);
+    }),
+    TestCase::custom("aot.cdylib", &|runner| {
+        if !runner.is_native || runner.target_compiler.triple.contains("windows") {
+            // Linking the C program requires a C compiler for the target. On Windows it would
+            // need to be linked against the import library instead.
+            eprintln!("Not running cdylib test");
+            return;
+        }
+
+        runner.run_rustc(["example/cdylib.rs"]);
+
+        let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs);
+        let mut cc_cmd = Command::new("cc");
+        cc_cmd
+            .arg("example/cdylib.c")
+            .arg("-o")
+            .arg(out_dir.join("cdylib_test"))
+            .arg("-L")
+            .arg(&out_dir)
+            .arg("-lcdylib")
+            .arg(format!("-Wl,-rpath,{}", out_dir.display()));
+        spawn_and_wait(cc_cmd);
+        runner.run_out_command("cdylib_test", &[]);
+    }),
+    TestCase::custom("aot.place_offsets", &|runner| {
+        runner.run_rustc(["example/place_offsets.rs", "--emit", "llvm-ir,link"]);
+        runner.run_out_command("place_offsets", &[]);
+
+        for func in ["13place_offsets4read", "13place_offsets5write"] {
+            let (clif_file, clif) = runner.read_unopt_clif("place_offsets", func);
+
+            // All projections have a constant offset, so no address computation is necessary.
+            let address_computations = clif
+                .lines()
+                .filter(|line| line.contains(" = imul") || line.contains(" = iadd"))
+                .count();
+            if address_computations != 0 {
+                eprintln!("{clif}");
+                eprintln!(
+                    "[AOT] place_offsets: found {address_computations} address computations in \
+                     {}, expected 0",
+                    clif_file.display(),
+                );
+                std::process::exit(1);
+            }
+        }
+    }),
+    TestCase::custom("aot.data_addrs", &|runner| {
+        runner.run_rustc(["example/data_addrs.rs", "--emit", "llvm-ir,link"]);
+        runner.run_out_command("data_addrs", &[]);
+
+        for func in ["10data_addrs9sum_table", "10data_addrs6select"] {
+            let (clif_file, clif) = runner.read_unopt_clif("data_addrs", func);
+
+            let mut global_values = clif
+                .lines()
+                .filter_map(|line| line.split_once(" = global_value."))
+                .map(|(_, rest)| rest.split_whitespace().nth(1).unwrap())
+                .collect::<Vec<_>>();
+            global_values.sort();
+            let materialized = global_values.len();
+            global_values.dedup();
+            if global_values.is_empty() || materialized != global_values.len() {
+                eprintln!("{clif}");
+                eprintln!(
+                    "[AOT] data_addrs: found {materialized} address computations of {} data \
+                     objects in {}, expected one per data object",
+                    global_values.len(),
+                    clif_file.display(),
+                );
+                std::process::exit(1);
+            }
+        }
+    }),
+    TestCase::custom("aot.codegen_units", &|runner| {
+        runner.run_rustc(["example/codegen_units.rs", "-Ccodegen-units=16"]);
+        runner.run_out_command("codegen_units", &[]);
+
+        if !runner.is_native
+            || runner.target_compiler.triple.contains("windows")
+            || Command::new("nm").arg("--version").output().is_err()
+        {
+            eprintln!("Not checking symbols of codegen_units test");
+            return;
+        }
+
+        let mut nm_cmd = Command::new("nm");
+        nm_cmd.arg(BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("codegen_units"));
+        let output = nm_cmd.output().unwrap();
+        let symbols = String::from_utf8_lossy(&output.stdout);
+        let copies = symbols
+            .lines()
+            .filter(|line| line.contains("13codegen_units7generic") && !line.contains(" U "))
+            .count();
+        if copies != 1 {
+            eprintln!("{symbols}");
+            eprintln!("[AOT] codegen_units: found {copies} copies of `generic::<u8>`, expected 1");
+            std::process::exit(1);
+        }
+
+        let local_copies = symbols
+            .lines()
+            .filter(|line| line.contains("13codegen_units10local_copy") && line.contains(" t "))
+            .count();
+        if local_copies < 2 {
+            eprintln!("{symbols}");
+            eprintln!(
+                "[AOT] codegen_units: found {local_copies} local copies of `local_copy::<u8>`, \
+                 expected one per codegen unit using it"
+            );
+            std::process::exit(1);
+        }
+
+        if runner.target_compiler.triple.contains("linux") {
+            for func in ["13codegen_units4weak", "13codegen_units12linkonce_odr"] {
+                if !symbols.lines().any(|line| line.contains(func) && line.contains(" W ")) {
+                    eprintln!("{symbols}");
+                    eprintln!("[AOT] codegen_units: `{func}` is not a weak symbol");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }),
+    TestCase::custom("aot.emit_asm", &|runner| {
+        runner.run_rustc([
+            "example/debuginfo.rs",
+            "--emit",
+            "asm,link",
+            "-Ccodegen-units=2",
+            "--crate-name",
+            "emit_asm",
+        ]);
+        runner.run_out_command("emit_asm", &[]);
+
+        let asm_file = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("emit_asm.s");
+        let asm = fs::read_to_string(&asm_file).unwrap();
+        if !asm.lines().any(|line| line.contains("8emit_asm7inspect") && line.ends_with(':')) {
+            eprintln!("{asm}");
+            eprintln!("[AOT] emit_asm: no disassembly of `inspect` in {}", asm_file.display());
+            std::process::exit(1);
+        }
+    }),
+    TestCase::custom("aot.debuginfo", &|runner| {
+        runner.run_rustc(["example/debuginfo.rs"]);
+        runner.run_out_command("debuginfo", &[]);
+
+        if !runner.is_native || Command::new("gdb").arg("--version").output().is_err() {
+            eprintln!("Not running debuginfo test in gdb");
+            return;
+        }
+
+        let script = fs::read_to_string("example/debuginfo.gdb").unwrap();
+        let mut gdb_cmd = Command::new("gdb");
+        gdb_cmd
+            .arg("-nx")
+            .arg("-batch")
+            .arg("-x")
+            .arg("example/debuginfo.gdb")
+            .arg(BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("debuginfo"));
+        let output = gdb_cmd.output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let mut rest = &*stdout;
+        for check in script.lines().filter_map(|line| line.strip_prefix("# check: ")) {
+            match rest.find(check) {
+                Some(pos) => rest = &rest[pos + check.len()..],
+                None => {
+                    eprintln!("{stdout}{}", String::from_utf8_lossy(&output.stderr));
+                    eprintln!("[AOT] debuginfo: `{check}` not found in gdb output");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }),
 ];
 
 pub(crate) static RAND_REPO: GitRepo = GitRepo::github(
@@ -471,7 +703,28 @@ fn run_rustc<I, S>(&self, args: I)
         spawn_and_wait(self.rustc_command(args));
     }
 
+    /// Read the clif ir of the function whose symbol name contains `func` before optimizations,
+    /// as written by `--emit llvm-ir` for the example `name`.
+    fn read_unopt_clif(&self, name: &str, func: &str) -> (PathBuf, String) {
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&self.dirs).join(format!("{name}.clif"));
+        let clif_file = fs::read_dir(&clif_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| {
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                file_name.contains(func) && file_name.ends_with(".unopt.clif")
+            })
+            .unwrap();
+        let clif = fs::read_to_string(&clif_file).unwrap();
+        (clif_file, clif)
+    }
+
     fn run_out_command(&self, name: &str, args: &[&str]) {
+        spawn_and_wait(self.out_command(name, args));
+    }
+
+    #[must_use]
+    fn out_command(&self, name: &str, args: &[&str]) -> Command {
         let mut full_cmd = vec![];
 
         // Prepend the RUN_WRAPPER's
@@ -492,7 +745,6 @@ fn run_out_command(&self, name: &str, args: &[&str]) {
 
         let mut cmd = Command::new(first);
         cmd.args(cmd_iter);
-
-        spawn_and_wait(cmd);
+        cmd
     }
 }
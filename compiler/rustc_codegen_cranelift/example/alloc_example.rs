@@ -39,5 +39,38 @@ fn main(_argc: isize, _argv: *const *const u8) -> isize {
         puts(*world as *const str as *const u8);
     }
 
+    // Boxed ZST don't allocate, but still need to have their drop glue run.
+    let zst = Box::new(ZstDrop);
+    if zst.as_ref() as *const ZstDrop as usize == 0 {
+        core::intrinsics::abort();
+    }
+    drop(zst);
+
+    let boxed = Box::new((42u64, NoisyDrop("Dropped boxed value\0")));
+    if boxed.0 != 42 {
+        core::intrinsics::abort();
+    }
+    drop(boxed);
+
     0
 }
+
+struct ZstDrop;
+
+impl Drop for ZstDrop {
+    fn drop(&mut self) {
+        unsafe {
+            puts("Dropped boxed ZST\0" as *const str as *const u8);
+        }
+    }
+}
+
+struct NoisyDrop(&'static str);
+
+impl Drop for NoisyDrop {
+    fn drop(&mut self) {
+        unsafe {
+            puts(self.0 as *const str as *const u8);
+        }
+    }
+}
@@ -5,6 +5,9 @@
 use std::{
     marker::Unsize,
     ops::{CoerceUnsized, Deref, DispatchFromDyn},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
 };
 
 struct Ptr<T: ?Sized>(Box<T>);
@@ -33,6 +36,22 @@ fn deref(&self) -> &T {
 impl<T: CoerceUnsized<U>, U> CoerceUnsized<Wrapper<U>> for Wrapper<T> {}
 impl<T: DispatchFromDyn<U>, U> DispatchFromDyn<Wrapper<U>> for Wrapper<T> {}
 
+#[repr(transparent)]
+struct RefWrapper<'a, T: ?Sized>(&'a T);
+
+impl<T: ?Sized> Deref for RefWrapper<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<'a, T: Unsize<U> + ?Sized, U: ?Sized> DispatchFromDyn<RefWrapper<'a, U>>
+    for RefWrapper<'a, T>
+{
+}
+
 trait Trait {
     // This method isn't object-safe yet. Unsized by-value `self` is object-safe (but not callable
     // without unsized_locals), but wrappers around `Self` currently are not.
@@ -55,6 +74,41 @@ fn wrapper_ptr_wrapper(self: Wrapper<Ptr<Wrapper<Self>>>) -> i32 {
     }
 }
 
+trait Receivers {
+    fn by_ref(&self) -> i32;
+    fn by_box(self: Box<Self>) -> i32;
+    fn by_ref_wrapper(self: RefWrapper<'_, Self>) -> i32;
+    fn by_rc(self: Rc<Self>) -> i32;
+    fn by_arc(self: Arc<Self>) -> i32;
+    fn by_pin_mut(self: Pin<&mut Self>) -> i32;
+    fn by_pin_box(self: Pin<Box<Self>>) -> i32;
+}
+
+impl Receivers for i32 {
+    fn by_ref(&self) -> i32 {
+        *self
+    }
+    fn by_box(self: Box<Self>) -> i32 {
+        *self
+    }
+    fn by_ref_wrapper(self: RefWrapper<'_, Self>) -> i32 {
+        *self.0
+    }
+    fn by_rc(self: Rc<Self>) -> i32 {
+        *self
+    }
+    fn by_arc(self: Arc<Self>) -> i32 {
+        *self
+    }
+    fn by_pin_mut(mut self: Pin<&mut Self>) -> i32 {
+        *self += 1;
+        *self
+    }
+    fn by_pin_box(self: Pin<Box<Self>>) -> i32 {
+        *self
+    }
+}
+
 fn main() {
     let pw = Ptr(Box::new(Wrapper(5))) as Ptr<Wrapper<dyn Trait>>;
     assert_eq!(pw.ptr_wrapper(), 5);
@@ -64,4 +118,29 @@ fn main() {
 
     let wpw = Wrapper(Ptr(Box::new(Wrapper(7)))) as Wrapper<Ptr<Wrapper<dyn Trait>>>;
     assert_eq!(wpw.wrapper_ptr_wrapper(), 7);
+
+    let r = &8 as &dyn Receivers;
+    assert_eq!(r.by_ref(), 8);
+
+    let b = Box::new(9) as Box<dyn Receivers>;
+    assert_eq!(b.by_box(), 9);
+
+    let rw = RefWrapper(&10 as &dyn Receivers);
+    assert_eq!(rw.by_ref_wrapper(), 10);
+
+    let rc = Rc::new(11) as Rc<dyn Receivers>;
+    assert_eq!(rc.clone().by_rc(), 11);
+    assert_eq!(Rc::strong_count(&rc), 1);
+
+    let arc = Arc::new(12) as Arc<dyn Receivers>;
+    assert_eq!(arc.clone().by_arc(), 12);
+    assert_eq!(Arc::strong_count(&arc), 1);
+
+    let mut val = 12;
+    let pm = Pin::new(&mut val) as Pin<&mut dyn Receivers>;
+    assert_eq!(pm.by_pin_mut(), 13);
+    assert_eq!(val, 13);
+
+    let pb = Box::pin(14) as Pin<Box<dyn Receivers>>;
+    assert_eq!(pb.by_pin_box(), 14);
 }
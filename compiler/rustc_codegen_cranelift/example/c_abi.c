@@ -0,0 +1,95 @@
+// C side of the c_abi test. The `roundtrip_*` functions take a struct by value and return it with
+// every field incremented. The `call_*` functions do the same after passing the struct through the
+// given Rust function.
+
+#include <stdarg.h>
+#include <stdint.h>
+
+#define DEFINE_ROUNDTRIP(name, type, modify)                                                       \
+    type roundtrip_##name(type x) {                                                                \
+        modify;                                                                                    \
+        return x;                                                                                  \
+    }                                                                                              \
+    type call_##name(type (*f)(type), type x) {                                                    \
+        x = f(x);                                                                                  \
+        modify;                                                                                    \
+        return x;                                                                                  \
+    }
+
+struct I8x3 { int8_t a, b, c; };
+struct I16x3 { int16_t a, b, c; };
+struct I32x2 { int32_t a, b; };
+struct I32x3 { int32_t a, b, c; };
+struct I64x2 { int64_t a, b; };
+struct I64x3 { int64_t a, b, c; };
+struct F32x2 { float a, b; };
+struct F32x3 { float a, b, c; };
+struct F64x2 { double a, b; };
+struct F32I32 { float a; int32_t b; };
+struct I8I64 { int8_t a; int64_t b; };
+struct F64I8 { double a; int8_t b; };
+struct F32F32F64 { float a, b; double c; };
+struct F32F32I64 { float a, b; int64_t c; };
+
+DEFINE_ROUNDTRIP(i8x3, struct I8x3, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(i16x3, struct I16x3, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(i32x2, struct I32x2, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(i32x3, struct I32x3, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(i64x2, struct I64x2, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(i64x3, struct I64x3, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(f32x2, struct F32x2, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(f32x3, struct F32x3, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(f64x2, struct F64x2, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(f32i32, struct F32I32, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(i8i64, struct I8I64, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(f64i8, struct F64I8, x.a += 1; x.b += 2)
+DEFINE_ROUNDTRIP(f32f32f64, struct F32F32F64, x.a += 1; x.b += 2; x.c += 3)
+DEFINE_ROUNDTRIP(f32f32i64, struct F32F32I64, x.a += 1; x.b += 2; x.c += 3)
+
+// Calls the given Rust variadic function with ten (integer, double) pairs, so both kinds of
+// arguments partially end up on the stack.
+double call_sum_variadic(double (*f)(int, ...)) {
+    return f(10, 1LL, 1.5, 2LL, 2.5, 3LL, 3.5, 4LL, 4.5, 5LL, 5.5, 6LL, 6.5, 7LL, 7.5, 8LL, 8.5,
+             9LL, 9.5, 10LL, 10.5);
+}
+
+// Sums `n` (integer, double) pairs passed as variadic arguments.
+double sum_variadic_c(int n, ...) {
+    va_list args;
+    va_start(args, n);
+    double sum = 0;
+    for (int i = 0; i < n; i++) {
+        sum += va_arg(args, long long);
+        sum += va_arg(args, double);
+    }
+    va_end(args);
+    return sum;
+}
+
+typedef float f32x4 __attribute__((vector_size(16)));
+typedef int32_t i32x4 __attribute__((vector_size(16)));
+typedef uint8_t u8x8 __attribute__((vector_size(8)));
+
+f32x4 add_f32x4(f32x4 a, f32x4 b) {
+    return a + b;
+}
+
+i32x4 call_i32x4(i32x4 (*f)(i32x4, i32x4), i32x4 a, i32x4 b) {
+    return f(a, b) * 2;
+}
+
+u8x8 add_u8x8(u8x8 a, u8x8 b) {
+    return a + b;
+}
+
+#if defined(__x86_64__)
+// Both x86_64 calling conventions can be used on any x86_64 target.
+#define DEFINE_SUM(name, abi)                                                                      \
+    __attribute__((abi)) double name(int32_t a, double b, struct I64x2 c, int64_t d, int64_t e,    \
+                                     double f) {                                                   \
+        return a + b + c.a + c.b + d + e + f;                                                      \
+    }
+
+DEFINE_SUM(win64_sum, ms_abi)
+DEFINE_SUM(sysv64_sum, sysv_abi)
+#endif
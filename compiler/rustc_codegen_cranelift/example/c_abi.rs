@@ -0,0 +1,185 @@
+// Checks that passing structs by value to and from C functions follows the C abi. The C side is
+// in `c_abi.c`.
+
+#![feature(c_variadic, repr_simd, simd_ffi)]
+
+use std::ffi::{c_int, VaList};
+
+macro_rules! test_struct {
+    ($name:ident, $roundtrip:ident, $call:ident, $callback:ident, $ty:ident { $($field:ident: $field_ty:ty = $val:expr),* }) => {
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        struct $ty {
+            $($field: $field_ty,)*
+        }
+
+        extern "C" {
+            fn $roundtrip(x: $ty) -> $ty;
+            fn $call(f: extern "C" fn($ty) -> $ty, x: $ty) -> $ty;
+        }
+
+        extern "C" fn $callback(x: $ty) -> $ty {
+            let mut i = 0;
+            $ty { $($field: { i += 1; x.$field * 10 as $field_ty + i as $field_ty },)* }
+        }
+
+        fn $name() {
+            let x = $ty { $($field: $val,)* };
+            let mut i = 0;
+            let expected = $ty { $($field: { i += 1; x.$field + i as $field_ty },)* };
+            assert_eq!(unsafe { $roundtrip(x) }, expected, "roundtrip");
+
+            let mut i = 0;
+            let expected = $ty { $($field: { i += 1; x.$field * 10 as $field_ty + (2 * i) as $field_ty },)* };
+            assert_eq!(unsafe { $call($callback, x) }, expected, "call");
+        }
+    };
+}
+
+test_struct!(test_i8x3, roundtrip_i8x3, call_i8x3, callback_i8x3, I8x3 { a: i8 = 1, b: i8 = -2, c: i8 = 3 });
+test_struct!(test_i16x3, roundtrip_i16x3, call_i16x3, callback_i16x3, I16x3 { a: i16 = 1, b: i16 = -2, c: i16 = 300 });
+test_struct!(test_i32x2, roundtrip_i32x2, call_i32x2, callback_i32x2, I32x2 { a: i32 = 1, b: i32 = -2 });
+test_struct!(test_i32x3, roundtrip_i32x3, call_i32x3, callback_i32x3, I32x3 { a: i32 = 1, b: i32 = -2, c: i32 = 3 });
+test_struct!(test_i64x2, roundtrip_i64x2, call_i64x2, callback_i64x2, I64x2 { a: i64 = 1, b: i64 = -2 });
+test_struct!(test_i64x3, roundtrip_i64x3, call_i64x3, callback_i64x3, I64x3 { a: i64 = 1, b: i64 = -2, c: i64 = 3 });
+test_struct!(test_f32x2, roundtrip_f32x2, call_f32x2, callback_f32x2, F32x2 { a: f32 = 1.5, b: f32 = -2.5 });
+test_struct!(test_f32x3, roundtrip_f32x3, call_f32x3, callback_f32x3, F32x3 { a: f32 = 1.5, b: f32 = -2.5, c: f32 = 3.5 });
+test_struct!(test_f64x2, roundtrip_f64x2, call_f64x2, callback_f64x2, F64x2 { a: f64 = 1.5, b: f64 = -2.5 });
+test_struct!(test_f32i32, roundtrip_f32i32, call_f32i32, callback_f32i32, F32I32 { a: f32 = 1.5, b: i32 = -2 });
+test_struct!(test_i8i64, roundtrip_i8i64, call_i8i64, callback_i8i64, I8I64 { a: i8 = 1, b: i64 = -2 });
+test_struct!(test_f64i8, roundtrip_f64i8, call_f64i8, callback_f64i8, F64I8 { a: f64 = 1.5, b: i8 = -2 });
+test_struct!(test_f32f32f64, roundtrip_f32f32f64, call_f32f32f64, callback_f32f32f64, F32F32F64 { a: f32 = 1.5, b: f32 = -2.5, c: f64 = 3.5 });
+test_struct!(test_f32f32i64, roundtrip_f32f32i64, call_f32f32i64, callback_f32f32i64, F32F32I64 { a: f32 = 1.5, b: f32 = -2.5, c: i64 = -3 });
+
+extern "C" {
+    fn call_sum_variadic(f: unsafe extern "C" fn(c_int, ...) -> f64) -> f64;
+    fn sum_variadic_c(n: c_int, ...) -> f64;
+}
+
+unsafe fn sum_pairs(n: c_int, mut args: VaList<'_, '_>) -> f64 {
+    (0..n).map(|_| args.arg::<i64>() as f64 + args.arg::<f64>()).sum()
+}
+
+unsafe extern "C" fn sum_variadic(n: c_int, mut args: ...) -> f64 {
+    let sum_copy = args.with_copy(|copy| sum_pairs(n, copy));
+    let sum = sum_pairs(n, args.as_va_list());
+    assert_eq!(sum, sum_copy);
+    sum
+}
+
+unsafe extern "C" fn sum_ints_variadic(n: c_int, mut args: ...) -> i64 {
+    (0..n).map(|_| args.arg::<i64>()).sum()
+}
+
+fn test_variadic() {
+    assert_eq!(unsafe { call_sum_variadic(sum_variadic) }, 115.0);
+    assert_eq!(unsafe { sum_ints_variadic(8, 1i64, 2i64, 3i64, 4i64, 5i64, 6i64, 7i64, -8i64) }, 20);
+
+    unsafe {
+        assert_eq!(sum_variadic_c(1, 1i64, 0.5f64), 1.5);
+        macro_rules! call_with_ten_pairs {
+            ($f:ident) => {
+                $f(10, 1i64, 1.5, 2i64, 2.5, 3i64, 3.5, 4i64, 4.5, 5i64, 5.5, 6i64, 6.5, 7i64, 7.5,
+                   8i64, 8.5, 9i64, 9.5, 10i64, 10.5)
+            };
+        }
+        assert_eq!(call_with_ten_pairs!(sum_variadic_c), 115.0);
+        assert_eq!(call_with_ten_pairs!(sum_variadic), 115.0);
+
+        // The callee is passed to the trampoline setting `al` in the next free register or on the
+        // stack when all registers are used.
+        let sum_variadic_c_ptr: unsafe extern "C" fn(c_int, ...) -> f64 =
+            std::hint::black_box(sum_variadic_c);
+        assert_eq!(sum_variadic_c_ptr(1, 1i64, 0.5f64), 1.5);
+        assert_eq!(sum_variadic_c_ptr(2, 1i64, 0.5f64, 2i64, 0.25f64), 3.75);
+        assert_eq!(call_with_ten_pairs!(sum_variadic_c_ptr), 115.0);
+    }
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct F32x4(f32, f32, f32, f32);
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct I32x4(i32, i32, i32, i32);
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct U8x8(u8, u8, u8, u8, u8, u8, u8, u8);
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct I64x4(i64, i64, i64, i64);
+
+#[allow(improper_ctypes)]
+extern "C" {
+    fn add_f32x4(a: F32x4, b: F32x4) -> F32x4;
+    fn call_i32x4(f: extern "C" fn(I32x4, I32x4) -> I32x4, a: I32x4, b: I32x4) -> I32x4;
+    fn add_u8x8(a: U8x8, b: U8x8) -> U8x8;
+}
+
+#[allow(improper_ctypes_definitions)]
+extern "C" fn sub_i32x4(a: I32x4, b: I32x4) -> I32x4 {
+    I32x4(a.0 - b.0, a.1 - b.1, a.2 - b.2, a.3 - b.3)
+}
+
+// Vectors larger than 128bit are split into multiple 128bit vectors.
+#[allow(improper_ctypes_definitions)]
+extern "C" fn swap_i64x4(a: I64x4, b: I64x4) -> I64x4 {
+    I64x4(b.0, a.1, b.2, a.3)
+}
+
+fn test_simd() {
+    let a = F32x4(1.0, 2.0, 3.0, 4.0);
+    let b = F32x4(0.5, -1.0, 10.0, 0.0);
+    assert_eq!(unsafe { add_f32x4(a, b) }, F32x4(1.5, 1.0, 13.0, 4.0));
+
+    let a = I32x4(10, 20, 30, 40);
+    let b = I32x4(1, 2, 3, -4);
+    assert_eq!(unsafe { call_i32x4(sub_i32x4, a, b) }, I32x4(18, 36, 54, 88));
+
+    let a = U8x8(1, 2, 3, 4, 5, 6, 7, 8);
+    let b = U8x8(10, 20, 30, 40, 50, 60, 70, 255);
+    assert_eq!(unsafe { add_u8x8(a, b) }, U8x8(11, 22, 33, 44, 55, 66, 77, 7));
+
+    let swap_i64x4 = std::hint::black_box(swap_i64x4 as extern "C" fn(I64x4, I64x4) -> I64x4);
+    let a = I64x4(1, 2, 3, 4);
+    let b = I64x4(-1, -2, -3, -4);
+    assert_eq!(swap_i64x4(a, b), I64x4(-1, 2, -3, 4));
+}
+
+#[cfg(target_arch = "x86_64")]
+fn test_explicit_abis() {
+    extern "win64" {
+        fn win64_sum(a: i32, b: f64, c: I64x2, d: i64, e: i64, f: f64) -> f64;
+    }
+    extern "sysv64" {
+        fn sysv64_sum(a: i32, b: f64, c: I64x2, d: i64, e: i64, f: f64) -> f64;
+    }
+
+    let c = I64x2 { a: 3, b: 4 };
+    assert_eq!(unsafe { win64_sum(1, 2.5, c, 5, 6, 7.5) }, 29.0);
+    assert_eq!(unsafe { sysv64_sum(1, 2.5, c, 5, 6, 7.5) }, 29.0);
+}
+
+fn main() {
+    test_i8x3();
+    test_i16x3();
+    test_i32x2();
+    test_i32x3();
+    test_i64x2();
+    test_i64x3();
+    test_f32x2();
+    test_f32x3();
+    test_f64x2();
+    test_f32i32();
+    test_i8i64();
+    test_f64i8();
+    test_f32f32f64();
+    test_f32f32i64();
+    test_variadic();
+    test_simd();
+    #[cfg(target_arch = "x86_64")]
+    test_explicit_abis();
+}
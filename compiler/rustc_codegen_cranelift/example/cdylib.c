@@ -0,0 +1,15 @@
+// C side of the cdylib test. Calls the functions exported by `cdylib.rs`.
+
+#include <assert.h>
+#include <stdint.h>
+
+int32_t add(int32_t a, int32_t b);
+int64_t renamed_mul(int64_t a, int64_t b);
+
+int main(void) {
+    assert(add(1, 2) == 3);
+    assert(add(-5, 3) == -2);
+    assert(renamed_mul(6, 7) == 42);
+    assert(renamed_mul(-3, 1000000000000) == -3000000000000);
+    return 0;
+}
@@ -0,0 +1,18 @@
+// A C API exposed by a cdylib. It is called by the C program in `cdylib.c`.
+
+#![crate_type = "cdylib"]
+
+// Not exported. Instantiated from both exported functions.
+fn checked<T: std::fmt::Debug>(val: Option<T>) -> T {
+    val.unwrap_or_else(|| panic!("overflow"))
+}
+
+#[no_mangle]
+pub extern "C" fn add(a: i32, b: i32) -> i32 {
+    checked(a.checked_add(b))
+}
+
+#[export_name = "renamed_mul"]
+pub extern "C" fn mul(a: i64, b: i64) -> i64 {
+    checked(a.checked_mul(b))
+}
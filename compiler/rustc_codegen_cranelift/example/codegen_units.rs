@@ -0,0 +1,71 @@
+// Checks that generic instances used from multiple codegen units are defined only once in the final
+// binary, that functions copied into every codegen unit using them link together and that functions
+// with weak or linkonce linkage are weak symbols. Compiled with `-Ccodegen-units=16`.
+
+#![feature(linkage)]
+
+#[inline(never)]
+fn generic<T: Default + std::fmt::Debug>() -> String {
+    format!("{:?}", T::default())
+}
+
+// `#[inline(always)]` functions get an internal copy in every codegen unit using them.
+#[inline(always)]
+fn local_copy<T: Default + PartialEq>(value: T) -> bool {
+    value == T::default()
+}
+
+// Weak and linkonce functions may be defined by multiple object files. The linker picks one.
+#[linkage = "weak"]
+#[inline(never)]
+fn weak() -> u32 {
+    42
+}
+
+#[linkage = "linkonce_odr"]
+#[inline(never)]
+fn linkonce_odr() -> u32 {
+    43
+}
+
+macro_rules! user_module {
+    ($name:ident) => {
+        mod $name {
+            pub fn addr() -> usize {
+                super::generic::<u8> as usize
+            }
+
+            pub fn call() -> String {
+                let mut v = Vec::<u8>::new();
+                v.push(1);
+                super::generic::<u8>() + &v.len().to_string()
+            }
+
+            pub fn linked() -> (bool, u32, u32) {
+                (super::local_copy(0u8), super::weak(), super::linkonce_odr())
+            }
+        }
+    };
+}
+
+user_module!(first);
+user_module!(second);
+user_module!(third);
+user_module!(fourth);
+
+fn main() {
+    assert_eq!(first::call(), "01");
+    assert_eq!(second::call(), "01");
+    assert_eq!(third::call(), "01");
+    assert_eq!(fourth::call(), "01");
+
+    let addr = first::addr();
+    assert_eq!(second::addr(), addr);
+    assert_eq!(third::addr(), addr);
+    assert_eq!(fourth::addr(), addr);
+
+    assert_eq!(first::linked(), (true, 42, 43));
+    assert_eq!(second::linked(), (true, 42, 43));
+    assert_eq!(third::linked(), (true, 42, 43));
+    assert_eq!(fourth::linked(), (true, 42, 43));
+}
@@ -0,0 +1,27 @@
+// Checks that the address of every static is computed only once per function, even when it is
+// referenced inside a loop or in multiple branches. The build system counts the address
+// computations in the clif ir of `sum_table` and `select`.
+
+static TABLE: [u32; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+static OTHER: u32 = 10;
+
+#[inline(never)]
+fn sum_table() -> u32 {
+    let mut sum = 0;
+    for i in 0..TABLE.len() {
+        sum += TABLE[i];
+    }
+    sum
+}
+
+#[inline(never)]
+fn select(first: bool) -> u32 {
+    let value = if first { TABLE[0] + OTHER } else { TABLE[7] * OTHER };
+    value + TABLE[std::hint::black_box(1)]
+}
+
+fn main() {
+    assert_eq!(sum_table(), 36);
+    assert_eq!(select(true), 13);
+    assert_eq!(select(false), 82);
+}
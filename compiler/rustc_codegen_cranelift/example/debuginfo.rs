@@ -0,0 +1,43 @@
+// Checks that a debugger can print local variables and arguments. See `debuginfo.gdb` for the
+// debugger commands and expected output.
+
+#![allow(dead_code)]
+
+use std::hint::black_box;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+enum Shape {
+    Circle { radius: f64 },
+    Rect(Point, Point),
+}
+
+// Takes enough arguments for some to be passed on the stack, which moves the stack pointer of the
+// caller around the call.
+#[inline(never)]
+fn many_args(a: u64, b: u64, c: u64, d: u64, e: u64, f: u64, g: u64, h: u64, i: u64) -> u64 {
+    black_box(a + b + c + d + e + f + g + h + i)
+}
+
+#[inline(never)]
+fn inspect(number: u64, point: Point, values: [u16; 3]) {
+    let doubled = number * 2;
+    let shapes =
+        [Shape::Circle { radius: 1.5 }, Shape::Rect(Point { x: 3, y: 4 }, Point { x: 5, y: 6 })];
+    let slice: &[u16] = &values[1..];
+    let pair = (doubled, true);
+    black_box(&doubled);
+    black_box(&point);
+    black_box(&values);
+    black_box(&shapes);
+    black_box(&slice);
+    black_box(&pair);
+    many_args(doubled, 1, 2, 3, 4, 5, 6, 7, 8);
+}
+
+fn main() {
+    inspect(42, Point { x: 1, y: -2 }, [7, 8, 9]);
+}
@@ -175,6 +175,20 @@ fn main() {
 
     unsafe {
         printf("Hello %s\n\0" as *const str as *const i8, "printf\0" as *const str as *const i8);
+        printf(
+            "Hello %s %d\n\0" as *const str as *const i8,
+            "printf\0" as *const str as *const i8,
+            42,
+        );
+
+        let printf_ptr: unsafe extern "C" fn(*const i8, ...) -> i32 = printf;
+        printf_ptr("Hello printf pointer\n\0" as *const str as *const i8);
+        printf_ptr(
+            "Hello %s %d %d\n\0" as *const str as *const i8,
+            "printf pointer\0" as *const str as *const i8,
+            1,
+            2,
+        );
 
         let hello: &[u8] = b"Hello\0" as &[u8; 6];
         let ptr: *const i8 = hello as *const [u8] as *const i8;
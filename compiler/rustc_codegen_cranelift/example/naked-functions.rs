@@ -0,0 +1,77 @@
+// run-pass
+
+#![feature(asm_const, naked_functions)]
+
+use std::arch::asm;
+use std::hint::black_box;
+
+const OFFSET: u64 = 40;
+
+#[cfg(not(no_unstable_features))]
+extern "C" fn double(a: u64) -> u64 {
+    a * 2
+}
+
+#[cfg(target_arch = "x86_64")]
+mod naked {
+    use super::*;
+
+    #[naked]
+    pub extern "C" fn add(a: u64, b: u64) -> u64 {
+        unsafe { asm!("lea rax, [rdi + rsi]", "ret", options(noreturn)) }
+    }
+
+    #[naked]
+    pub extern "C" fn add_offset(a: u64) -> u64 {
+        unsafe { asm!("lea rax, [rdi + {}]", "ret", const OFFSET, options(noreturn)) }
+    }
+
+    #[cfg(not(no_unstable_features))]
+    #[naked]
+    pub extern "C" fn call_double(a: u64) -> u64 {
+        unsafe { asm!("push rax", "call {}", "pop rcx", "ret", sym double, options(noreturn)) }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod naked {
+    use super::*;
+
+    #[naked]
+    pub extern "C" fn add(a: u64, b: u64) -> u64 {
+        unsafe { asm!("add x0, x0, x1", "ret", options(noreturn)) }
+    }
+
+    #[naked]
+    pub extern "C" fn add_offset(a: u64) -> u64 {
+        unsafe { asm!("add x0, x0, #{}", "ret", const OFFSET, options(noreturn)) }
+    }
+
+    #[cfg(not(no_unstable_features))]
+    #[naked]
+    pub extern "C" fn call_double(a: u64) -> u64 {
+        unsafe {
+            asm!(
+                "stp x29, x30, [sp, #-16]!",
+                "bl {}",
+                "ldp x29, x30, [sp], #16",
+                "ret",
+                sym double,
+                options(noreturn)
+            )
+        }
+    }
+}
+
+fn main() {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        assert_eq!(naked::add(1, 2), 3);
+        assert_eq!(naked::add_offset(2), 42);
+        #[cfg(not(no_unstable_features))]
+        assert_eq!(naked::call_double(21), 42);
+
+        let add: extern "C" fn(u64, u64) -> u64 = black_box(naked::add);
+        assert_eq!(add(40, 2), 42);
+    }
+}
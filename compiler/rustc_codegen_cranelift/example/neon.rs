@@ -200,6 +200,84 @@ unsafe fn test_vqadd_u8() {
     assert_eq!(r, e);
 }
 
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vaddv() {
+    let a = u8x16::from([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 255]);
+    assert_eq!(vaddvq_u8(transmute(a)), 119);
+    assert_eq!(vaddlvq_u8(transmute(a)), 375);
+    let a = i16x4::from([1, -2, 3, -4]);
+    assert_eq!(vaddv_s16(transmute(a)), -2);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vmaxv_f32() {
+    let a = f32x4::from([1., -2., 3., -4.]);
+    assert_eq!(vmaxvq_f32(transmute(a)), 3.);
+    let a = f32x4::from([1., f32::NAN, 3., -4.]);
+    assert!(vmaxvq_f32(transmute(a)).is_nan());
+    assert_eq!(vminnmvq_f32(transmute(a)), -4.);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vhadd() {
+    let a = u8x16::from([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 255]);
+    let b = u8x16::from([1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 255]);
+    let e = u8x16::from([1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 255]);
+    let r: u8x16 = transmute(vhaddq_u8(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+
+    let a = i16x4::from([1, -2, 3, i16::MAX]);
+    let b = i16x4::from([2, -3, 4, i16::MAX]);
+    let e = i16x4::from([2, -2, 4, i16::MAX]);
+    let r: i16x4 = transmute(vrhadd_s16(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vabd() {
+    let a = i8x8::from([1, -2, 3, -4, 5, 6, 7, -128]);
+    let b = i8x8::from([0, 3, 2, 5, 4, 7, 6, 127]);
+    let e = i8x8::from([1, 5, 1, 9, 1, 1, 1, -1]);
+    let r: i8x8 = transmute(vabd_s8(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+
+    let a = f32x4::from([1., -2., 3., -4.]);
+    let b = f32x4::from([2., 3., -4., -5.]);
+    let e = f32x4::from([1., 5., 7., 1.]);
+    let r: f32x4 = transmute(vabdq_f32(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vmull() {
+    let a = u8x8::from([1, 2, 3, 4, 5, 6, 7, 255]);
+    let b = u8x8::from([2, 3, 4, 5, 6, 7, 8, 255]);
+    let e = u16x8::from([2, 6, 12, 20, 30, 42, 56, 65025]);
+    let r: u16x8 = transmute(vmull_u8(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+
+    let a = i16x4::from([1, -2, i16::MIN, i16::MAX]);
+    let b = i16x4::from([-3, -4, i16::MIN, i16::MAX]);
+    let e = i32x4::from([-3, 8, 1 << 30, 0x3FFF0001]);
+    let r: i32x4 = transmute(vmull_s16(transmute(a), transmute(b)));
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vqtbl1() {
+    let t = u8x16::from([10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25]);
+    let idx = u8x8::from([0, 15, 16, 3, 255, 1, 2, 7]);
+    let e = u8x8::from([10, 25, 0, 13, 0, 11, 12, 17]);
+    let r: u8x8 = transmute(vqtbl1_u8(transmute(t), transmute(idx)));
+    assert_eq!(r, e);
+
+    let a = u8x16::splat(42);
+    let idx = u8x16::from([0, 15, 16, 3, 255, 1, 2, 7, 8, 9, 10, 11, 12, 13, 14, 128]);
+    let e = u8x16::from([10, 25, 42, 13, 42, 11, 12, 17, 18, 19, 20, 21, 22, 23, 24, 42]);
+    let r: u8x16 = transmute(vqtbx1q_u8(transmute(a), transmute(t), transmute(idx)));
+    assert_eq!(r, e);
+}
+
 #[cfg(target_arch = "aarch64")]
 fn main() {
     unsafe {
@@ -227,6 +305,13 @@ fn main() {
 
         test_vqsub_u8();
         test_vqadd_u8();
+
+        test_vaddv();
+        test_vmaxv_f32();
+        test_vhadd();
+        test_vabd();
+        test_vmull();
+        test_vqtbl1();
     }
 }
 
@@ -0,0 +1,43 @@
+// Checks that constant offsets of nested field and array projections are folded into the offset of
+// the load or store. The build system counts the address computations in the clif ir of `read` and
+// `write`.
+
+#![allow(dead_code)]
+
+#[derive(Copy, Clone)]
+struct Inner {
+    a: u32,
+    b: [u64; 4],
+}
+
+struct Outer {
+    x: u8,
+    inner: Inner,
+    arr: [Inner; 3],
+}
+
+#[inline(never)]
+fn read(o: &Outer) -> u64 {
+    o.arr[2].b[3]
+}
+
+#[inline(never)]
+fn write(o: &mut Outer, v: u64) {
+    o.arr[1].b[2] = v;
+}
+
+#[inline(never)]
+fn read_dynamic(o: &Outer, i: usize) -> u64 {
+    o.arr[i].b[3]
+}
+
+fn main() {
+    let inner = Inner { a: 1, b: [2, 3, 4, 5] };
+    let mut o = Outer { x: 6, inner, arr: [inner; 3] };
+    o.arr[2].b[3] = 7;
+    write(&mut o, 8);
+    assert_eq!(read(&o), 7);
+    assert_eq!(o.arr[1].b, [2, 3, 8, 5]);
+    assert_eq!(read_dynamic(&o, 2), 7);
+    assert_eq!(read_dynamic(&o, 0), 5);
+}
@@ -1,8 +1,11 @@
 #![feature(
+    abi_unadjusted,
     core_intrinsics,
     coroutines,
     coroutine_trait,
     is_sorted,
+    ptr_sub_ptr,
+    platform_intrinsics,
     repr_simd,
     tuple_trait,
     unboxed_closures
@@ -102,6 +105,19 @@ fn main() {
     assert_eq!(black_box(f32::NAN) as i128, 0);
     assert_eq!(black_box(f32::NAN) as u128, 0);
 
+    // Values which don't fit in 64 bits or need rounding
+    assert_eq!(black_box(u128::MAX) as f64, 340282366920938463463374607431768211455.0);
+    assert_eq!(black_box(u128::MAX) as f32, f32::INFINITY);
+    assert_eq!(black_box(i128::MIN) as f64, -170141183460469231731687303715884105728.0);
+    assert_eq!(black_box(i128::MIN) as f32, -170141183460469231731687303715884105728.0);
+    assert_eq!(black_box((1u128 << 100) + 1) as f64, 1267650600228229401496703205376.0);
+    assert_eq!(black_box(-(1i128 << 70) - 1) as f32, -1180591620717411303424.0);
+    assert_eq!(black_box(1267650600228229401496703205376.0f64) as u128, 1 << 100);
+    assert_eq!(black_box(-1180591620717411303424.0f32) as i128, -(1 << 70));
+    assert_eq!(black_box(1.5e30f64) as u128, 1499999999999999889089448902656);
+    assert_eq!("1.5e30".parse::<f64>().unwrap() as u128, 1499999999999999889089448902656);
+    assert_eq!(format!("{}", black_box(u128::MAX)), "340282366920938463463374607431768211455");
+
     // Test signed 128bit comparing
     let max = usize::MAX as i128;
     if 100i128 < 0i128 || 100i128 > max {
@@ -110,6 +126,61 @@ fn main() {
 
     test_checked_mul();
 
+    test_checked_add_sub_128();
+
+    test_saturating();
+
+    test_bit_manipulation();
+
+    test_float_math();
+
+    test_mem_intrinsics();
+
+    test_volatile();
+
+    test_offset_from();
+
+    test_unchecked_arith();
+
+    test_float_to_int_casts();
+
+    test_discriminants();
+
+    test_catch_unwind_without_panic();
+
+    test_black_box();
+
+    test_raw_eq_compare_bytes();
+
+    test_size_and_align_of_val();
+
+    test_align_offset();
+
+    test_widening_mul();
+
+    test_ord();
+
+    test_hint_intrinsics(black_box(3));
+
+    test_platform_intrinsics();
+    test_gather_scatter();
+
+    test_unadjusted_abi();
+
+    test_atomics();
+
+    test_dense_switch();
+
+    test_narrowed_compare();
+
+    test_static_references();
+
+    test_runtime_calls();
+
+    test_system_abi();
+
+    test_prefetch();
+
     let _a = 1u32 << 2u8;
 
     let empty: [i32; 0] = [];
@@ -122,6 +193,10 @@ fn main() {
         test_simd();
     }
 
+    // rdtsc and cpuid are implemented using inline asm, which isn't supported in JIT mode.
+    #[cfg(all(target_arch = "x86_64", not(jit)))]
+    test_timestamp_and_cpuid();
+
     Box::pin(move |mut _task_context| {
         yield ();
     })
@@ -197,6 +272,29 @@ fn rust_call_abi() {
 #[allow(improper_ctypes_definitions)]
 extern "C" fn foo(_a: I64X2) {}
 
+#[cfg(all(target_arch = "x86_64", not(jit)))]
+fn test_timestamp_and_cpuid() {
+    let start = unsafe { _rdtsc() };
+    for _ in 0..100 {
+        std::hint::spin_loop();
+    }
+    assert!(unsafe { _rdtsc() } > start);
+
+    let cpuid = unsafe { __cpuid(0) };
+    let mut vendor = [0u8; 12];
+    vendor[..4].copy_from_slice(&cpuid.ebx.to_le_bytes());
+    vendor[4..8].copy_from_slice(&cpuid.edx.to_le_bytes());
+    vendor[8..].copy_from_slice(&cpuid.ecx.to_le_bytes());
+    assert!(cpuid.eax >= 1);
+    assert!(vendor.iter().all(|b| b.is_ascii_graphic()), "{:?}", vendor);
+
+    if unsafe { __cpuid(0x8000_0001) }.edx & (1 << 27) != 0 {
+        let mut aux = 0;
+        let start = unsafe { __rdtscp(&mut aux) };
+        assert!(unsafe { __rdtscp(&mut aux) } > start);
+    }
+}
+
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "sse2")]
 unsafe fn test_simd() {
@@ -222,6 +320,10 @@ unsafe fn test_simd() {
     test_mm256_movemask_epi8();
     test_mm_add_epi8();
     test_mm_add_pd();
+    test_mm_packs_epi16();
+    test_mm_sll_epi16();
+    test_mm_min_max_pd();
+    test_mm_comi_sd();
     test_mm_cvtepi8_epi16();
     test_mm_cvtsi128_si64();
 
@@ -312,6 +414,85 @@ unsafe fn test_mm_add_pd() {
     assert_eq_m128d(r, _mm_setr_pd(6.0, 12.0));
 }
 
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn test_mm_packs_epi16() {
+    let a = _mm_setr_epi16(0x80, -0x81, 0, 0, 0, 0, 0, 0);
+    let b = _mm_setr_epi16(0, 0, 0, 0, 0, 0, -0x81, 0x80);
+    let r = _mm_packs_epi16(a, b);
+    #[rustfmt::skip]
+    let e = _mm_setr_epi8(0x7F, -0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -0x80, 0x7F);
+    assert_eq_m128i(r, e);
+
+    let a = _mm_setr_epi32(0x8000, -0x8001, 0, 1);
+    let b = _mm_setr_epi32(-1, 2, -0x8001, 0x8000);
+    let r = _mm_packs_epi32(a, b);
+    let e = _mm_setr_epi16(0x7FFF, -0x8000, 0, 1, -1, 2, -0x8000, 0x7FFF);
+    assert_eq_m128i(r, e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn test_mm_sll_epi16() {
+    let a = _mm_setr_epi16(0xCC, -0xCC, 0xDD, -0xDD, 0xEE, -0xEE, 0xFF, -0xFF);
+    let r = _mm_sll_epi16(a, _mm_setr_epi16(4, 0, 0, 0, 0, 0, 0, 0));
+    let e = _mm_setr_epi16(0xCC0, -0xCC0, 0xDD0, -0xDD0, 0xEE0, -0xEE0, 0xFF0, -0xFF0);
+    assert_eq_m128i(r, e);
+    let r = _mm_srl_epi32(_mm_set1_epi32(-1), _mm_setr_epi32(4, 0, 0, 0));
+    assert_eq_m128i(r, _mm_set1_epi32(0x0FFF_FFFF));
+    let r = _mm_sra_epi32(_mm_set1_epi32(-64), _mm_setr_epi32(4, 0, 0, 0));
+    assert_eq_m128i(r, _mm_set1_epi32(-4));
+
+    // Shifting by at least the lane width shifts all bits out.
+    let r = _mm_sll_epi64(_mm_set1_epi64x(1), _mm_setr_epi32(64, 0, 0, 0));
+    assert_eq_m128i(r, _mm_setzero_si128());
+    let r = _mm_srl_epi16(a, _mm_setr_epi32(0, 1, 0, 0));
+    assert_eq_m128i(r, _mm_setzero_si128());
+    let r = _mm_sra_epi32(_mm_set1_epi32(-64), _mm_setr_epi32(32, 0, 0, 0));
+    assert_eq_m128i(r, _mm_set1_epi32(-1));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn test_mm_min_max_pd() {
+    let a = _mm_setr_pd(-1.0, 5.0);
+    let b = _mm_setr_pd(-100.0, 20.0);
+    assert_eq_m128d(_mm_min_pd(a, b), _mm_setr_pd(-100.0, 5.0));
+    assert_eq_m128d(_mm_max_pd(a, b), _mm_setr_pd(-1.0, 20.0));
+    assert_eq_m128d(_mm_min_sd(a, b), _mm_setr_pd(-100.0, 5.0));
+    assert_eq_m128d(_mm_max_sd(a, b), _mm_setr_pd(-1.0, 5.0));
+
+    // The second operand is returned if either operand is NaN.
+    let nan = _mm_setr_pd(f64::NAN, f64::NAN);
+    assert_eq_m128d(_mm_min_pd(nan, a), a);
+    assert_eq_m128d(_mm_max_pd(nan, a), a);
+
+    let a = _mm_setr_pd(4.0, 9.0);
+    assert_eq_m128d(_mm_sqrt_pd(a), _mm_setr_pd(2.0, 3.0));
+    assert_eq_m128d(_mm_sqrt_sd(_mm_setr_pd(1.0, 2.0), a), _mm_setr_pd(2.0, 2.0));
+
+    _mm_lfence();
+    _mm_mfence();
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn test_mm_comi_sd() {
+    let a = _mm_setr_pd(1.0, 2.0);
+    let b = _mm_setr_pd(1.0, 3.0);
+    let nan = _mm_setr_pd(f64::NAN, 2.0);
+    assert_eq!(_mm_comieq_sd(a, b), 1);
+    assert_eq!(_mm_comineq_sd(a, b), 0);
+    assert_eq!(_mm_comilt_sd(a, b), 0);
+    assert_eq!(_mm_comile_sd(a, b), 1);
+    assert_eq!(_mm_comigt_sd(a, b), 0);
+    assert_eq!(_mm_comige_sd(a, b), 1);
+    assert_eq!(_mm_ucomieq_sd(a, nan), 0);
+    assert_eq!(_mm_ucomineq_sd(a, nan), 1);
+    assert_eq!(_mm_ucomilt_sd(nan, a), 0);
+    assert_eq!(_mm_ucomige_sd(nan, a), 0);
+}
+
 #[cfg(target_arch = "x86_64")]
 fn assert_eq_m128i(x: std::arch::x86_64::__m128i, y: std::arch::x86_64::__m128i) {
     unsafe {
@@ -453,6 +634,951 @@ fn test_checked_mul() {
     assert_eq!((-1i64).checked_mul(i64::MIN + 1), Some(i64::MAX));
     assert_eq!(1i64.checked_mul(i64::MIN), Some(i64::MIN));
     assert_eq!(i64::MIN.checked_mul(i64::MIN), None);
+
+    assert_eq!(black_box(1u128 << 64).checked_mul(black_box(u64::MAX as u128)), Some(u128::MAX << 64));
+    assert_eq!(black_box(1u128 << 64).checked_mul(black_box(1 << 64)), None);
+    assert_eq!(black_box(u128::MAX).overflowing_mul(black_box(2)), (u128::MAX - 1, true));
+    assert_eq!(black_box(-1i128).checked_mul(black_box(i128::MIN + 1)), Some(i128::MAX));
+    assert_eq!(black_box(-1i128).checked_mul(black_box(i128::MIN)), None);
+    assert_eq!(black_box(1i128 << 63).checked_mul(black_box(-1 << 64)), Some(i128::MIN));
+    assert_eq!(black_box(i128::MAX).overflowing_mul(black_box(2)), (-2, true));
+}
+
+fn test_checked_add_sub_128() {
+    assert_eq!(black_box(u128::MAX - 1).checked_add(1), Some(u128::MAX));
+    assert_eq!(black_box(u128::MAX).checked_add(black_box(1)), None);
+    assert_eq!(black_box(u64::MAX as u128).checked_add(1), Some(1 << 64));
+    assert_eq!(black_box(1u128 << 64).checked_sub(1), Some(u64::MAX as u128));
+    assert_eq!(black_box(0u128).checked_sub(black_box(1)), None);
+
+    assert_eq!(black_box(i128::MAX - 1).checked_add(1), Some(i128::MAX));
+    assert_eq!(black_box(i128::MAX).checked_add(black_box(1)), None);
+    assert_eq!(black_box(i128::MIN).checked_add(black_box(-1)), None);
+    assert_eq!(black_box(-1i128).checked_add(black_box(i128::MIN + 1)), Some(i128::MIN));
+    assert_eq!(black_box(i128::MIN).checked_sub(black_box(1)), None);
+    assert_eq!(black_box(i128::MAX).checked_sub(black_box(-1)), None);
+    assert_eq!(black_box(-1i128).checked_sub(black_box(i128::MAX)), Some(i128::MIN));
+    assert_eq!(black_box(i64::MIN as i128).checked_sub(1), Some(i64::MIN as i128 - 1));
+}
+
+macro_rules! test_saturating_int {
+    ($uint:ty, $int:ty) => {{
+        assert_eq!(black_box(<$uint>::MAX - 1).saturating_add(black_box(1)), <$uint>::MAX);
+        assert_eq!(black_box(<$uint>::MAX).saturating_add(black_box(1)), <$uint>::MAX);
+        assert_eq!(black_box(<$uint>::MIN + 1).saturating_sub(black_box(1)), <$uint>::MIN);
+        assert_eq!(black_box(<$uint>::MIN).saturating_sub(black_box(1)), <$uint>::MIN);
+
+        assert_eq!(black_box(<$int>::MAX).saturating_add(black_box(-1)), <$int>::MAX - 1);
+        assert_eq!(black_box(<$int>::MAX).saturating_add(black_box(1)), <$int>::MAX);
+        assert_eq!(black_box(<$int>::MIN).saturating_add(black_box(-1)), <$int>::MIN);
+        assert_eq!(black_box(<$int>::MIN).saturating_sub(black_box(1)), <$int>::MIN);
+        assert_eq!(black_box(<$int>::MAX).saturating_sub(black_box(-1)), <$int>::MAX);
+        assert_eq!(black_box(-1 as $int).saturating_sub(black_box(<$int>::MAX)), <$int>::MIN);
+    }};
+}
+
+fn test_saturating() {
+    test_saturating_int!(u8, i8);
+    test_saturating_int!(u16, i16);
+    test_saturating_int!(u32, i32);
+    test_saturating_int!(u64, i64);
+    test_saturating_int!(u128, i128);
+    test_saturating_int!(usize, isize);
+}
+
+macro_rules! test_bit_manipulation_int {
+    ($int:ty) => {{
+        const BITS: u32 = <$int>::BITS;
+        // 0b0...01100...01
+        let x: $int = black_box(0b11 << (BITS / 2) | 1);
+
+        assert_eq!(x.count_ones(), 3);
+        assert_eq!(x.count_zeros(), BITS - 3);
+        assert_eq!(x.leading_zeros(), BITS / 2 - 2);
+        assert_eq!(x.trailing_zeros(), 0);
+        assert_eq!((x - 1).trailing_zeros(), BITS / 2);
+        assert_eq!(black_box(0 as $int).leading_zeros(), BITS);
+        assert_eq!(black_box(0 as $int).trailing_zeros(), BITS);
+        assert_eq!(black_box(!0 as $int).count_ones(), BITS);
+
+        assert_eq!(x.swap_bytes().swap_bytes(), x);
+        assert_eq!(black_box(0xab as $int).swap_bytes(), (0xab as $int) << (BITS - 8));
+        assert_eq!(x.reverse_bits(), 0b11 << (BITS / 2 - 2) | 1 << (BITS - 1));
+        assert_eq!(black_box(1 as $int).reverse_bits(), 1 << (BITS - 1));
+
+        assert_eq!(x.rotate_left(black_box(BITS / 2)), 0b11 | 1 << (BITS / 2));
+        assert_eq!(x.rotate_right(black_box(1)), 0b11 << (BITS / 2 - 1) | 1 << (BITS - 1));
+        // The rotate amount is taken modulo the bit width.
+        assert_eq!(x.rotate_left(black_box(BITS)), x);
+        assert_eq!(x.rotate_right(black_box(BITS + 1)), x.rotate_right(1));
+    }};
+}
+
+fn test_bit_manipulation() {
+    test_bit_manipulation_int!(u8);
+    test_bit_manipulation_int!(u16);
+    test_bit_manipulation_int!(u32);
+    test_bit_manipulation_int!(u64);
+    test_bit_manipulation_int!(u128);
+    test_bit_manipulation_int!(i16);
+    test_bit_manipulation_int!(i128);
+}
+
+fn test_float_math() {
+    use std::f64::consts::{E, PI};
+
+    assert_eq!(black_box(0f64).sin(), 0.);
+    assert_eq!(black_box(PI / 2.).sin(), 1.);
+    assert_eq!(black_box(0f64).cos(), 1.);
+    assert_eq!(black_box(1f64).exp(), E);
+    assert_eq!(black_box(E).ln(), 1.);
+    assert_eq!(black_box(8f64).log2(), 3.);
+    assert_eq!(black_box(1000f64).log10(), 3.);
+    assert_eq!(black_box(2f64).powf(black_box(10.)), 1024.);
+    assert_eq!(black_box(2f64).powi(black_box(-2)), 0.25);
+
+    assert!((black_box(1f32).sin() - 0.84147096).abs() < 1e-6);
+    assert!((black_box(1f32).cos() - 0.5403023).abs() < 1e-6);
+    assert_eq!(black_box(0f32).exp(), 1.);
+    assert_eq!(black_box(1f32).ln(), 0.);
+    assert_eq!(black_box(0.25f32).log2(), -2.);
+    assert_eq!(black_box(100f32).log10(), 2.);
+    assert_eq!(black_box(3f32).powf(black_box(2.)), 9.);
+
+    assert_eq!(black_box(2.5f32).floor(), 2.);
+    assert_eq!(black_box(-2.5f64).floor(), -3.);
+    assert_eq!(black_box(2.5f32).ceil(), 3.);
+    assert_eq!(black_box(-2.5f64).ceil(), -2.);
+    assert_eq!(black_box(2.5f32).trunc(), 2.);
+    assert_eq!(black_box(-2.5f64).trunc(), -2.);
+    assert_eq!(black_box(2.5f32).round(), 3.);
+    assert_eq!(black_box(-2.5f64).round(), -3.);
+    assert_eq!(black_box(2.25f32).sqrt(), 1.5);
+    assert_eq!(black_box(2f64).sqrt(), std::f64::consts::SQRT_2);
+    assert!(black_box(-1f64).sqrt().is_nan());
+
+    // NaN is only returned when both arguments are NaN.
+    assert_eq!(black_box(1f32).min(black_box(-1.)), -1.);
+    assert_eq!(black_box(1f64).max(black_box(-1.)), 1.);
+    assert_eq!(black_box(f32::NAN).min(black_box(2.)), 2.);
+    assert_eq!(black_box(2f32).min(black_box(f32::NAN)), 2.);
+    assert_eq!(black_box(f64::NAN).max(black_box(2.)), 2.);
+    assert_eq!(black_box(2f64).max(black_box(f64::NAN)), 2.);
+    assert!(black_box(f64::NAN).min(black_box(f64::NAN)).is_nan());
+    assert!(black_box(f32::NAN).max(black_box(f32::NAN)).is_nan());
+    assert_eq!(black_box(f64::NEG_INFINITY).max(black_box(f64::MIN)), f64::MIN);
+
+    // The intermediate product must not be rounded. Otherwise the results would be zero.
+    let a = black_box(1. + f64::EPSILON);
+    assert_eq!(a.mul_add(a, -(1. + 2. * f64::EPSILON)), f64::EPSILON * f64::EPSILON);
+    let a = black_box(1. + f32::EPSILON);
+    assert_eq!(a.mul_add(a, -(1. + 2. * f32::EPSILON)), f32::EPSILON * f32::EPSILON);
+
+    // Rounds half way cases to even using the default rounding mode.
+    unsafe {
+        assert_eq!(std::intrinsics::nearbyintf32(black_box(2.5)), 2.);
+        assert_eq!(std::intrinsics::nearbyintf64(black_box(-3.5)), -4.);
+        assert_eq!(std::intrinsics::rintf32(black_box(-2.5)), -2.);
+        assert_eq!(std::intrinsics::rintf64(black_box(3.5)), 4.);
+        assert_eq!(std::intrinsics::roundevenf32(black_box(0.5)), 0.);
+        assert_eq!(std::intrinsics::roundevenf64(black_box(1.5)), 2.);
+
+        assert_eq!(std::intrinsics::fadd_fast(black_box(1.5f32), black_box(2.)), 3.5);
+        assert_eq!(std::intrinsics::fsub_fast(black_box(1.5f64), black_box(2.)), -0.5);
+        assert_eq!(std::intrinsics::fmul_fast(black_box(1.5f32), black_box(2.)), 3.);
+        assert_eq!(std::intrinsics::fdiv_fast(black_box(1.5f64), black_box(2.)), 0.75);
+        assert_eq!(std::intrinsics::frem_fast(black_box(5.5f32), black_box(2.)), 1.5);
+    }
+}
+
+fn test_mem_intrinsics() {
+    use std::ptr;
+
+    // Small copies of a constant size are emitted inline, larger ones call memcpy or memmove.
+    let mut a = black_box([1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+    unsafe {
+        ptr::copy(a.as_ptr(), a.as_mut_ptr().add(1), 3);
+        assert_eq!(a[..5], [1, 1, 2, 3, 5]);
+        ptr::copy(a.as_ptr().add(8), a.as_mut_ptr().add(6), 8);
+        assert_eq!(a, [1, 1, 2, 3, 5, 6, 9, 10, 11, 12, 13, 14, 15, 16, 15, 16]);
+        ptr::copy(a.as_ptr().add(2), a.as_mut_ptr(), black_box(3));
+        assert_eq!(a[..5], [2, 3, 5, 3, 5]);
+    }
+
+    let src = black_box([1u16, 2, 3, 4, 5]);
+    let mut dst = [0u16; 5];
+    unsafe {
+        ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr(), 2);
+        assert_eq!(dst, [1, 2, 0, 0, 0]);
+        ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr(), 5);
+        assert_eq!(dst, src);
+    }
+
+    let mut big = black_box([0u64; 8]);
+    unsafe {
+        ptr::write_bytes(big.as_mut_ptr().add(1), 0xab, 2);
+        assert_eq!(big[..4], [0, 0xabab_abab_abab_abab, 0xabab_abab_abab_abab, 0]);
+        ptr::write_bytes(big.as_mut_ptr(), 0xcd, 8);
+        assert_eq!(big, [0xcdcd_cdcd_cdcd_cdcd; 8]);
+        ptr::write_bytes(big.as_mut_ptr(), black_box(0), black_box(8));
+        assert_eq!(big, [0; 8]);
+    }
+}
+
+fn test_volatile() {
+    use std::intrinsics::{unaligned_volatile_load, unaligned_volatile_store};
+    use std::ptr;
+
+    let mut x = black_box(1u32);
+    unsafe {
+        let p = ptr::addr_of_mut!(x);
+        assert_eq!(ptr::read_volatile(p), 1);
+        ptr::write_volatile(p, 2);
+        assert_eq!(ptr::read_volatile(p) + ptr::read_volatile(p), 4);
+    }
+
+    let mut f = black_box(1.5f64);
+    let mut big = black_box(u128::MAX);
+    let mut pair = black_box((1u8, 2u64));
+    unsafe {
+        ptr::write_volatile(&mut f, ptr::read_volatile(&f) * 2.0);
+        assert_eq!(f, 3.0);
+        ptr::write_volatile(&mut big, ptr::read_volatile(&big) - 1);
+        assert_eq!(big, u128::MAX - 1);
+        ptr::write_volatile(&mut pair, (3, 4));
+        assert_eq!(ptr::read_volatile(&pair), (3, 4));
+    }
+
+    let mut bytes = black_box([0u8; 16]);
+    unsafe {
+        let p = bytes.as_mut_ptr().add(1);
+        unaligned_volatile_store(p as *mut u64, 0x0807_0605_0403_0201);
+        assert_eq!(bytes[..10], [0, 1, 2, 3, 4, 5, 6, 7, 8, 0]);
+        assert_eq!(unaligned_volatile_load(p as *const u32), 0x0403_0201);
+        unaligned_volatile_store(p.add(4) as *mut (u16, u32), (0xaaaa, 0xbbbb_bbbb));
+        assert_eq!(unaligned_volatile_load(p.add(4) as *const (u16, u32)), (0xaaaa, 0xbbbb_bbbb));
+    }
+}
+
+fn test_offset_from() {
+    fn check<T: Default + Copy>() {
+        let arr = [T::default(); 10];
+        let start = black_box(arr.as_ptr());
+        let end = black_box(unsafe { start.add(7) });
+        unsafe {
+            assert_eq!(end.offset_from(start), 7);
+            assert_eq!(start.offset_from(end), -7);
+            assert_eq!(end.sub_ptr(start), 7);
+        }
+    }
+
+    check::<u8>();
+    check::<u32>();
+    check::<[u8; 3]>();
+    check::<[u16; 6]>();
+    check::<(u64, u8)>();
+    check::<[[u8; 25]; 30]>();
+    assert_eq!([1u8, 2, 3, 4, 5].iter().skip(1).len(), 4);
+    assert_eq!([[0u8; 7]; 9].iter().len(), 9);
+}
+
+fn test_unchecked_arith() {
+    use std::intrinsics::{
+        exact_div, unchecked_add, unchecked_mul, unchecked_shl, unchecked_shr, unchecked_sub,
+    };
+
+    unsafe {
+        assert_eq!(exact_div(black_box(42u32), 6), 7);
+        assert_eq!(exact_div(black_box(-42i32), 6), -7);
+        assert_eq!(exact_div(black_box(-120i8), 8), -15);
+        assert_eq!(exact_div(black_box(u64::MAX), 5), u64::MAX / 5);
+        assert_eq!(exact_div(black_box(i64::MIN), 1 << 20), i64::MIN >> 20);
+        assert_eq!(exact_div(black_box(-42i32), -6), 7);
+        assert_eq!(exact_div(black_box(3 * (1u128 << 100)), 3), 1 << 100);
+        assert_eq!(exact_div(black_box(91usize), black_box(13)), 7);
+
+        assert_eq!(unchecked_add(black_box(i32::MAX - 1), 1), i32::MAX);
+        assert_eq!(unchecked_sub(black_box(5u8), 5), 0);
+        assert_eq!(unchecked_mul(black_box(-3i64), 7), -21);
+        assert_eq!(unchecked_shl(black_box(1u16), 15), 0x8000);
+        assert_eq!(unchecked_shr(black_box(-256i32), 4), -16);
+        assert_eq!(unchecked_shr(black_box(u128::MAX), black_box(127)), 1);
+    }
+}
+
+fn test_float_to_int_casts() {
+    macro_rules! test_saturating_cast {
+        ($float:ident => $($int:ident),*) => {$(
+            assert_eq!(black_box($float::NAN) as $int, 0);
+            assert_eq!(black_box(-$float::NAN) as $int, 0);
+            assert_eq!(black_box($float::INFINITY) as $int, $int::MAX);
+            assert_eq!(black_box($float::NEG_INFINITY) as $int, $int::MIN);
+            assert_eq!(black_box($float::MIN) as $int, $int::MIN);
+            assert_eq!(black_box(-0.0 as $float) as $int, 0);
+            assert_eq!(black_box(-0.99 as $float) as $int, 0);
+            assert_eq!(black_box(42.99 as $float) as $int, 42);
+            assert_eq!(black_box($int::MIN as $float) as $int, $int::MIN);
+            assert_eq!(black_box($float::MIN_POSITIVE) as $int, 0);
+            assert_eq!(unsafe { black_box(100.5 as $float).to_int_unchecked::<$int>() }, 100);
+        )*};
+    }
+    test_saturating_cast!(f32 => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+    test_saturating_cast!(f64 => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+    // Just outside of the range of the integer type
+    assert_eq!(black_box(255.9f32) as u8, 255);
+    assert_eq!(black_box(256.0f32) as u8, 255);
+    assert_eq!(black_box(-1.0f64) as u8, 0);
+    assert_eq!(black_box(127.9f64) as i8, 127);
+    assert_eq!(black_box(-128.9f32) as i8, -128);
+    assert_eq!(black_box(-129.0f32) as i8, -128);
+    assert_eq!(black_box(65536.0f32) as u16, u16::MAX);
+    assert_eq!(black_box(-32769.0f64) as i16, i16::MIN);
+    assert_eq!(black_box(4294967296.0f64) as u32, u32::MAX);
+    assert_eq!(black_box(4294967295.0f64) as u32, u32::MAX);
+    assert_eq!(black_box(-2147483649.0f64) as i32, i32::MIN);
+    assert_eq!(black_box(18446744073709551616.0f64) as u64, u64::MAX);
+    assert_eq!(black_box(9223372036854775807.0f64) as i64, i64::MAX);
+    assert_eq!(black_box(-170141183460469231731687303715884105729.0f64) as i128, i128::MIN);
+    assert_eq!(black_box(1e39f64) as u128, u128::MAX);
+    assert_eq!(black_box(f64::MAX) as u128, u128::MAX);
+    assert_eq!(black_box(f32::MAX) as i128, i128::MAX);
+    assert_eq!(black_box(f32::MAX) as u128, 340282346638528859811704183484516925440);
+    assert_eq!(unsafe { black_box(-128.0f32).to_int_unchecked::<i8>() }, -128);
+    assert_eq!(unsafe { black_box(65535.0f64).to_int_unchecked::<u16>() }, 65535);
+}
+
+fn test_discriminants() {
+    use std::intrinsics::discriminant_value;
+    use std::mem::discriminant;
+    use std::num::{NonZeroU128, NonZeroU8};
+
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    enum Niche {
+        A(bool),
+        B,
+        C,
+        D,
+    }
+
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    #[repr(i8)]
+    enum Negative {
+        Min = -128,
+        MinusOne = -1,
+        Zero = 0,
+        Max = 127,
+    }
+
+    fn hash<T: std::hash::Hash>(val: T) -> u64 {
+        use std::hash::{BuildHasher, BuildHasherDefault};
+        BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default().hash_one(val)
+    }
+
+    let niches = black_box([Niche::A(true), Niche::A(false), Niche::B, Niche::C, Niche::D]);
+    for (i, a) in niches.iter().enumerate() {
+        assert_eq!(discriminant_value(a), i.saturating_sub(1) as isize);
+        for (j, b) in niches.iter().enumerate() {
+            assert_eq!(a == b, i == j);
+            assert_eq!(discriminant(a) == discriminant(b), i.max(1) == j.max(1));
+            assert_eq!(hash(a) == hash(b), i == j);
+        }
+    }
+
+    assert_eq!(discriminant_value(&black_box(Some(Some(false)))), 1);
+    assert_eq!(discriminant_value(&black_box(Some(None::<bool>))), 1);
+    assert_eq!(discriminant_value(&black_box(None::<Option<bool>>)), 0);
+    assert_eq!(discriminant_value(&black_box(Some('x'))), 1);
+    assert_eq!(discriminant_value(&black_box(None::<char>)), 0);
+    assert_eq!(discriminant_value(&black_box(NonZeroU8::new(255))), 1);
+    assert_eq!(discriminant_value(&black_box(NonZeroU8::new(0))), 0);
+    assert_eq!(discriminant_value(&black_box(NonZeroU128::new(u128::MAX))), 1);
+    assert_eq!(discriminant_value(&black_box(NonZeroU128::new(0))), 0);
+    assert_ne!(black_box(Some(Niche::D)), black_box(None));
+    assert_eq!(black_box(Some(Niche::D)), Some(Niche::D));
+
+    for (val, discr) in [
+        (Negative::Min, -128),
+        (Negative::MinusOne, -1),
+        (Negative::Zero, 0),
+        (Negative::Max, 127),
+    ] {
+        assert_eq!(discriminant_value(&black_box(val)), discr);
+        assert_eq!(black_box(val) as i8, discr);
+    }
+}
+
+fn test_catch_unwind_without_panic() {
+    let mut called = false;
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        called = true;
+        black_box(42)
+    }));
+    assert!(called);
+    assert_eq!(res.ok(), Some(42));
+}
+
+fn test_black_box() {
+    assert_eq!(black_box(()), ());
+    assert_eq!(black_box(true), true);
+    assert_eq!(black_box(-1i8), -1);
+    assert_eq!(black_box(u128::MAX - 1), u128::MAX - 1);
+    assert_eq!(black_box(1.5f32), 1.5);
+    assert_eq!(black_box((1u8, 2u64)), (1, 2));
+    assert_eq!(black_box([3u16; 33]), [3; 33]);
+    assert_eq!(black_box("str"), "str");
+
+    let mut x = 1;
+    *black_box(&mut x) += 1;
+    assert_eq!(x, 2);
+}
+
+fn test_raw_eq_compare_bytes() {
+    use std::cmp::Ordering;
+    use std::intrinsics::{compare_bytes, raw_eq};
+
+    macro_rules! test_raw_eq {
+        ($($len:literal),*) => {$(
+            let a = black_box([7u8; $len]);
+            let mut b = a;
+            assert!(unsafe { raw_eq(&a, &b) });
+            assert!(a == b);
+            if $len > 0 {
+                b[$len - 1] = 8;
+                assert!(unsafe { !raw_eq(&a, &b) });
+                assert!(a != b);
+            }
+        )*};
+    }
+    test_raw_eq!(0, 1, 2, 3, 4, 7, 8, 16, 17, 100);
+    assert!(unsafe { raw_eq(&black_box([1u32, 2]), &[1, 2]) });
+    assert!(unsafe { !raw_eq(&black_box(1u128 << 100), &(1 << 99)) });
+
+    let a = black_box(*b"hello world");
+    unsafe {
+        assert_eq!(compare_bytes(a.as_ptr(), b"hello".as_ptr(), 5), 0);
+        assert!(compare_bytes(a.as_ptr(), b"help".as_ptr(), 4) < 0);
+        assert!(compare_bytes(b"\xff".as_ptr(), a.as_ptr(), 1) > 0);
+        assert_eq!(compare_bytes(a.as_ptr(), b"".as_ptr(), black_box(0)), 0);
+    }
+    assert_eq!(black_box("abc").cmp("abd"), Ordering::Less);
+    assert_eq!(black_box(&[2u8, 0][..]).cmp(&[1u8, 255, 255][..]), Ordering::Greater);
+    assert_eq!(black_box(&b"abc"[..]).cmp(&b"abc"[..]), Ordering::Equal);
+}
+
+fn test_size_and_align_of_val() {
+    use std::fmt::Debug;
+    use std::mem::{align_of_val, size_of_val};
+
+    struct SliceTail<T: ?Sized> {
+        _a: u8,
+        _b: T,
+    }
+
+    #[repr(align(32))]
+    #[derive(Debug)]
+    struct Aligned(u8);
+
+    let slice: &[u16] = black_box(&[1, 2, 3]);
+    assert_eq!((size_of_val(slice), align_of_val(slice)), (6, 2));
+    let s: &str = black_box("hello");
+    assert_eq!((size_of_val(s), align_of_val(s)), (5, 1));
+
+    let tail: &SliceTail<[u32]> = black_box(&SliceTail { _a: 1, _b: [1, 2, 3] });
+    assert_eq!((size_of_val(tail), align_of_val(tail)), (16, 4));
+    let nested: &SliceTail<SliceTail<[u64]>> =
+        black_box(&SliceTail { _a: 1, _b: SliceTail { _a: 2, _b: [1, 2] } });
+    assert_eq!((size_of_val(nested), align_of_val(nested)), (32, 8));
+
+    let dyn_small: &dyn Debug = black_box(&1u16);
+    assert_eq!((size_of_val(dyn_small), align_of_val(dyn_small)), (2, 2));
+    let dyn_tail: &SliceTail<dyn Debug> = black_box(&SliceTail { _a: 1, _b: Aligned(2) });
+    assert_eq!((size_of_val(dyn_tail), align_of_val(dyn_tail)), (64, 32));
+    let dyn_tail: &SliceTail<dyn Debug> = black_box(&SliceTail { _a: 1, _b: 2u8 });
+    assert_eq!((size_of_val(dyn_tail), align_of_val(dyn_tail)), (2, 1));
+
+    let boxed: Box<[u64]> = black_box(vec![1, 2, 3, 4].into_boxed_slice());
+    assert_eq!((size_of_val(&*boxed), align_of_val(&*boxed)), (32, 8));
+    let boxed: Box<dyn Debug> = black_box(Box::new(Aligned(1)));
+    assert_eq!((size_of_val(&*boxed), align_of_val(&*boxed)), (32, 32));
+    drop(boxed);
+}
+
+fn test_align_offset() {
+    #[repr(align(64))]
+    struct Aligned([u8; 256]);
+
+    let buf = black_box(Aligned([0; 256]));
+    let base = buf.0.as_ptr();
+    for i in 0..16 {
+        let p = black_box(unsafe { base.add(i) });
+        assert_eq!(p.align_offset(1), 0);
+        assert_eq!(p.align_offset(8), (8 - i % 8) % 8);
+        assert_eq!(p.align_offset(black_box(16)), (16 - i) % 16);
+        // Non power of two strides need a modular inverse to compute the offset.
+        let p3 = p as *const [u8; 3];
+        let off = p3.align_offset(4);
+        assert!(off < 4 && (i + off * 3) % 4 == 0);
+    }
+    assert_eq!((unsafe { base.add(1) } as *const u16).align_offset(2), usize::MAX);
+
+    let (prefix, middle, suffix) = unsafe { buf.0[3..67].align_to::<u64>() };
+    assert_eq!((prefix.len(), middle.len(), suffix.len()), (5, 7, 3));
+    let (prefix, middle, suffix) = unsafe { buf.0[1..].align_to::<[u16; 3]>() };
+    assert_eq!((prefix.len(), middle.len(), suffix.len()), (1, 42, 2));
+}
+
+fn test_widening_mul() {
+    fn umul(a: u64, b: u64) -> u128 {
+        a as u128 * b as u128
+    }
+    fn smul(a: i64, b: i64) -> i128 {
+        a as i128 * b as i128
+    }
+
+    assert_eq!(umul(black_box(u64::MAX), black_box(u64::MAX)), 0xffff_ffff_ffff_fffe_0000_0000_0000_0001);
+    assert_eq!(umul(black_box(1 << 63), black_box(2)), 1 << 64);
+    assert_eq!(umul(black_box(0), black_box(u64::MAX)), 0);
+    assert_eq!(smul(black_box(i64::MIN), black_box(i64::MIN)), 1 << 126);
+    assert_eq!(smul(black_box(i64::MIN), black_box(i64::MAX)), -(1 << 126) + (1 << 63));
+    assert_eq!(smul(black_box(-1), black_box(i64::MAX)), -(i64::MAX as i128));
+    assert_eq!(black_box(u32::MAX) as u128 * black_box(u32::MAX) as u128, 0xffff_fffe_0000_0001);
+    assert_eq!(black_box(-5i8) as i128 * black_box(i32::MIN) as i128, 10737418240);
+    // Sign extension into an unsigned product and zero extension into a signed product
+    assert_eq!((black_box(-1i64) as u128).wrapping_mul(black_box(2u64) as u128), u128::MAX - 1);
+    assert_eq!(black_box(u64::MAX) as i128 * black_box(-1i64) as i128, -(u64::MAX as i128));
+}
+
+fn test_ord() {
+    use std::cmp::Ordering::{self, *};
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Derived(i8, u128);
+
+    fn check<T: Ord>(values: &[T]) {
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(black_box(a).cmp(black_box(b)), i.cmp(&j));
+            }
+        }
+    }
+
+    check(&[i8::MIN, -1, 0, 1, i8::MAX]);
+    check(&[0u32, 1, u32::MAX]);
+    check(&[i64::MIN, -1, 0, i64::MAX]);
+    check(&[i128::MIN, -1, 0, 1 << 64, i128::MAX]);
+    check(&[0u128, u64::MAX as u128, 1 << 64, u128::MAX]);
+    check(&[false, true]);
+    check(&['a', 'b', char::MAX]);
+    check(&[Derived(-1, u128::MAX), Derived(0, 0), Derived(0, 1 << 100), Derived(1, 0)]);
+    assert_eq!(black_box(f64::NAN).partial_cmp(&1.0), None::<Ordering>);
+    assert_eq!(black_box(-0.0f32).partial_cmp(&0.0), Some(Equal));
+    assert_eq!(black_box(2.0f64).total_cmp(&-2.0), Greater);
+}
+
+fn test_hint_intrinsics(x: u32) {
+    use std::intrinsics::{assume, likely, unlikely};
+
+    unsafe { assume(x < 10) };
+    assert!(likely(x == 3));
+    assert!(!unlikely(x == 4));
+    let taken = if unlikely(x > 5) { 1 } else if likely(x % 2 == 1) { 2 } else { 3 };
+    assert_eq!(taken, 2);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct U32X4(u32, u32, u32, u32);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct I32X4(i32, i32, i32, i32);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct F32X4(f32, f32, f32, f32);
+
+extern "platform-intrinsic" {
+    fn simd_add<T>(x: T, y: T) -> T;
+    fn simd_shuffle<T, I, U>(x: T, y: T, idx: I) -> U;
+    fn simd_reduce_add_unordered<T, U>(x: T) -> U;
+    fn simd_mul<T>(x: T, y: T) -> T;
+    fn simd_eq<T, U>(x: T, y: T) -> U;
+    fn simd_insert<T, U>(x: T, idx: u32, val: U) -> T;
+    fn simd_extract<T, U>(x: T, idx: u32) -> U;
+    fn simd_select<M, T>(m: M, a: T, b: T) -> T;
+    fn simd_cast<T, U>(x: T) -> U;
+    fn simd_bitmask<T, U>(x: T) -> U;
+    fn simd_gather<T, P, M>(val: T, ptr: P, mask: M) -> T;
+    fn simd_scatter<T, P, M>(val: T, ptr: P, mask: M);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct PtrX4<T>(*mut T, *mut T, *mut T, *mut T);
+
+fn test_gather_scatter() {
+    let mut data = [10u32, 20, 30, 40, 50];
+    let base = data.as_mut_ptr();
+    let mask = black_box(I32X4(-1, 0, -1, -1));
+    unsafe {
+        let ptrs = PtrX4(base.add(4), base.add(0), base.add(2), base.add(1));
+        let fallback = U32X4(1, 2, 3, 4);
+        assert_eq!(simd_gather(fallback, ptrs, mask), U32X4(50, 2, 30, 20));
+
+        simd_scatter(U32X4(5, 6, 7, 8), ptrs, mask);
+    }
+    assert_eq!(data, [10, 8, 7, 40, 5]);
+}
+
+fn test_platform_intrinsics() {
+    let a = black_box(U32X4(1, 2, 3, 4));
+    let b = black_box(U32X4(10, 20, 30, u32::MAX));
+    unsafe {
+        assert_eq!(simd_add(a, b), U32X4(11, 22, 33, 3));
+        const IDX: [u32; 4] = [7, 0, 5, 2];
+        assert_eq!(simd_shuffle::<_, _, U32X4>(a, b, IDX), U32X4(u32::MAX, 1, 20, 3));
+        assert_eq!(simd_reduce_add_unordered::<_, u32>(a), 10);
+        assert_eq!(simd_mul(a, b), U32X4(10, 40, 90, u32::MAX - 3));
+        assert_eq!(simd_eq::<_, U32X4>(a, U32X4(1, 0, 3, 0)), U32X4(u32::MAX, 0, u32::MAX, 0));
+
+        assert_eq!(simd_insert(a, 1, 42u32), U32X4(1, 42, 3, 4));
+        assert_eq!(simd_extract::<_, u32>(a, 2), 3);
+        // Non-constant indices
+        assert_eq!(simd_insert(a, black_box(3), 42u32), U32X4(1, 2, 3, 42));
+        assert_eq!(simd_extract::<_, u32>(b, black_box(1)), 20);
+
+        let mask = black_box(I32X4(-1, 0, -1, 0));
+        assert_eq!(simd_select(mask, a, b), U32X4(1, 20, 3, u32::MAX));
+        assert_eq!(simd_bitmask::<_, u8>(mask), 0b0101);
+
+        let f = black_box(F32X4(-1.5, 0.5, 2.75, 1e9));
+        assert_eq!(simd_cast::<_, I32X4>(f), I32X4(-1, 0, 2, 1_000_000_000));
+        assert_eq!(simd_cast::<_, F32X4>(I32X4(-3, 0, 7, 16_777_217)), F32X4(-3.0, 0.0, 7.0, 16_777_216.0));
+        assert_eq!(simd_cast::<_, I32X4>(b), I32X4(10, 20, 30, -1));
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Mixed {
+    a: u8,
+    b: f64,
+    c: [u16; 3],
+    d: (),
+    e: U32X4,
+}
+
+// The unadjusted ABI passes every field of an aggregate as separate value.
+#[allow(improper_ctypes_definitions)]
+extern "unadjusted" fn unadjusted_callee(x: Mixed, y: (u32, u64), z: [u64; 3]) -> Mixed {
+    Mixed {
+        a: x.a + z[0] as u8,
+        b: x.b * 2.0,
+        c: [x.c[2], x.c[1], x.c[0]],
+        d: (),
+        e: U32X4(y.0, y.1 as u32, z[1] as u32, z[2] as u32),
+    }
+}
+
+fn test_unadjusted_abi() {
+    let x = Mixed { a: 1, b: 1.5, c: [1, 2, 3], d: (), e: U32X4(0, 0, 0, 0) };
+    let expected = Mixed { a: 3, b: 3.0, c: [3, 2, 1], d: (), e: U32X4(4, 5, 6, 7) };
+    assert_eq!(unadjusted_callee(x, (4, 5), [2, 6, 7]), expected);
+    let f: extern "unadjusted" fn(Mixed, (u32, u64), [u64; 3]) -> Mixed =
+        black_box(unadjusted_callee);
+    assert_eq!(f(black_box(x), (4, 5), [2, 6, 7]), expected);
+}
+
+macro_rules! test_atomic_int {
+    ($atomic:ty, $int:ty) => {{
+        use std::sync::atomic::Ordering::*;
+
+        let a = <$atomic>::new(black_box(5));
+        assert_eq!(a.load(Relaxed), 5);
+        a.store(6, Release);
+        assert_eq!(a.load(Acquire), 6);
+        assert_eq!(a.swap(7, AcqRel), 6);
+        assert_eq!(a.compare_exchange(7, 8, SeqCst, Relaxed), Ok(7));
+        assert_eq!(a.compare_exchange(7, 9, Acquire, Acquire), Err(8));
+        assert_eq!(a.compare_exchange_weak(8, 9, Release, Relaxed), Ok(8));
+        assert_eq!(a.fetch_add(3, Relaxed), 9);
+        assert_eq!(a.fetch_sub(2, Release), 12);
+        assert_eq!(a.fetch_and(0b1100, Acquire), 10);
+        assert_eq!(a.fetch_or(0b0011, AcqRel), 0b1000);
+        assert_eq!(a.fetch_xor(0b0110, SeqCst), 0b1011);
+        assert_eq!(a.fetch_nand(0b0110, SeqCst), 0b1101);
+        assert_eq!(a.load(SeqCst), !0b0100);
+        a.store(<$int>::MAX, Relaxed);
+        assert_eq!(a.fetch_add(1, Relaxed), <$int>::MAX);
+        assert_eq!(a.swap(0, Relaxed), <$int>::MIN);
+        assert_eq!(a.fetch_max(4, Relaxed), 0);
+        assert_eq!(a.fetch_min(2, Relaxed), 4);
+        assert_eq!(a.fetch_update(Relaxed, Relaxed, |x| Some(x * 3)), Ok(2));
+        assert_eq!(a.into_inner(), 6);
+    }};
+}
+
+fn test_atomics() {
+    use std::sync::atomic::*;
+
+    test_atomic_int!(AtomicU8, u8);
+    test_atomic_int!(AtomicI8, i8);
+    test_atomic_int!(AtomicU16, u16);
+    test_atomic_int!(AtomicI16, i16);
+    test_atomic_int!(AtomicU32, u32);
+    test_atomic_int!(AtomicI32, i32);
+    test_atomic_int!(AtomicU64, u64);
+    test_atomic_int!(AtomicI64, i64);
+    test_atomic_int!(AtomicUsize, usize);
+    test_atomic_int!(AtomicIsize, isize);
+
+    let i = AtomicI32::new(-1);
+    assert_eq!(i.fetch_max(-5, Ordering::Relaxed), -1);
+    assert_eq!(i.fetch_min(-5, Ordering::Relaxed), -1);
+    assert_eq!(i.load(Ordering::Relaxed), -5);
+
+    let b = AtomicBool::new(false);
+    assert_eq!(b.fetch_or(true, Ordering::SeqCst), false);
+    assert_eq!(b.fetch_xor(true, Ordering::SeqCst), true);
+    assert_eq!(b.load(Ordering::SeqCst), false);
+
+    let mut x = 1;
+    let mut y = 2;
+    let p = AtomicPtr::new(&mut x as *mut i32);
+    assert_eq!(p.swap(&mut y, Ordering::AcqRel), &mut x as *mut i32);
+    assert_eq!(
+        p.compare_exchange(&mut y, &mut x, Ordering::SeqCst, Ordering::Relaxed),
+        Ok(&mut y as *mut i32),
+    );
+    assert_eq!(unsafe { *p.load(Ordering::Acquire) }, 1);
+
+    for ordering in [Ordering::Acquire, Ordering::Release, Ordering::AcqRel, Ordering::SeqCst] {
+        fence(ordering);
+        compiler_fence(ordering);
+    }
+
+    #[cfg(all(target_arch = "x86_64", not(jit)))]
+    if is_x86_feature_detected!("cmpxchg16b") {
+        unsafe { test_atomics_128() };
+    }
+}
+
+// 128bit atomics need `cmpxchg16b` and are not supported in jit mode.
+#[cfg(all(target_arch = "x86_64", not(jit)))]
+#[target_feature(enable = "cmpxchg16b")]
+unsafe fn test_atomics_128() {
+    use std::intrinsics::*;
+
+    #[repr(align(16))]
+    struct Aligned<T>(T);
+
+    let big = 1u128 << 100 | 7;
+    let mut a = Aligned(black_box(big));
+    let p = &mut a.0 as *mut u128;
+    unsafe {
+        assert_eq!(atomic_load_acquire(p), big);
+        atomic_store_release(p, 3);
+        assert_eq!(atomic_xchg_acqrel(p, big), 3);
+        assert_eq!(atomic_cxchg_seqcst_seqcst(p, big, 5), (big, true));
+        assert_eq!(atomic_cxchg_seqcst_seqcst(p, big, 6), (5, false));
+        assert_eq!(atomic_xadd_relaxed(p, u128::MAX), 5);
+        assert_eq!(atomic_xsub_relaxed(p, 1 << 64), 4);
+        assert_eq!(atomic_and_seqcst(p, u128::MAX << 1), 4u128.wrapping_sub(1 << 64));
+        assert_eq!(atomic_or_seqcst(p, 1), 4u128.wrapping_sub(1 << 64));
+        assert_eq!(atomic_xor_seqcst(p, u128::MAX), 5u128.wrapping_sub(1 << 64));
+        assert_eq!(atomic_nand_seqcst(p, 0), (1 << 64) - 6);
+        assert_eq!(atomic_umax_seqcst(p, 1 << 70), u128::MAX);
+        assert_eq!(atomic_umin_seqcst(p, 1 << 70), u128::MAX);
+        assert_eq!(atomic_load_seqcst(p), 1 << 70);
+
+        let p = p as *mut i128;
+        assert_eq!(atomic_max_seqcst(p, -1), 1 << 70);
+        assert_eq!(atomic_min_seqcst(p, -1), 1 << 70);
+        assert_eq!(atomic_load_relaxed(p), -1);
+    }
+}
+
+#[repr(C)]
+struct AbiTriple {
+    a: u64,
+    b: u64,
+    c: u64,
+}
+
+extern "system" fn system_abi_callee(a: i32, b: f64, c: AbiTriple) -> f64 {
+    a as f64 + b + (c.a + c.b + c.c) as f64
+}
+
+#[cfg(target_arch = "x86_64")]
+extern "win64" fn win64_abi_callee(a: i32, b: f64, c: AbiTriple) -> f64 {
+    a as f64 + b + (c.a + c.b + c.c) as f64
+}
+
+#[cfg(target_arch = "x86_64")]
+extern "sysv64" fn sysv64_abi_callee(a: i32, b: f64, c: AbiTriple) -> f64 {
+    a as f64 + b + (c.a + c.b + c.c) as f64
+}
+
+fn test_system_abi() {
+    let triple = || AbiTriple { a: 1, b: 2, c: 3 };
+    assert_eq!(system_abi_callee(1, 0.5, triple()), 7.5);
+    let system_fn: extern "system" fn(i32, f64, AbiTriple) -> f64 = black_box(system_abi_callee);
+    assert_eq!(system_fn(1, 0.5, triple()), 7.5);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        assert_eq!(win64_abi_callee(1, 0.5, triple()), 7.5);
+        let win64_fn: extern "win64" fn(i32, f64, AbiTriple) -> f64 = black_box(win64_abi_callee);
+        assert_eq!(win64_fn(1, 0.5, triple()), 7.5);
+
+        assert_eq!(sysv64_abi_callee(1, 0.5, triple()), 7.5);
+        let sysv64_fn: extern "sysv64" fn(i32, f64, AbiTriple) -> f64 =
+            black_box(sysv64_abi_callee);
+        assert_eq!(sysv64_fn(1, 0.5, triple()), 7.5);
+    }
+}
+
+fn test_runtime_calls() {
+    // These are lowered to calls to libm and compiler-builtins.
+    assert_eq!(black_box(7.5f32) % black_box(2.0), 1.5);
+    assert_eq!(black_box(-7.5f64) % black_box(2.0), -1.5);
+    assert_eq!(black_box(3.0f32).powi(black_box(3)), 27.0);
+    assert_eq!(black_box(2.0f64).powi(black_box(-1)), 0.5);
+    assert_eq!(black_box(2.0f64).powf(black_box(10.0)), 1024.0);
+    assert_eq!(black_box(16.0f32).log2(), 4.0);
+    assert_eq!(black_box(1u128 << 100) * black_box(3), 3 << 100);
+    assert_eq!(black_box(-(1i128 << 100)) / black_box(1 << 90), -1024);
+    assert_eq!(black_box(u128::MAX) % black_box(1 << 64), u64::MAX as u128);
+}
+
+static STATIC_A: u32 = 42;
+static STATIC_B: &u32 = &STATIC_A;
+
+static WORDS_A: [&str; 2] = ["a", "bc"];
+static WORDS_B: [&str; 1] = ["def"];
+static WORD_TABLE: &[&[&str]] = &[&WORDS_A, &WORDS_B, &[]];
+
+struct Cyclic {
+    other: &'static Cyclic,
+    val: u32,
+}
+
+static CYCLIC_A: Cyclic = Cyclic { other: &CYCLIC_B, val: 1 };
+static CYCLIC_B: Cyclic = Cyclic { other: &CYCLIC_A, val: 2 };
+
+fn test_static_references() {
+    assert!(std::ptr::eq(*black_box(&STATIC_B), &STATIC_A));
+    assert_eq!(**black_box(&STATIC_B), 42);
+
+    let table = *black_box(&WORD_TABLE);
+    assert_eq!(table.len(), 3);
+    assert!(std::ptr::eq(table[0], &WORDS_A[..]));
+    assert_eq!(table[0], ["a", "bc"]);
+    assert_eq!(table[1], ["def"]);
+    assert!(table[2].is_empty());
+    assert_eq!(
+        table.iter().flat_map(|words| words.iter()).map(|word| word.len()).sum::<usize>(),
+        6
+    );
+
+    let cyclic = black_box(&CYCLIC_A);
+    assert_eq!(cyclic.other.val, 2);
+    assert!(std::ptr::eq(cyclic.other.other, &CYCLIC_A));
+}
+
+#[inline(never)]
+fn narrowed_compare(u: u8, i: i8, o: Option<u8>) -> [bool; 8] {
+    [
+        u as u32 == 200,
+        u as u32 == 256,
+        u as u64 > 100,
+        i as i32 == -1,
+        i as i32 >= -100,
+        i as i32 > 200,
+        (i as u32) < 5,
+        o.is_none(),
+    ]
+}
+
+fn test_narrowed_compare() {
+    // Comparisons of extended values against constants may be performed on the narrower value, but
+    // only when the constant fits in the narrower type.
+    assert_eq!(
+        narrowed_compare(black_box(200), black_box(-1), black_box(None)),
+        [true, false, true, true, true, false, false, true],
+    );
+    assert_eq!(
+        narrowed_compare(black_box(0), black_box(-128), black_box(Some(0))),
+        [false, false, false, false, false, false, false, false],
+    );
+    assert_eq!(
+        narrowed_compare(black_box(255), black_box(4), black_box(Some(1))),
+        [false, false, true, false, true, false, true, false],
+    );
+
+    // 128bit comparisons against constants are not narrowed.
+    assert!(black_box(u64::MAX as u128) == u64::MAX as u128);
+    assert!(black_box(u128::MAX) != u64::MAX as u128);
+    for (v, res) in [(0, 0), (1 << 63, 1), (u64::MAX as u128, 2), (u128::MAX, 3), (5, 0)] {
+        let switched = match black_box(v) {
+            0x8000_0000_0000_0000 => 1,
+            0xFFFF_FFFF_FFFF_FFFF => 2,
+            u128::MAX => 3,
+            _ => 0,
+        };
+        assert_eq!(switched, res);
+    }
+}
+
+#[inline(never)]
+fn dense_switch(v: u8) -> u8 {
+    match v {
+        10 => 1,
+        11 => 2,
+        12 => 3,
+        14 => 4,
+        16 => 5,
+        17 => 6,
+        19 => 7,
+        _ => 0,
+    }
+}
+
+fn test_dense_switch() {
+    // Values below the minimum, above the maximum and in holes must all go to the otherwise arm.
+    for (v, res) in [
+        (0, 0),
+        (9, 0),
+        (10, 1),
+        (11, 2),
+        (12, 3),
+        (13, 0),
+        (14, 4),
+        (15, 0),
+        (16, 5),
+        (17, 6),
+        (18, 0),
+        (19, 7),
+        (20, 0),
+        (255, 0),
+    ] {
+        assert_eq!(dense_switch(black_box(v)), res);
+    }
+}
+
+fn test_prefetch() {
+    let data = [1u64, 2, 3, 4];
+    let mut sum = 0;
+    for i in 0..data.len() {
+        unsafe {
+            std::intrinsics::prefetch_read_data(data.as_ptr().add(i), 0);
+            std::intrinsics::prefetch_read_data(data.as_ptr().add(i), 1);
+            std::intrinsics::prefetch_write_data(data.as_ptr().add(i), 2);
+            std::intrinsics::prefetch_read_instruction(test_prefetch as *const u8, 3);
+        }
+        sum += data[i];
+    }
+    assert_eq!(sum, 10);
 }
 
 #[derive(PartialEq)]
@@ -37,4 +37,41 @@ fn main() {
     assert_eq!(contained.file(), file!());
     assert_eq!(contained.line(), 17);
     assert_eq!(contained.column(), 5);
+
+    let method = ().tracked_method();
+    assert_eq!(method.file(), file!());
+    assert_eq!(method.line(), 41);
+    assert_eq!(method.column(), 21);
+
+    let dyn_method = (&() as &dyn Tracked).tracked_method();
+    assert_eq!(dyn_method.file(), file!());
+    assert_eq!(dyn_method.line(), 46);
+    assert_eq!(dyn_method.column(), 44);
+
+    // Calls through function pointers go through a shim which passes the location of the
+    // function itself.
+    let fn_ptr = std::hint::black_box(crate::tracked as fn() -> &'static Location<'static>)();
+    assert_eq!(fn_ptr.file(), file!());
+    assert_eq!(fn_ptr.line(), 8);
+    assert_eq!(fn_ptr.column(), 1);
+
+    // The location is forwarded through nested `#[track_caller]` functions.
+    let forwarded = forward_tracked();
+    assert_eq!(forwarded.file(), file!());
+    assert_eq!(forwarded.line(), 59);
+    assert_eq!(forwarded.column(), 21);
 }
+
+#[track_caller]
+fn forward_tracked() -> &'static Location<'static> {
+    tracked()
+}
+
+trait Tracked {
+    #[track_caller]
+    fn tracked_method(&self) -> &'static Location<'static> {
+        Location::caller()
+    }
+}
+
+impl Tracked for () {}
@@ -0,0 +1,51 @@
+// Unsupported constructs must only abort codegen of the function containing them. With
+// `-Cllvm-args=allow_unsupported=true` the crate still compiles and works as long as the affected
+// functions aren't called. Calling them prints an error and exits.
+
+#![feature(abi_vectorcall)]
+
+// Cranelift stack slots are limited to 4GiB.
+#[inline(never)]
+fn huge_local() -> u8 {
+    let a = [1u8; 1 << 32];
+    std::hint::black_box(&a)[0]
+}
+
+// Only supported on x86 and x86_64. Cranelift doesn't implement this calling convention.
+#[cfg(target_arch = "x86_64")]
+#[inline(never)]
+extern "vectorcall" fn vectorcall(a: f64, b: f64) -> f64 {
+    a + b
+}
+
+// Calls to functions with an unsupported calling convention are unsupported too.
+#[cfg(target_arch = "x86_64")]
+#[inline(never)]
+fn call_vectorcall() -> f64 {
+    vectorcall(1.0, 2.0)
+}
+
+#[inline(never)]
+fn supported(a: u8) -> u8 {
+    a + 1
+}
+
+fn main() {
+    assert_eq!(supported(1), 2);
+
+    let Some(arg) = std::env::args().nth(1) else {
+        return;
+    };
+    match &*arg {
+        "huge_local" => {
+            huge_local();
+        }
+        #[cfg(target_arch = "x86_64")]
+        "vectorcall" => {
+            call_vectorcall();
+        }
+        _ => panic!("unknown argument {}", arg),
+    }
+
+    unreachable!("calling an unsupported function must exit");
+}
@@ -41,10 +41,6 @@ rm tests/ui/rust-2018/proc-macro-crate-in-paths.rs
 rm tests/ui/proc-macro/allowed-signatures.rs
 rm tests/ui/proc-macro/no-mangle-in-proc-macro-issue-111888.rs
 
-# vendor intrinsics
-rm tests/ui/sse2.rs # CodegenBackend::target_features not yet implemented
-rm tests/ui/simd/array-type.rs # "Index argument for `simd_insert` is not a constant"
-
 # exotic linkages
 rm tests/ui/issues/issue-33992.rs # unsupported linkages
 rm tests/incremental/hashes/function_interfaces.rs # same
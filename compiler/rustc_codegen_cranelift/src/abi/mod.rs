@@ -3,6 +3,7 @@
 mod comments;
 mod pass_mode;
 mod returning;
+mod varargs;
 
 use std::borrow::Cow;
 
@@ -10,12 +11,12 @@
 use cranelift_module::ModuleError;
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::ty::layout::FnAbiOf;
-use rustc_session::Session;
-use rustc_target::abi::call::{Conv, FnAbi};
+use rustc_target::abi::call::{Conv, FnAbi, PassMode};
 use rustc_target::spec::abi::Abi;
 
 use self::pass_mode::*;
 pub(crate) use self::returning::codegen_return;
+pub(crate) use self::varargs::codegen_va_arg;
 use crate::prelude::*;
 
 fn clif_sig_from_fn_abi<'tcx>(
@@ -23,7 +24,8 @@ fn clif_sig_from_fn_abi<'tcx>(
     default_call_conv: CallConv,
     fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
 ) -> Signature {
-    let call_conv = conv_to_call_conv(tcx.sess, fn_abi.conv, default_call_conv);
+    let call_conv = conv_to_call_conv(fn_abi.conv, default_call_conv)
+        .unwrap_or_else(|err| bug!("call conv should have been checked by `check_fn_abi`: {err}"));
 
     let inputs = fn_abi.args.iter().flat_map(|arg_abi| arg_abi.get_abi_param(tcx).into_iter());
 
@@ -34,25 +36,35 @@ fn clif_sig_from_fn_abi<'tcx>(
     Signature { params, returns, call_conv }
 }
 
-pub(crate) fn conv_to_call_conv(sess: &Session, c: Conv, default_call_conv: CallConv) -> CallConv {
-    match c {
+/// Returns the Cranelift calling convention for `c` or a description of why it isn't supported.
+pub(crate) fn conv_to_call_conv(c: Conv, default_call_conv: CallConv) -> Result<CallConv, String> {
+    Ok(match c {
+        // `extern "system"` is lowered to `Conv::C` on all targets supported by Cranelift. Only
+        // 32bit Windows uses stdcall for it. On x86_64 Windows `default_call_conv` is the Win64
+        // calling convention.
         Conv::Rust | Conv::C => default_call_conv,
         Conv::Cold | Conv::PreserveMost | Conv::PreserveAll => CallConv::Cold,
         Conv::X86_64SysV => CallConv::SystemV,
+        // rustc lowers `extern "efiapi"` to this on x86_64 and to `Conv::C` elsewhere.
         Conv::X86_64Win64 => CallConv::WindowsFastcall,
 
-        // Should already get a back compat warning
-        Conv::X86Fastcall | Conv::X86Stdcall | Conv::X86ThisCall | Conv::X86VectorCall => {
-            default_call_conv
+        // rustc lowers these to `Conv::C` on all targets other than 32bit x86, which Cranelift
+        // doesn't support.
+        Conv::X86Fastcall | Conv::X86Stdcall | Conv::X86ThisCall => {
+            return Err(format!("32bit x86 call conv {c:?} is not supported by Cranelift"));
         }
+        Conv::X86VectorCall => return Err("vectorcall call conv not yet implemented".to_owned()),
 
         Conv::X86Intr | Conv::RiscvInterrupt { .. } => {
-            sess.fatal(format!("interrupt call conv {c:?} not yet implemented"))
+            return Err(format!("interrupt call conv {c:?} not yet implemented"));
         }
 
-        Conv::ArmAapcs => sess.fatal("aapcs call conv not yet implemented"),
+        Conv::ArmAapcs => {
+            return Err("aapcs call conv is not supported as Cranelift doesn't support 32bit arm"
+                .to_owned());
+        }
         Conv::CCmseNonSecureCall => {
-            sess.fatal("C-cmse-nonsecure-call call conv is not yet implemented");
+            return Err("C-cmse-nonsecure-call call conv is not yet implemented".to_owned());
         }
 
         Conv::Msp430Intr
@@ -62,7 +74,29 @@ pub(crate) fn conv_to_call_conv(sess: &Session, c: Conv, default_call_conv: Call
         | Conv::AvrNonBlockingInterrupt => {
             unreachable!("tried to use {c:?} call conv which only exists on an unsupported target");
         }
+    })
+}
+
+/// Check that the calling convention of `fn_abi` is supported and that all arguments and the
+/// return value are passed in a way that can be represented in Cranelift IR. This must be called
+/// before the signature is lowered, as the lowering assumes that everything is supported.
+pub(crate) fn check_fn_abi<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    default_call_conv: CallConv,
+    fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
+) -> Result<(), String> {
+    conv_to_call_conv(fn_abi.conv, default_call_conv)?;
+
+    let args = fn_abi.args.iter().map(|arg_abi| (arg_abi, false));
+    for (arg_abi, is_return) in args.chain(std::iter::once((&fn_abi.ret, true))) {
+        if let Err(err) = check_arg_abi(tcx, arg_abi, is_return) {
+            return Err(format!(
+                "passing `{}` {err} is not supported for the {:?} calling convention",
+                arg_abi.layout.ty, fn_abi.conv,
+            ));
+        }
     }
+    Ok(())
 }
 
 pub(crate) fn get_function_sig<'tcx>(
@@ -71,14 +105,29 @@ pub(crate) fn get_function_sig<'tcx>(
     inst: Instance<'tcx>,
 ) -> Signature {
     assert!(!inst.args.has_infer());
-    clif_sig_from_fn_abi(
-        tcx,
-        default_call_conv,
-        &RevealAllLayoutCx(tcx).fn_abi_of_instance(inst, ty::List::empty()),
-    )
+    let fn_abi = RevealAllLayoutCx(tcx).fn_abi_of_instance(inst, ty::List::empty());
+    if check_fn_abi(tcx, default_call_conv, fn_abi).is_err() {
+        // The error is reported when codegening the function or a call to it. Calling this
+        // placeholder signature is never attempted, as the function body is replaced with a trap
+        // and all calls to it are rejected.
+        return Signature::new(default_call_conv);
+    }
+    let mut sig = clif_sig_from_fn_abi(tcx, default_call_conv, fn_abi);
+    // Imported variadic functions are declared with only their fixed parameters. Every call
+    // replaces the signature with one matching the actual arguments. Variadic functions defined
+    // by cg_clif need extra parameters to access the variadic arguments.
+    if fn_abi.c_variadic && !tcx.is_foreign_item(inst.def_id()) {
+        self::varargs::add_vararg_params(tcx, &mut sig);
+    }
+    sig
 }
 
 /// Instance must be monomorphized
+///
+/// Instances defined in the current codegen unit were already declared with their actual linkage
+/// by `predefine_mono_items`, which takes precedence over `Linkage::Import`. All other instances are
+/// defined in another codegen unit or crate. Rustc only internalizes instances which are not
+/// referenced from other codegen units, so those are always exported or shared with weak linkage.
 pub(crate) fn import_function<'tcx>(
     tcx: TyCtxt<'tcx>,
     module: &mut dyn Module,
@@ -112,6 +161,11 @@ pub(crate) fn get_function_ref(&mut self, inst: Instance<'tcx>) -> FuncRef {
         func_ref
     }
 
+    /// Call a function with an explicit clif signature. Prefer [`Self::call_runtime`] when the
+    /// arguments and return value have Rust types. This is only still used where the callee
+    /// doesn't follow the C abi of its Rust signature (like the `i64x2` return of the i128
+    /// division libcalls on Windows) or where only clif values are available (like the float
+    /// casts and the panic lang items).
     pub(crate) fn lib_call(
         &mut self,
         name: &str,
@@ -171,6 +225,88 @@ pub(crate) fn lib_call_unadjusted(
         assert!(results.len() <= 2, "{}", results.len());
         Cow::Borrowed(results)
     }
+
+    /// Call a runtime library function like a libm or compiler-builtins function as if it was
+    /// declared as `extern "C" fn(args...) -> ret`. Arguments and the return value are passed
+    /// according to the C abi, so structs are for example passed by reference when necessary.
+    pub(crate) fn call_runtime(
+        &mut self,
+        name: &str,
+        args: &[CValue<'tcx>],
+        ret_layout: TyAndLayout<'tcx>,
+    ) -> CValue<'tcx> {
+        let fn_sig = self.tcx.mk_fn_sig(
+            args.iter().map(|arg| arg.layout().ty),
+            ret_layout.ty,
+            false,
+            rustc_hir::Unsafety::Unsafe,
+            Abi::C { unwind: false },
+        );
+        let fn_abi = RevealAllLayoutCx(self.tcx)
+            .fn_abi_of_fn_ptr(ty::Binder::dummy(fn_sig), ty::List::empty());
+
+        let func_id = match self.cx.runtime_functions.get(name) {
+            Some(&func_id) => func_id,
+            None => {
+                let sig =
+                    clif_sig_from_fn_abi(self.tcx, self.target_config.default_call_conv, fn_abi);
+                let func_id = self.module.declare_function(name, Linkage::Import, &sig).unwrap();
+                self.cx.runtime_functions.insert(name.to_owned(), func_id);
+                func_id
+            }
+        };
+        let func_ref = self.module.declare_func_in_func(func_id, &mut self.bcx.func);
+        if self.clif_comments.enabled() {
+            self.add_comment(func_ref, format!("{:?}", name));
+        }
+
+        let call = |fx: &mut FunctionCx<'_, '_, 'tcx>, ret_ptr: Option<Value>| {
+            let mut call_args: Vec<Value> = ret_ptr.into_iter().collect();
+            for (&arg, arg_abi) in args.iter().zip(fn_abi.args.iter()) {
+                call_args.extend(adjust_arg_for_abi(fx, arg, arg_abi, false));
+            }
+            let call_inst = fx.bcx.ins().call(func_ref, &call_args);
+            if fx.clif_comments.enabled() {
+                fx.add_comment(call_inst, format!("call_runtime {}", name));
+            }
+            call_inst
+        };
+
+        // Use scalar return values directly rather than spilling them to the stack.
+        match (&fn_abi.ret.mode, ret_layout.abi) {
+            (PassMode::Direct(_), rustc_target::abi::Abi::Scalar(_)) => {
+                let call_inst = call(self, None);
+                CValue::by_val(self.bcx.inst_results(call_inst)[0], ret_layout)
+            }
+            _ => {
+                let ret_place = CPlace::new_stack_slot(self, ret_layout);
+                self::returning::codegen_with_call_return_arg(
+                    self,
+                    &fn_abi.ret,
+                    ret_place,
+                    |fx, ret_ptr| Ok(call(fx, ret_ptr)),
+                )
+                .unwrap_or_else(|_| unreachable!("extern \"C\" calls are always supported"));
+                ret_place.to_cvalue(self)
+            }
+        }
+    }
+
+    /// Shorthand for [`call_runtime`](Self::call_runtime) with a return type rather than layout.
+    pub(crate) fn easy_call(
+        &mut self,
+        name: &str,
+        args: &[CValue<'tcx>],
+        return_ty: Ty<'tcx>,
+    ) -> CValue<'tcx> {
+        let ret_layout = self.layout_of(return_ty);
+        self.call_runtime(name, args, ret_layout)
+    }
+}
+
+enum CallTarget {
+    Direct(FuncRef),
+    Indirect(SigRef, Value),
 }
 
 /// Make a [`CPlace`] capable of holding value of the specified type.
@@ -179,12 +315,18 @@ fn make_local_place<'tcx>(
     local: Local,
     layout: TyAndLayout<'tcx>,
     is_ssa: bool,
-) -> CPlace<'tcx> {
+) -> CodegenResult<CPlace<'tcx>> {
     if layout.is_unsized() {
-        fx.tcx.sess.span_fatal(
+        return Err(fx.unsupported(
             fx.mir.local_decls[local].source_info.span,
             "unsized locals are not yet supported",
-        );
+        ));
+    }
+    if layout.size.bytes() >= u64::from(u32::MAX - 16) {
+        return Err(fx.unsupported(
+            fx.mir.local_decls[local].source_info.span,
+            format!("values of type {} are too big to store on the stack", layout.ty),
+        ));
     }
     let place = if is_ssa {
         if let rustc_target::abi::Abi::ScalarPair(_, _) = layout.abi {
@@ -198,10 +340,13 @@ fn make_local_place<'tcx>(
 
     self::comments::add_local_place_comments(fx, place, local);
 
-    place
+    Ok(place)
 }
 
-pub(crate) fn codegen_fn_prelude<'tcx>(fx: &mut FunctionCx<'_, '_, 'tcx>, start_block: Block) {
+pub(crate) fn codegen_fn_prelude<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    start_block: Block,
+) -> CodegenResult {
     fx.bcx.append_block_params_for_function_params(start_block);
 
     fx.bcx.switch_to_block(start_block);
@@ -213,25 +358,18 @@ pub(crate) fn codegen_fn_prelude<'tcx>(fx: &mut FunctionCx<'_, '_, 'tcx>, start_
 
     let mut block_params_iter = fx.bcx.func.dfg.block_params(start_block).to_vec().into_iter();
     let ret_place =
-        self::returning::codegen_return_param(fx, &ssa_analyzed, &mut block_params_iter);
+        self::returning::codegen_return_param(fx, &ssa_analyzed, &mut block_params_iter)?;
     assert_eq!(fx.local_map.push(ret_place), RETURN_PLACE);
 
     // None means pass_mode == NoPass
     enum ArgKind<'tcx> {
         Normal(Option<CValue<'tcx>>),
         Spread(Vec<Option<CValue<'tcx>>>),
+        VaList,
     }
 
     let fn_abi = fx.fn_abi.take().unwrap();
 
-    // FIXME implement variadics in cranelift
-    if fn_abi.c_variadic {
-        fx.tcx.sess.span_fatal(
-            fx.mir.span,
-            "Defining variadic functions is not yet supported by Cranelift",
-        );
-    }
-
     let mut arg_abis_iter = fn_abi.args.iter();
 
     let func_params = fx
@@ -261,6 +399,10 @@ enum ArgKind<'tcx> {
                 }
 
                 (local, ArgKind::Spread(params), arg_ty)
+            } else if fn_abi.c_variadic && local.as_usize() == fx.mir.arg_count {
+                // The last argument of a C-variadic function is the `VaListImpl` for the variadic
+                // arguments.
+                (local, ArgKind::VaList, arg_ty)
             } else {
                 let arg_abi = arg_abis_iter.next().unwrap();
                 let param =
@@ -279,6 +421,8 @@ enum ArgKind<'tcx> {
     }
 
     assert!(arg_abis_iter.next().is_none(), "ArgAbi left behind");
+    let vararg_params =
+        if fn_abi.c_variadic { block_params_iter.by_ref().collect() } else { Vec::new() };
     fx.fn_abi = Some(fn_abi);
     assert!(block_params_iter.next().is_none(), "arg_value left behind");
 
@@ -308,7 +452,7 @@ enum ArgKind<'tcx> {
 
         let layout = fx.layout_of(ty);
         let is_ssa = ssa_analyzed[local].is_ssa(fx, ty);
-        let place = make_local_place(fx, local, layout, is_ssa);
+        let place = make_local_place(fx, local, layout, is_ssa)?;
         assert_eq!(fx.local_map.push(place), local);
 
         match arg_kind {
@@ -324,6 +468,7 @@ enum ArgKind<'tcx> {
                     }
                 }
             }
+            ArgKind::VaList => self::varargs::codegen_va_list_init(fx, place, &vararg_params)?,
         }
     }
 
@@ -333,11 +478,13 @@ enum ArgKind<'tcx> {
 
         let is_ssa = ssa_analyzed[local].is_ssa(fx, ty);
 
-        let place = make_local_place(fx, local, layout, is_ssa);
+        let place = make_local_place(fx, local, layout, is_ssa)?;
         assert_eq!(fx.local_map.push(place), local);
     }
 
     fx.bcx.ins().jump(*fx.block_map.get(START_BLOCK).unwrap(), &[]);
+
+    Ok(())
 }
 
 struct CallArgument<'tcx> {
@@ -363,7 +510,7 @@ pub(crate) fn codegen_terminator_call<'tcx>(
     args: &[Operand<'tcx>],
     destination: Place<'tcx>,
     target: Option<BasicBlock>,
-) {
+) -> CodegenResult {
     let func = codegen_operand(fx, func);
     let fn_sig = func.layout().ty.fn_sig(fx.tcx);
 
@@ -376,7 +523,7 @@ pub(crate) fn codegen_terminator_call<'tcx>(
                 .polymorphize(fx.tcx);
 
         if fx.tcx.symbol_name(instance).name.starts_with("llvm.") {
-            crate::intrinsics::codegen_llvm_intrinsic_call(
+            return crate::intrinsics::codegen_llvm_intrinsic_call(
                 fx,
                 &fx.tcx.symbol_name(instance).name,
                 fn_args,
@@ -385,12 +532,11 @@ pub(crate) fn codegen_terminator_call<'tcx>(
                 target,
                 source_info.span,
             );
-            return;
         }
 
         match instance.def {
             InstanceDef::Intrinsic(_) => {
-                crate::intrinsics::codegen_intrinsic_call(
+                return crate::intrinsics::codegen_intrinsic_call(
                     fx,
                     instance,
                     args,
@@ -398,14 +544,13 @@ pub(crate) fn codegen_terminator_call<'tcx>(
                     target,
                     source_info,
                 );
-                return;
             }
             InstanceDef::DropGlue(_, None) => {
                 // empty drop glue - a nop.
                 let dest = target.expect("Non terminating drop_in_place_real???");
                 let ret_block = fx.get_block(dest);
                 fx.bcx.ins().jump(ret_block, &[]);
-                return;
+                return Ok(());
             }
             _ => Some(instance),
         }
@@ -422,6 +567,15 @@ pub(crate) fn codegen_terminator_call<'tcx>(
     } else {
         RevealAllLayoutCx(fx.tcx).fn_abi_of_fn_ptr(fn_sig, extra_args)
     };
+    if let Err(err) = check_fn_abi(fx.tcx, fx.target_config.default_call_conv, fn_abi) {
+        return Err(fx.unsupported(source_info.span, err));
+    }
+    if fn_sig.c_variadic() && !matches!(fn_sig.abi(), Abi::C { .. }) {
+        return Err(fx.unsupported(
+            source_info.span,
+            format!("Variadic call for non-C abi {:?}", fn_sig.abi()),
+        ));
+    }
 
     let is_cold = if fn_sig.abi() == Abi::RustCold {
         true
@@ -475,11 +629,6 @@ pub(crate) fn codegen_terminator_call<'tcx>(
     let args = args;
     assert_eq!(fn_abi.args.len(), args.len());
 
-    enum CallTarget {
-        Direct(FuncRef),
-        Indirect(SigRef, Value),
-    }
-
     let (func_ref, first_arg_override) = match instance {
         // Trait object call
         Some(Instance { def: InstanceDef::Virtual(_, idx), .. }) => {
@@ -520,7 +669,7 @@ enum CallTarget {
     };
 
     self::returning::codegen_with_call_return_arg(fx, &fn_abi.ret, ret_place, |fx, return_ptr| {
-        let call_args = return_ptr
+        let mut call_args = return_ptr
             .into_iter()
             .chain(first_arg_override.into_iter())
             .chain(
@@ -533,6 +682,18 @@ enum CallTarget {
             )
             .collect::<Vec<Value>>();
 
+        let func_ref = if fn_sig.c_variadic() {
+            self::varargs::adjust_variadic_call(
+                fx,
+                source_info.span,
+                instance,
+                func_ref,
+                &mut call_args,
+            )?
+        } else {
+            func_ref
+        };
+
         let call_inst = match func_ref {
             CallTarget::Direct(func_ref) => fx.bcx.ins().call(func_ref, &call_args),
             CallTarget::Indirect(sig, func_ptr) => {
@@ -540,34 +701,8 @@ enum CallTarget {
             }
         };
 
-        // FIXME find a cleaner way to support varargs
-        if fn_sig.c_variadic() {
-            if !matches!(fn_sig.abi(), Abi::C { .. }) {
-                fx.tcx.sess.span_fatal(
-                    source_info.span,
-                    format!("Variadic call for non-C abi {:?}", fn_sig.abi()),
-                );
-            }
-            let sig_ref = fx.bcx.func.dfg.call_signature(call_inst).unwrap();
-            let abi_params = call_args
-                .into_iter()
-                .map(|arg| {
-                    let ty = fx.bcx.func.dfg.value_type(arg);
-                    if !ty.is_int() {
-                        // FIXME set %al to upperbound on float args once floats are supported
-                        fx.tcx.sess.span_fatal(
-                            source_info.span,
-                            format!("Non int ty {:?} for variadic call", ty),
-                        );
-                    }
-                    AbiParam::new(ty)
-                })
-                .collect::<Vec<AbiParam>>();
-            fx.bcx.func.dfg.signatures[sig_ref].params = abi_params;
-        }
-
-        call_inst
-    });
+        Ok(call_inst)
+    })?;
 
     if let Some(dest) = target {
         let ret_block = fx.get_block(dest);
@@ -575,6 +710,8 @@ enum CallTarget {
     } else {
         fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
     }
+
+    Ok(())
 }
 
 pub(crate) fn codegen_drop<'tcx>(
@@ -4,6 +4,7 @@
 use rustc_target::abi::call::{
     ArgAbi, ArgAttributes, ArgExtension as RustcArgExtension, CastTarget, PassMode, Reg, RegKind,
 };
+use rustc_target::abi::{FieldsShape, Integer, Primitive, Variants};
 use smallvec::{smallvec, SmallVec};
 
 use crate::prelude::*;
@@ -38,6 +39,74 @@ fn apply_arg_attrs_to_abi_param(mut param: AbiParam, arg_attrs: ArgAttributes) -
     param
 }
 
+/// Cranelift only supports 128bit vectors. Like LLVM, smaller vectors are widened to a single
+/// 128bit vector and larger vectors are split into multiple 128bit vectors.
+fn vector_to_abi_types(tcx: TyCtxt<'_>, layout: TyAndLayout<'_>) -> SmallVec<[Type; 2]> {
+    let element = match layout.abi {
+        Abi::Vector { element, count: _ } => scalar_to_clif_type(tcx, element),
+        _ => unreachable!("{:?}", layout.abi),
+    };
+    let part_ty = element.by(16 / element.bytes()).unwrap();
+    smallvec![part_ty; usize::try_from(layout.size.bytes().div_ceil(16)).unwrap()]
+}
+
+/// Aggregates are only passed directly for the `unadjusted` ABI. Like LLVM does for first-class
+/// aggregates, every scalar and vector leaf field is passed as separate value. Returns the offset
+/// and type of each leaf field, or `None` if the aggregate contains a field which can't be split
+/// this way, like a union or an enum with multiple variants.
+fn aggregate_to_abi_parts<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    layout: TyAndLayout<'tcx>,
+) -> Option<SmallVec<[(Size, Type); 2]>> {
+    fn flatten<'tcx>(
+        tcx: TyCtxt<'tcx>,
+        layout: TyAndLayout<'tcx>,
+        offset: Size,
+        parts: &mut SmallVec<[(Size, Type); 2]>,
+    ) -> Option<()> {
+        if layout.is_zst() {
+            return Some(());
+        }
+        match layout.abi {
+            Abi::Scalar(scalar) => {
+                parts.push((offset, scalar_to_clif_type(tcx, scalar)));
+                return Some(());
+            }
+            Abi::Vector { .. } if layout.size.bytes() == 16 => {
+                parts.push((offset, vector_to_abi_types(tcx, layout)[0]));
+                return Some(());
+            }
+            Abi::Uninhabited | Abi::Vector { .. } => return None,
+            Abi::ScalarPair(_, _) | Abi::Aggregate { sized: true } => {}
+            Abi::Aggregate { sized: false } => return None,
+        }
+        if !matches!(layout.variants, Variants::Single { .. }) {
+            return None;
+        }
+        match layout.fields {
+            FieldsShape::Primitive | FieldsShape::Union(_) => None,
+            FieldsShape::Array { stride, count } => {
+                let elem = layout.field(&RevealAllLayoutCx(tcx), 0);
+                for i in 0..count {
+                    flatten(tcx, elem, offset + stride * i, parts)?;
+                }
+                Some(())
+            }
+            FieldsShape::Arbitrary { .. } => {
+                for i in 0..layout.fields.count() {
+                    let field = layout.field(&RevealAllLayoutCx(tcx), i);
+                    flatten(tcx, field, offset + layout.fields.offset(i), parts)?;
+                }
+                Some(())
+            }
+        }
+    }
+
+    let mut parts = smallvec![];
+    flatten(tcx, layout, Size::ZERO, &mut parts)?;
+    Some(parts)
+}
+
 fn cast_target_to_abi_params(cast: &CastTarget) -> SmallVec<[AbiParam; 2]> {
     let (rest_count, rem_bytes) = if cast.rest.unit.size.bytes() == 0 {
         (0, 0)
@@ -74,6 +143,24 @@ fn apply_arg_attrs_to_abi_param(mut param: AbiParam, arg_attrs: ArgAttributes) -
     args
 }
 
+/// Compute the offset of each cast target part within the value. Like for an LLVM struct, every
+/// part is placed at its natural alignment. For example `{ f32, i64 }` places the `i64` at offset
+/// 8 rather than 4.
+fn cast_target_offsets(abi_params: &[AbiParam]) -> (SmallVec<[i64; 2]>, u32) {
+    let mut offset = 0u32;
+    let offsets = abi_params
+        .iter()
+        .map(|param| {
+            let size = param.value_type.bytes();
+            offset = (offset + size - 1) / size * size;
+            let param_offset = offset;
+            offset += size;
+            i64::from(param_offset)
+        })
+        .collect();
+    (offsets, offset)
+}
+
 impl<'tcx> ArgAbiExt<'tcx> for ArgAbi<'tcx, Ty<'tcx>> {
     fn get_abi_param(&self, tcx: TyCtxt<'tcx>) -> SmallVec<[AbiParam; 2]> {
         match self.mode {
@@ -84,9 +171,13 @@ impl<'tcx> ArgAbiExt<'tcx> for ArgAbi<'tcx, Ty<'tcx>> {
                     attrs
                 )],
                 Abi::Vector { .. } => {
-                    let vector_ty = crate::intrinsics::clif_vector_type(tcx, self.layout);
-                    smallvec![AbiParam::new(vector_ty)]
+                    vector_to_abi_types(tcx, self.layout).into_iter().map(AbiParam::new).collect()
                 }
+                Abi::Aggregate { .. } => aggregate_to_abi_parts(tcx, self.layout)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(_, ty)| AbiParam::new(ty))
+                    .collect(),
                 _ => unreachable!("{:?}", self.layout.abi),
             },
             PassMode::Pair(attrs_a, attrs_b) => match self.layout.abi {
@@ -101,8 +192,13 @@ impl<'tcx> ArgAbiExt<'tcx> for ArgAbi<'tcx, Ty<'tcx>> {
                 _ => unreachable!("{:?}", self.layout.abi),
             },
             PassMode::Cast { ref cast, pad_i32 } => {
-                assert!(!pad_i32, "padding support not yet implemented");
-                cast_target_to_abi_params(cast)
+                let mut params = cast_target_to_abi_params(cast);
+                if pad_i32 {
+                    // Some ABIs like o32 on MIPS require an unused i32 argument before the actual
+                    // argument to get it correctly aligned.
+                    params.insert(0, AbiParam::new(types::I32));
+                }
+                params
             }
             PassMode::Indirect { attrs, meta_attrs: None, on_stack } => {
                 if on_stack {
@@ -134,10 +230,18 @@ fn get_abi_return(&self, tcx: TyCtxt<'tcx>) -> (Option<AbiParam>, Vec<AbiParam>)
                 Abi::Scalar(scalar) => {
                     (None, vec![AbiParam::new(scalar_to_clif_type(tcx, scalar))])
                 }
-                Abi::Vector { .. } => {
-                    let vector_ty = crate::intrinsics::clif_vector_type(tcx, self.layout);
-                    (None, vec![AbiParam::new(vector_ty)])
-                }
+                Abi::Vector { .. } => (
+                    None,
+                    vector_to_abi_types(tcx, self.layout).into_iter().map(AbiParam::new).collect(),
+                ),
+                Abi::Aggregate { .. } => (
+                    None,
+                    aggregate_to_abi_parts(tcx, self.layout)
+                        .unwrap()
+                        .into_iter()
+                        .map(|(_, ty)| AbiParam::new(ty))
+                        .collect(),
+                ),
                 _ => unreachable!("{:?}", self.layout.abi),
             },
             PassMode::Pair(_, _) => match self.layout.abi {
@@ -162,6 +266,71 @@ fn get_abi_return(&self, tcx: TyCtxt<'tcx>) -> (Option<AbiParam>, Vec<AbiParam>)
     }
 }
 
+/// Check that the way an argument or return value is passed according to the ABI can be
+/// represented in Cranelift IR. Returns a description of the problem if it can't.
+pub(super) fn check_arg_abi<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    arg_abi: &ArgAbi<'tcx, Ty<'tcx>>,
+    is_return: bool,
+) -> Result<(), String> {
+    match arg_abi.mode {
+        PassMode::Ignore => Ok(()),
+        PassMode::Direct(_) => match arg_abi.layout.abi {
+            Abi::Scalar(_) => Ok(()),
+            Abi::Vector { element, count: _ } => {
+                if let Primitive::Int(Integer::I128, _) = element.primitive() {
+                    Err("as vector of 128bit integers".to_owned())
+                } else if is_return && arg_abi.layout.size.bytes() > 32 {
+                    Err("as vector return value larger than 256bit".to_owned())
+                } else {
+                    Ok(())
+                }
+            }
+            Abi::Aggregate { .. } => {
+                if aggregate_to_abi_parts(tcx, arg_abi.layout).is_some() {
+                    Ok(())
+                } else {
+                    Err("directly as aggregate with non-scalar fields".to_owned())
+                }
+            }
+            abi => Err(format!("directly with non-scalar abi {abi:?}")),
+        },
+        PassMode::Pair(_, _) => match arg_abi.layout.abi {
+            Abi::ScalarPair(_, _) => Ok(()),
+            abi => Err(format!("as pair with non-scalar-pair abi {abi:?}")),
+        },
+        PassMode::Cast { ref cast, pad_i32 } => {
+            if is_return && pad_i32 {
+                return Err("with padding as return value".to_owned());
+            }
+            let unit_size = cast.rest.unit.size.bytes();
+            if unit_size != 0
+                && cast.rest.total.bytes() % unit_size != 0
+                && cast.rest.unit.kind != RegKind::Integer
+            {
+                return Err(format!("split into partial {:?} registers", cast.rest.unit.kind));
+            }
+            Ok(())
+        }
+        PassMode::Indirect { meta_attrs: None, on_stack, .. } => {
+            if is_return && on_stack {
+                Err("on the stack as return value".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+        PassMode::Indirect { meta_attrs: Some(_), on_stack, .. } => {
+            if is_return {
+                Err("as unsized return value".to_owned())
+            } else if on_stack {
+                Err("on the stack as unsized value".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
 pub(super) fn to_casted_value<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     arg: CValue<'tcx>,
@@ -169,13 +338,13 @@ pub(super) fn to_casted_value<'tcx>(
 ) -> SmallVec<[Value; 2]> {
     let (ptr, meta) = arg.force_stack(fx);
     assert!(meta.is_none());
-    let mut offset = 0;
-    cast_target_to_abi_params(cast)
+    let abi_params = cast_target_to_abi_params(cast);
+    let (offsets, _) = cast_target_offsets(&abi_params);
+    abi_params
         .into_iter()
-        .map(|param| {
-            let val = ptr.offset_i64(fx, offset).load(fx, param.value_type, MemFlags::new());
-            offset += i64::from(param.value_type.bytes());
-            val
+        .zip(offsets)
+        .map(|(param, offset)| {
+            ptr.offset_i64(fx, offset).load(fx, param.value_type, MemFlags::new())
         })
         .collect()
 }
@@ -187,7 +356,7 @@ pub(super) fn from_casted_value<'tcx>(
     cast: &CastTarget,
 ) -> CValue<'tcx> {
     let abi_params = cast_target_to_abi_params(cast);
-    let abi_param_size: u32 = abi_params.iter().map(|param| param.value_type.bytes()).sum();
+    let (offsets, abi_param_size) = cast_target_offsets(&abi_params);
     let layout_size = u32::try_from(layout.size.bytes()).unwrap();
     let ptr = fx.create_stack_slot(
         // Stack slot size may be bigger for example `[u8; 3]` which is packed into an `i32`.
@@ -196,21 +365,76 @@ pub(super) fn from_casted_value<'tcx>(
         std::cmp::max(abi_param_size, layout_size),
         u32::try_from(layout.align.pref.bytes()).unwrap(),
     );
-    let mut offset = 0;
     let mut block_params_iter = block_params.iter().copied();
-    for param in abi_params {
-        let val = ptr.offset_i64(fx, offset).store(
-            fx,
-            block_params_iter.next().unwrap(),
-            MemFlags::new(),
-        );
-        offset += i64::from(param.value_type.bytes());
-        val
+    for offset in offsets {
+        ptr.offset_i64(fx, offset).store(fx, block_params_iter.next().unwrap(), MemFlags::new());
     }
     assert_eq!(block_params_iter.next(), None, "Leftover block param");
     CValue::by_ref(ptr, layout)
 }
 
+pub(super) fn to_vector_parts<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    arg: CValue<'tcx>,
+) -> SmallVec<[Value; 2]> {
+    let part_tys = vector_to_abi_types(fx.tcx, arg.layout());
+    if part_tys.len() == 1 && arg.layout().size.bytes() == 16 {
+        return smallvec![arg.load_scalar(fx)];
+    }
+
+    let ptr = fx.create_stack_slot(u32::try_from(part_tys.len() * 16).unwrap(), 16);
+    CPlace::for_ptr(ptr, arg.layout()).write_cvalue(fx, arg);
+    part_tys
+        .into_iter()
+        .enumerate()
+        .map(|(i, part_ty)| ptr.offset_i64(fx, i as i64 * 16).load(fx, part_ty, MemFlags::new()))
+        .collect()
+}
+
+pub(super) fn from_vector_parts<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    block_params: &[Value],
+    layout: TyAndLayout<'tcx>,
+) -> CValue<'tcx> {
+    if block_params.len() == 1 && layout.size.bytes() == 16 {
+        return CValue::by_val(block_params[0], layout);
+    }
+
+    let ptr = fx.create_stack_slot(u32::try_from(block_params.len() * 16).unwrap(), 16);
+    for (i, &param) in block_params.iter().enumerate() {
+        ptr.offset_i64(fx, i as i64 * 16).store(fx, param, MemFlags::new());
+    }
+    CValue::by_ref(ptr, layout)
+}
+
+pub(super) fn to_aggregate_parts<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    arg: CValue<'tcx>,
+) -> SmallVec<[Value; 2]> {
+    let parts = aggregate_to_abi_parts(fx.tcx, arg.layout()).unwrap();
+    let (ptr, meta) = arg.force_stack(fx);
+    assert!(meta.is_none());
+    parts
+        .into_iter()
+        .map(|(offset, ty)| ptr.offset_i64(fx, offset.bytes() as i64).load(fx, ty, MemFlags::new()))
+        .collect()
+}
+
+pub(super) fn from_aggregate_parts<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    block_params: &[Value],
+    layout: TyAndLayout<'tcx>,
+) -> CValue<'tcx> {
+    let parts = aggregate_to_abi_parts(fx.tcx, layout).unwrap();
+    assert_eq!(parts.len(), block_params.len());
+    let place = CPlace::new_stack_slot(fx, layout);
+    let ptr = place.to_ptr();
+    for ((offset, _), &param) in parts.into_iter().zip(block_params) {
+        ptr.offset_i64(fx, offset.bytes() as i64).store(fx, param, MemFlags::new());
+    }
+    place.to_cvalue(fx)
+}
+
 /// Get a set of values to be passed as function arguments.
 pub(super) fn adjust_arg_for_abi<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
@@ -221,12 +445,22 @@ pub(super) fn adjust_arg_for_abi<'tcx>(
     assert_assignable(fx, arg.layout().ty, arg_abi.layout.ty, 16);
     match arg_abi.mode {
         PassMode::Ignore => smallvec![],
-        PassMode::Direct(_) => smallvec![arg.load_scalar(fx)],
+        PassMode::Direct(_) => match arg_abi.layout.abi {
+            Abi::Vector { .. } => to_vector_parts(fx, arg),
+            Abi::Aggregate { .. } => to_aggregate_parts(fx, arg),
+            _ => smallvec![arg.load_scalar(fx)],
+        },
         PassMode::Pair(_, _) => {
             let (a, b) = arg.load_scalar_pair(fx);
             smallvec![a, b]
         }
-        PassMode::Cast { ref cast, .. } => to_casted_value(fx, arg, cast),
+        PassMode::Cast { ref cast, pad_i32 } => {
+            let mut vals = to_casted_value(fx, arg, cast);
+            if pad_i32 {
+                vals.insert(0, fx.bcx.ins().iconst(types::I32, 0));
+            }
+            vals
+        }
         PassMode::Indirect { .. } => {
             if is_owned {
                 match arg.force_stack(fx) {
@@ -276,16 +510,21 @@ pub(super) fn cvalue_for_param<'tcx>(
 
     match arg_abi.mode {
         PassMode::Ignore => None,
-        PassMode::Direct(_) => {
-            assert_eq!(block_params.len(), 1, "{:?}", block_params);
-            Some(CValue::by_val(block_params[0], arg_abi.layout))
-        }
+        PassMode::Direct(_) => match arg_abi.layout.abi {
+            Abi::Vector { .. } => Some(from_vector_parts(fx, &block_params, arg_abi.layout)),
+            Abi::Aggregate { .. } => Some(from_aggregate_parts(fx, &block_params, arg_abi.layout)),
+            _ => {
+                assert_eq!(block_params.len(), 1, "{:?}", block_params);
+                Some(CValue::by_val(block_params[0], arg_abi.layout))
+            }
+        },
         PassMode::Pair(_, _) => {
             assert_eq!(block_params.len(), 2, "{:?}", block_params);
             Some(CValue::by_val_pair(block_params[0], block_params[1], arg_abi.layout))
         }
-        PassMode::Cast { ref cast, .. } => {
-            Some(from_casted_value(fx, &block_params, arg_abi.layout, cast))
+        PassMode::Cast { ref cast, pad_i32 } => {
+            let block_params = if pad_i32 { &block_params[1..] } else { &block_params[..] };
+            Some(from_casted_value(fx, block_params, arg_abi.layout, cast))
         }
         PassMode::Indirect { attrs: _, meta_attrs: None, on_stack: _ } => {
             assert_eq!(block_params.len(), 1, "{:?}", block_params);
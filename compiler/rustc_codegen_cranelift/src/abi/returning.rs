@@ -11,7 +11,7 @@ pub(super) fn codegen_return_param<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     ssa_analyzed: &rustc_index::IndexSlice<Local, crate::analyze::SsaKind>,
     block_params_iter: &mut impl Iterator<Item = Value>,
-) -> CPlace<'tcx> {
+) -> CodegenResult<CPlace<'tcx>> {
     let (ret_place, ret_param): (_, SmallVec<[_; 2]>) = match fx.fn_abi.as_ref().unwrap().ret.mode {
         PassMode::Ignore | PassMode::Direct(_) | PassMode::Pair(_, _) | PassMode::Cast { .. } => {
             let is_ssa =
@@ -22,7 +22,7 @@ pub(super) fn codegen_return_param<'tcx>(
                     RETURN_PLACE,
                     fx.fn_abi.as_ref().unwrap().ret.layout,
                     is_ssa,
-                ),
+                )?,
                 smallvec![],
             )
         }
@@ -49,7 +49,7 @@ pub(super) fn codegen_return_param<'tcx>(
         fx.fn_abi.as_ref().unwrap().ret.layout,
     );
 
-    ret_place
+    Ok(ret_place)
 }
 
 /// Invokes the closure with if necessary a value representing the return pointer. When the closure
@@ -58,8 +58,8 @@ pub(super) fn codegen_with_call_return_arg<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     ret_arg_abi: &ArgAbi<'tcx, Ty<'tcx>>,
     ret_place: CPlace<'tcx>,
-    f: impl FnOnce(&mut FunctionCx<'_, '_, 'tcx>, Option<Value>) -> Inst,
-) {
+    f: impl FnOnce(&mut FunctionCx<'_, '_, 'tcx>, Option<Value>) -> CodegenResult<Inst>,
+) -> CodegenResult {
     let (ret_temp_place, return_ptr) = match ret_arg_abi.mode {
         PassMode::Ignore => (None, None),
         PassMode::Indirect { attrs: _, meta_attrs: None, on_stack: _ } => {
@@ -79,14 +79,37 @@ pub(super) fn codegen_with_call_return_arg<'tcx>(
         PassMode::Direct(_) | PassMode::Pair(_, _) | PassMode::Cast { .. } => (None, None),
     };
 
-    let call_inst = f(fx, return_ptr);
+    let call_inst = f(fx, return_ptr)?;
 
     match ret_arg_abi.mode {
         PassMode::Ignore => {}
-        PassMode::Direct(_) => {
-            let ret_val = fx.bcx.inst_results(call_inst)[0];
-            ret_place.write_cvalue(fx, CValue::by_val(ret_val, ret_arg_abi.layout));
-        }
+        PassMode::Direct(_) => match ret_arg_abi.layout.abi {
+            Abi::Vector { .. } => {
+                let results = fx
+                    .bcx
+                    .inst_results(call_inst)
+                    .iter()
+                    .copied()
+                    .collect::<SmallVec<[Value; 2]>>();
+                let result = super::pass_mode::from_vector_parts(fx, &results, ret_arg_abi.layout);
+                ret_place.write_cvalue(fx, result);
+            }
+            Abi::Aggregate { .. } => {
+                let results = fx
+                    .bcx
+                    .inst_results(call_inst)
+                    .iter()
+                    .copied()
+                    .collect::<SmallVec<[Value; 2]>>();
+                let result =
+                    super::pass_mode::from_aggregate_parts(fx, &results, ret_arg_abi.layout);
+                ret_place.write_cvalue(fx, result);
+            }
+            _ => {
+                let ret_val = fx.bcx.inst_results(call_inst)[0];
+                ret_place.write_cvalue(fx, CValue::by_val(ret_val, ret_arg_abi.layout));
+            }
+        },
         PassMode::Pair(_, _) => {
             let ret_val_a = fx.bcx.inst_results(call_inst)[0];
             let ret_val_b = fx.bcx.inst_results(call_inst)[1];
@@ -111,6 +134,8 @@ pub(super) fn codegen_with_call_return_arg<'tcx>(
             unreachable!("unsized return value")
         }
     }
+
+    Ok(())
 }
 
 /// Codegen a return instruction with the right return value(s) if any.
@@ -124,8 +149,21 @@ pub(crate) fn codegen_return(fx: &mut FunctionCx<'_, '_, '_>) {
         }
         PassMode::Direct(_) => {
             let place = fx.get_local_place(RETURN_PLACE);
-            let ret_val = place.to_cvalue(fx).load_scalar(fx);
-            fx.bcx.ins().return_(&[ret_val]);
+            let ret_val = place.to_cvalue(fx);
+            match ret_val.layout().abi {
+                Abi::Vector { .. } => {
+                    let ret_vals = super::pass_mode::to_vector_parts(fx, ret_val);
+                    fx.bcx.ins().return_(&ret_vals);
+                }
+                Abi::Aggregate { .. } => {
+                    let ret_vals = super::pass_mode::to_aggregate_parts(fx, ret_val);
+                    fx.bcx.ins().return_(&ret_vals);
+                }
+                _ => {
+                    let ret_val = ret_val.load_scalar(fx);
+                    fx.bcx.ins().return_(&[ret_val]);
+                }
+            }
         }
         PassMode::Pair(_, _) => {
             let place = fx.get_local_place(RETURN_PLACE);
@@ -0,0 +1,432 @@
+//! Support for defining C-variadic functions.
+//!
+//! Cranelift doesn't know about variadic functions. On x86_64 System V all argument registers not
+//! used by the fixed arguments are added as extra parameters to the signature of a variadic
+//! function, followed by a stack argument whose address is the start of the variadic arguments
+//! passed on the stack. The prelude spills the registers into a register save area the same way a
+//! C compiler would. `va_arg` then reads the variadic arguments from either this area or the
+//! stack.
+//!
+//! For calls to variadic functions the signature of the call is replaced with one matching the
+//! actual arguments.
+
+use std::fmt::Write;
+
+use cranelift_codegen::ir::{AbiParam, ArgumentPurpose, SigRef};
+use target_lexicon::BinaryFormat;
+
+use super::CallTarget;
+use crate::prelude::*;
+
+const SYSV_GP_ARG_REGS: u32 = 6;
+const SYSV_FP_ARG_REGS: u32 = 8;
+
+/// Offset of the first xmm register within the register save area.
+const REG_SAVE_AREA_FP_OFFSET: u32 = SYSV_GP_ARG_REGS * 8;
+const REG_SAVE_AREA_SIZE: u32 = REG_SAVE_AREA_FP_OFFSET + SYSV_FP_ARG_REGS * 16;
+
+// Offsets of the fields of `VaListImpl` on x86_64 System V.
+const VA_LIST_GP_OFFSET: i32 = 0;
+const VA_LIST_FP_OFFSET: i32 = 4;
+const VA_LIST_OVERFLOW_ARG_AREA: i32 = 8;
+const VA_LIST_REG_SAVE_AREA: i32 = 16;
+
+fn is_x86_64_sysv(tcx: TyCtxt<'_>) -> bool {
+    tcx.sess.target.arch == "x86_64" && !tcx.sess.target.is_like_windows
+}
+
+fn supports_variadic_definitions(tcx: TyCtxt<'_>) -> bool {
+    is_x86_64_sysv(tcx)
+}
+
+/// Returns true if floats passed as variadic arguments use the same registers as for fixed
+/// arguments.
+fn supports_float_varargs(tcx: TyCtxt<'_>) -> bool {
+    is_x86_64_sysv(tcx) || (tcx.sess.target.arch == "aarch64" && !tcx.sess.target.is_like_osx)
+}
+
+/// The amount of argument registers used by the fixed parameters of a function.
+struct ArgRegUsage {
+    gp: u32,
+    fp: u32,
+    /// Some fixed parameters are passed on the stack.
+    stack: bool,
+}
+
+fn arg_reg_usage(params: &[AbiParam]) -> ArgRegUsage {
+    let mut usage = ArgRegUsage { gp: 0, fp: 0, stack: false };
+    for param in params {
+        if let ArgumentPurpose::StructArgument(_) = param.purpose {
+            usage.stack = true;
+        } else if param.value_type.is_float() || param.value_type.is_vector() {
+            usage.fp += 1;
+        } else {
+            usage.gp += param.value_type.bytes().div_ceil(8);
+        }
+    }
+    usage.stack |= usage.gp > SYSV_GP_ARG_REGS || usage.fp > SYSV_FP_ARG_REGS;
+    usage
+}
+
+/// Add parameters for all argument registers which are not used by the fixed parameters of a
+/// variadic function and for the variadic arguments passed on the stack.
+pub(super) fn add_vararg_params(tcx: TyCtxt<'_>, sig: &mut Signature) {
+    if !supports_variadic_definitions(tcx) {
+        return;
+    }
+
+    let usage = arg_reg_usage(&sig.params);
+    for _ in usage.gp..SYSV_GP_ARG_REGS {
+        sig.params.push(AbiParam::new(types::I64));
+    }
+    for _ in usage.fp..SYSV_FP_ARG_REGS {
+        sig.params.push(AbiParam::new(types::F64));
+    }
+    sig.params.push(AbiParam::special(pointer_ty(tcx), ArgumentPurpose::StructArgument(8)));
+}
+
+/// Spill the registers which may contain variadic arguments and initialize `va_list` to point to
+/// the first variadic argument. `vararg_params` are the params added by [`add_vararg_params`].
+pub(super) fn codegen_va_list_init<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    va_list: CPlace<'tcx>,
+    vararg_params: &[Value],
+) -> CodegenResult {
+    if !supports_variadic_definitions(fx.tcx) {
+        return Err(fx.unsupported(
+            fx.mir.span,
+            "Defining variadic functions is not yet supported by Cranelift on this target",
+        ));
+    }
+
+    let params = &fx.bcx.func.signature.params;
+    let usage = arg_reg_usage(&params[..params.len() - vararg_params.len()]);
+    if usage.stack {
+        return Err(fx.unsupported(
+            fx.mir.span,
+            "Defining variadic functions with fixed arguments passed on the stack is not yet \
+            supported by Cranelift",
+        ));
+    }
+
+    let (&overflow_arg_area, reg_params) = vararg_params.split_last().unwrap();
+    let reg_save_area = fx.create_stack_slot(REG_SAVE_AREA_SIZE, 16);
+    let (gp_params, fp_params) = reg_params.split_at((SYSV_GP_ARG_REGS - usage.gp) as usize);
+    for (reg, &param) in (usage.gp..).zip(gp_params) {
+        reg_save_area.offset_i64(fx, i64::from(reg) * 8).store(fx, param, MemFlags::trusted());
+    }
+    for (reg, &param) in (usage.fp..).zip(fp_params) {
+        reg_save_area.offset_i64(fx, i64::from(REG_SAVE_AREA_FP_OFFSET + reg * 16)).store(
+            fx,
+            param,
+            MemFlags::trusted(),
+        );
+    }
+
+    let reg_save_area = reg_save_area.get_addr(fx);
+    let gp_offset = fx.bcx.ins().iconst(types::I32, i64::from(usage.gp * 8));
+    let fp_offset =
+        fx.bcx.ins().iconst(types::I32, i64::from(REG_SAVE_AREA_FP_OFFSET + usage.fp * 16));
+
+    let va_list = va_list.to_ptr();
+    for (offset, val) in [
+        (VA_LIST_GP_OFFSET, gp_offset),
+        (VA_LIST_FP_OFFSET, fp_offset),
+        (VA_LIST_OVERFLOW_ARG_AREA, overflow_arg_area),
+        (VA_LIST_REG_SAVE_AREA, reg_save_area),
+    ] {
+        va_list.offset_i64(fx, offset.into()).store(fx, val, MemFlags::trusted());
+    }
+
+    Ok(())
+}
+
+/// Codegen the `va_arg` intrinsic. `va_list` is a pointer to the `VaListImpl` to read the next
+/// argument from.
+pub(crate) fn codegen_va_arg<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    span: Span,
+    va_list: Value,
+    ret: CPlace<'tcx>,
+) -> CodegenResult {
+    if !supports_variadic_definitions(fx.tcx) {
+        return Err(
+            fx.unsupported(span, "`va_arg` is not yet supported by Cranelift on this target")
+        );
+    }
+
+    let layout = ret.layout();
+    let (offset_field, reg_save_area_end, reg_size) = match layout.ty.kind() {
+        ty::Int(_) | ty::Uint(_) | ty::RawPtr(_) if layout.size.bytes() <= 8 => {
+            (VA_LIST_GP_OFFSET, REG_SAVE_AREA_FP_OFFSET, 8)
+        }
+        ty::Float(FloatTy::F64) => (VA_LIST_FP_OFFSET, REG_SAVE_AREA_SIZE, 16),
+        _ => {
+            return Err(fx.unsupported(
+                span,
+                format!("`va_arg` for type `{:?}` is not yet supported by Cranelift", layout.ty),
+            ));
+        }
+    };
+    let clif_ty = fx.clif_type(layout.ty).unwrap();
+
+    let va_list = Pointer::new(va_list);
+    let offset_ptr = va_list.offset_i64(fx, offset_field.into());
+    let overflow_arg_area_ptr = va_list.offset_i64(fx, VA_LIST_OVERFLOW_ARG_AREA.into());
+
+    let reg_block = fx.bcx.create_block();
+    let stack_block = fx.bcx.create_block();
+    let done_block = fx.bcx.create_block();
+    let arg_addr = fx.bcx.append_block_param(done_block, fx.pointer_type);
+
+    let offset = offset_ptr.load(fx, types::I32, MemFlags::trusted());
+    let in_reg_save_area =
+        fx.bcx.ins().icmp_imm(IntCC::UnsignedLessThan, offset, i64::from(reg_save_area_end));
+    fx.bcx.ins().brif(in_reg_save_area, reg_block, &[], stack_block, &[]);
+
+    fx.bcx.switch_to_block(reg_block);
+    let reg_save_area = va_list.offset_i64(fx, VA_LIST_REG_SAVE_AREA.into()).load(
+        fx,
+        fx.pointer_type,
+        MemFlags::trusted(),
+    );
+    let offset_ext = fx.bcx.ins().uextend(fx.pointer_type, offset);
+    let addr = fx.bcx.ins().iadd(reg_save_area, offset_ext);
+    let next_offset = fx.bcx.ins().iadd_imm(offset, reg_size);
+    offset_ptr.store(fx, next_offset, MemFlags::trusted());
+    fx.bcx.ins().jump(done_block, &[addr]);
+
+    // All arguments supported by `va_arg` take a single eightbyte on the stack.
+    fx.bcx.switch_to_block(stack_block);
+    let overflow_arg_area = overflow_arg_area_ptr.load(fx, fx.pointer_type, MemFlags::trusted());
+    let next_overflow_arg_area = fx.bcx.ins().iadd_imm(overflow_arg_area, 8);
+    overflow_arg_area_ptr.store(fx, next_overflow_arg_area, MemFlags::trusted());
+    fx.bcx.ins().jump(done_block, &[overflow_arg_area]);
+
+    fx.bcx.seal_block(reg_block);
+    fx.bcx.seal_block(stack_block);
+    fx.bcx.seal_block(done_block);
+    fx.bcx.switch_to_block(done_block);
+    let val = Pointer::new(arg_addr).load(fx, clif_ty, MemFlags::trusted());
+    ret.write_cvalue(fx, CValue::by_val(val, layout));
+
+    Ok(())
+}
+
+/// Replace the signature used to call a variadic function through `target` with one matching the
+/// actual arguments `call_args`. Returns the call target to use instead of `target`.
+pub(super) fn adjust_variadic_call<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    span: Span,
+    callee: Option<Instance<'tcx>>,
+    target: CallTarget,
+    call_args: &mut Vec<Value>,
+) -> CodegenResult<CallTarget> {
+    let sig_ref = match target {
+        CallTarget::Direct(func_ref) => fx.bcx.func.dfg.ext_funcs[func_ref].signature,
+        CallTarget::Indirect(sig_ref, _) => sig_ref,
+    };
+    let mut abi_params = Vec::with_capacity(call_args.len());
+    for &arg in &*call_args {
+        let ty = fx.bcx.func.dfg.value_type(arg);
+        if !(ty.is_int() || ty == types::F64 && supports_float_varargs(fx.tcx)) {
+            return Err(fx.unsupported(span, format!("Non int ty {:?} for variadic call", ty)));
+        }
+        abi_params.push(AbiParam::new(ty));
+    }
+    fx.bcx.func.dfg.signatures[sig_ref].params = abi_params;
+
+    if is_x86_64_sysv(fx.tcx) {
+        return set_vector_reg_count(fx, span, callee, target, sig_ref, call_args);
+    }
+
+    Ok(target)
+}
+
+/// On x86_64 System V `al` has to contain an upper bound of the number of vector registers used by
+/// a call to a variadic function. Cranelift can't set `al`, so when vector registers are used
+/// the call is redirected to a trampoline which sets `al` and then jumps to the actual callee.
+///
+/// Trampolines are shared between all calls with the same callee and vector register count. For
+/// indirect calls the callee is passed to the trampoline as extra argument after all other
+/// arguments. The variadic callee ignores it. Trampolines are emitted at most once per codegen
+/// unit and are weak definitions, so duplicates from other codegen units are merged by the linker.
+fn set_vector_reg_count<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    span: Span,
+    callee: Option<Instance<'tcx>>,
+    target: CallTarget,
+    sig_ref: SigRef,
+    call_args: &mut Vec<Value>,
+) -> CodegenResult<CallTarget> {
+    let params = &fx.bcx.func.dfg.signatures[sig_ref].params;
+    let vector_regs = params
+        .iter()
+        .filter(|param| param.value_type.is_float() || param.value_type.is_vector())
+        .count()
+        .min(SYSV_FP_ARG_REGS as usize);
+    if vector_regs == 0 {
+        return Ok(target);
+    }
+
+    if let Some(Instance { def: InstanceDef::Item(def_id), .. }) = callee {
+        // Variadic functions defined in the current crate are codegened by cg_clif, which doesn't
+        // look at `al`.
+        if def_id.is_local() && !fx.tcx.is_foreign_item(def_id) {
+            return Ok(target);
+        }
+    }
+
+    if fx.cx.is_jit {
+        return Err(fx.unsupported(
+            span,
+            "Variadic calls with float arguments are not supported in JIT mode",
+        ));
+    }
+
+    let (trampoline_name, jump_target) = match target {
+        CallTarget::Direct(_) => {
+            let callee_name = fx.tcx.symbol_name(callee.unwrap()).name;
+            (format!("{callee_name}_n{vector_regs}"), JumpTarget::Symbol(callee_name))
+        }
+        CallTarget::Indirect(_, func_ptr) => {
+            let Some(location) = extra_arg_location(params) else {
+                return Err(fx.unsupported(
+                    span,
+                    "Indirect variadic calls with float and 128bit int arguments are not yet \
+                    supported by Cranelift",
+                ));
+            };
+            fx.bcx.func.dfg.signatures[sig_ref].params.push(AbiParam::new(fx.pointer_type));
+            call_args.push(func_ptr);
+            let location_name = match location {
+                ArgLocation::Reg(reg) => reg.to_owned(),
+                ArgLocation::Stack(offset) => format!("stack{offset}"),
+            };
+            (format!("indirect_{location_name}_n{vector_regs}"), JumpTarget::Arg(location))
+        }
+    };
+    let trampoline_name = format!("__cg_clif_variadic_call_{trampoline_name}");
+
+    // Declaring the trampoline and emitting it happen together, so a trampoline which is already
+    // declared has already been emitted for this codegen unit.
+    if fx.module.get_name(&trampoline_name).is_none() {
+        emit_trampoline(fx, span, &trampoline_name, vector_regs, jump_target)?;
+    }
+
+    // The trampoline is called with different signatures, so it is declared without any params
+    // and called indirectly.
+    let trampoline = fx
+        .module
+        .declare_function(
+            &trampoline_name,
+            Linkage::Import,
+            &Signature::new(fx.target_config.default_call_conv),
+        )
+        .unwrap();
+    let trampoline = fx.module.declare_func_in_func(trampoline, fx.bcx.func);
+    if fx.clif_comments.enabled() {
+        fx.add_comment(trampoline, format!("variadic call trampoline {trampoline_name}"));
+    }
+    let trampoline = fx.bcx.ins().func_addr(fx.pointer_type, trampoline);
+    Ok(CallTarget::Indirect(sig_ref, trampoline))
+}
+
+/// Location of an argument at the entry of the callee.
+enum ArgLocation {
+    Reg(&'static str),
+    /// Offset from the stack pointer.
+    Stack(u32),
+}
+
+/// The location in which an extra pointer argument is passed after the arguments `params`.
+/// 128bit ints are not supported.
+fn extra_arg_location(params: &[AbiParam]) -> Option<ArgLocation> {
+    const SYSV_GP_ARG_REG_NAMES: [&str; SYSV_GP_ARG_REGS as usize] =
+        ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+    let mut gp = 0;
+    let mut fp = 0;
+    let mut stack = 0;
+    for param in params {
+        if param.value_type.is_float() {
+            if fp < SYSV_FP_ARG_REGS {
+                fp += 1;
+            } else {
+                stack += 1;
+            }
+        } else if param.value_type.bytes() > 8 {
+            return None;
+        } else if gp < SYSV_GP_ARG_REGS {
+            gp += 1;
+        } else {
+            stack += 1;
+        }
+    }
+
+    Some(if gp < SYSV_GP_ARG_REGS {
+        ArgLocation::Reg(SYSV_GP_ARG_REG_NAMES[gp as usize])
+    } else {
+        // Stack arguments start right after the return address.
+        ArgLocation::Stack(8 + stack * 8)
+    })
+}
+
+/// Where a variadic call trampoline jumps to.
+enum JumpTarget<'a> {
+    Symbol(&'a str),
+    /// The callee is passed as argument at the given location.
+    Arg(ArgLocation),
+}
+
+/// Emit a trampoline named `name` which sets `al` to `vector_regs` and then jumps to `target`.
+fn emit_trampoline(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    span: Span,
+    name: &str,
+    vector_regs: usize,
+    target: JumpTarget<'_>,
+) -> CodegenResult {
+    let binary_format = crate::target_triple(fx.tcx.sess).binary_format;
+    let symbol_prefix = match binary_format {
+        BinaryFormat::Elf => "",
+        BinaryFormat::Macho => "_",
+        _ => {
+            return Err(fx.unsupported(
+                span,
+                format!(
+                    "Variadic calls with float arguments are not supported for {binary_format:?}"
+                ),
+            ));
+        }
+    };
+
+    let generated_asm = &mut fx.cx.global_asm;
+    if binary_format == BinaryFormat::Elf {
+        writeln!(generated_asm, ".section .text.{name},\"axG\",@progbits,{name},comdat").unwrap();
+        writeln!(generated_asm, ".weak {name}").unwrap();
+        writeln!(generated_asm, ".hidden {name}").unwrap();
+        writeln!(generated_asm, ".type {name},@function").unwrap();
+    } else {
+        writeln!(generated_asm, ".globl _{name}").unwrap();
+        writeln!(generated_asm, ".weak_definition _{name}").unwrap();
+        writeln!(generated_asm, ".private_extern _{name}").unwrap();
+    }
+    writeln!(generated_asm, "{symbol_prefix}{name}:").unwrap();
+    writeln!(generated_asm, "    mov al, {vector_regs}").unwrap();
+    match target {
+        JumpTarget::Symbol(symbol) => writeln!(generated_asm, "    jmp {symbol_prefix}{symbol}"),
+        JumpTarget::Arg(ArgLocation::Reg(reg)) => writeln!(generated_asm, "    jmp {reg}"),
+        JumpTarget::Arg(ArgLocation::Stack(offset)) => {
+            writeln!(generated_asm, "    jmp qword ptr [rsp + {offset}]")
+        }
+    }
+    .unwrap();
+    if binary_format == BinaryFormat::Elf {
+        writeln!(generated_asm, ".size {name}, .-{name}").unwrap();
+        generated_asm.push_str(".text\n");
+    }
+    generated_asm.push('\n');
+
+    Ok(())
+}
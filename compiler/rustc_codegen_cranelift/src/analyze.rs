@@ -1,6 +1,7 @@
 //! SSA analysis
 
 use rustc_index::IndexVec;
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
 use rustc_middle::mir::StatementKind::*;
 use rustc_middle::ty::Ty;
 
@@ -38,3 +39,43 @@ pub(crate) fn analyze(fx: &FunctionCx<'_, '_, '_>) -> IndexVec<Local, SsaKind> {
 
     flag_map
 }
+
+/// Find the locals which are assigned a constant exactly once and are never written or borrowed
+/// otherwise, like the index rustc generates for `array[2]`. Arguments are written by the caller, so
+/// they are never included.
+pub(crate) fn constant_locals<'tcx>(
+    mir: &'tcx Body<'tcx>,
+) -> FxHashMap<Local, &'tcx ConstOperand<'tcx>> {
+    struct WriteCounter {
+        writes: IndexVec<Local, u32>,
+    }
+
+    impl<'tcx> Visitor<'tcx> for WriteCounter {
+        fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+            if context.is_mutating_use() || context.is_borrow() || context.is_address_of() {
+                self.writes[local] += 1;
+            }
+        }
+    }
+
+    let mut write_counter =
+        WriteCounter { writes: mir.local_decls.iter().map(|_| 0).collect::<IndexVec<Local, _>>() };
+    write_counter.visit_body(mir);
+
+    let mut constant_locals = FxHashMap::default();
+    for bb in mir.basic_blocks.iter() {
+        for stmt in bb.statements.iter() {
+            if let Assign(place_and_rval) = &stmt.kind {
+                if let (Some(local), Rvalue::Use(Operand::Constant(constant))) =
+                    (place_and_rval.0.as_local(), &place_and_rval.1)
+                {
+                    if local.as_usize() > mir.arg_count && write_counter.writes[local] == 1 {
+                        constant_locals.insert(local, &**constant);
+                    }
+                }
+            }
+        }
+    }
+
+    constant_locals
+}
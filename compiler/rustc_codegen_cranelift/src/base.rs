@@ -4,13 +4,15 @@
 use cranelift_codegen::CodegenError;
 use cranelift_module::ModuleError;
 use rustc_ast::InlineAsmOptions;
+use rustc_data_structures::fx::FxHashSet;
 use rustc_index::IndexVec;
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::ty::adjustment::PointerCoercion;
 use rustc_middle::ty::layout::FnAbiOf;
 use rustc_middle::ty::print::with_no_trimmed_paths;
 
 use crate::constant::ConstantCx;
-use crate::debuginfo::FunctionDebugContext;
+use crate::debuginfo::{FunctionDebugContext, TypeDebugContext};
 use crate::prelude::*;
 use crate::pretty_clif::CommentWriter;
 
@@ -25,6 +27,7 @@ pub(crate) struct CodegenedFunction {
 pub(crate) fn codegen_fn<'tcx>(
     tcx: TyCtxt<'tcx>,
     cx: &mut crate::CodegenCx,
+    type_dbg: &mut TypeDebugContext<'tcx>,
     cached_func: Function,
     module: &mut dyn Module,
     instance: Instance<'tcx>,
@@ -91,17 +94,30 @@ pub(crate) fn codegen_fn<'tcx>(
         bcx,
         block_map,
         local_map: IndexVec::with_capacity(mir.local_decls.len()),
+        constant_locals: crate::analyze::constant_locals(mir),
         caller_location: None, // set by `codegen_fn_prelude`
 
         clif_comments,
         last_source_file: None,
         next_ssa_var: 0,
+        current_bb: None,
+        data_addrs: FxHashMap::default(),
     };
 
-    tcx.prof.generic_activity("codegen clif ir").run(|| codegen_fn_body(&mut fx, start_block));
+    tcx.prof.generic_activity("codegen clif ir").run(|| {
+        if let Err(unsupported) = codegen_fn_body(&mut fx, start_block) {
+            crate::unsupported::replace_body_with_trap(&mut fx, unsupported);
+        }
+    });
     fx.bcx.seal_all_blocks();
     fx.bcx.finalize();
 
+    if let (Some(debug_context), Some(func_debug_cx)) =
+        (&mut fx.cx.debug_context, &mut fx.func_debug_cx)
+    {
+        func_debug_cx.define_local_variables(debug_context, tcx, type_dbg, mir, &fx.local_map);
+    }
+
     // Recover all necessary data from fx, before accessing func will prevent future access to it.
     let symbol_name = fx.symbol_name;
     let clif_comments = fx.clif_comments;
@@ -126,6 +142,41 @@ pub(crate) fn codegen_fn<'tcx>(
     CodegenedFunction { symbol_name, func_id, func, clif_comments, func_debug_cx }
 }
 
+/// Returns whether `instance` is a `#[naked]` function. These are codegened by
+/// [`codegen_naked_fn`] rather than [`codegen_fn`].
+pub(crate) fn is_naked_fn<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> bool {
+    matches!(instance.def, InstanceDef::Item(_))
+        && tcx.codegen_fn_attrs(instance.def_id()).flags.contains(CodegenFnAttrFlags::NAKED)
+}
+
+/// Codegen a `#[naked]` function.
+///
+/// The body of a naked function consists of a single `asm!` block which must not be surrounded by
+/// a prologue, an epilogue or any stack slots. As Cranelift always emits those, the asm block is
+/// emitted as the entire function into the global asm instead. `predefine_mono_items` declares
+/// naked functions as imports, so that references from Cranelift generated code are resolved by
+/// the linker.
+pub(crate) fn codegen_naked_fn<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cx: &mut crate::CodegenCx,
+    module: &mut dyn Module,
+    instance: Instance<'tcx>,
+    linkage: Linkage,
+) {
+    debug_assert!(!instance.args.has_infer());
+
+    let symbol_name = tcx.symbol_name(instance).name;
+    let _timer = tcx.prof.generic_activity_with_arg("codegen naked fn", symbol_name);
+
+    let mir = tcx.instance_mir(instance.def);
+    let terminator = mir.basic_blocks[START_BLOCK].terminator();
+    if mir.basic_blocks.len() != 1 || !matches!(terminator.kind, TerminatorKind::InlineAsm { .. }) {
+        span_bug!(mir.span, "naked function body is not a single asm block");
+    }
+
+    crate::inline_asm::codegen_naked_asm(tcx, cx, module, instance, linkage, terminator);
+}
+
 pub(crate) fn compile_fn(
     cx: &mut crate::CodegenCx,
     cached_context: &mut Context,
@@ -172,7 +223,7 @@ pub(crate) fn compile_fn(
 
     // Define function
     cx.profiler.generic_activity("define function").run(|| {
-        context.want_disasm = cx.should_write_ir;
+        context.want_disasm = cx.should_write_ir || cx.assembly.is_some();
         match module.define_function(codegened_func.func_id, context) {
             Ok(()) => {}
             Err(ModuleError::Compilation(CodegenError::ImplLimitExceeded)) => {
@@ -210,6 +261,12 @@ pub(crate) fn compile_fn(
         }
     }
 
+    if let Some(assembly) = &mut cx.assembly {
+        if let Some(disasm) = &context.compiled_code().unwrap().vcode {
+            assembly.push_str(&format!("{}:\n{}\n", codegened_func.symbol_name, disasm));
+        }
+    }
+
     // Define debuginfo for function
     let isa = module.isa();
     let debug_context = &mut cx.debug_context;
@@ -248,7 +305,12 @@ pub(crate) fn verify_func(
     });
 }
 
-fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
+fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) -> CodegenResult {
+    let default_call_conv = fx.target_config.default_call_conv;
+    if let Err(err) = crate::abi::check_fn_abi(fx.tcx, default_call_conv, fx.fn_abi.unwrap()) {
+        return Err(fx.unsupported(fx.mir.span, err));
+    }
+
     let arg_uninhabited = fx
         .mir
         .args_iter()
@@ -257,16 +319,17 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
         fx.bcx.append_block_params_for_function_params(fx.block_map[START_BLOCK]);
         fx.bcx.switch_to_block(fx.block_map[START_BLOCK]);
         fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
-        return;
+        return Ok(());
     }
     fx.tcx
         .prof
         .generic_activity("codegen prelude")
-        .run(|| crate::abi::codegen_fn_prelude(fx, start_block));
+        .run(|| crate::abi::codegen_fn_prelude(fx, start_block))?;
 
     for (bb, bb_data) in fx.mir.basic_blocks.iter_enumerated() {
         let block = fx.get_block(bb);
         fx.bcx.switch_to_block(block);
+        fx.current_bb = Some(bb);
 
         if bb_data.is_cleanup {
             // Unwinding after panicking is not supported
@@ -279,7 +342,7 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
         fx.bcx.ins().nop();
         for stmt in &bb_data.statements {
             fx.set_debug_loc(stmt.source_info);
-            codegen_stmt(fx, block, stmt);
+            codegen_stmt(fx, block, stmt)?;
         }
 
         if fx.clif_comments.enabled() {
@@ -411,6 +474,34 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                             fx.bcx.ins().brif(discr, then_block, &[], else_block, &[]);
                         }
                     }
+                } else if fx.bcx.func.dfg.value_type(discr) == types::I128 {
+                    // `Switch` sign extends values between 2^63 and 2^64 when comparing them
+                    // against 128bit values, so emit a compare chain instead.
+                    for (value, block) in targets.iter() {
+                        let block = fx.get_block(block);
+                        let next_block = fx.bcx.create_block();
+                        let lsb = fx.bcx.ins().iconst(types::I64, value as u64 as i64);
+                        let msb = fx.bcx.ins().iconst(types::I64, (value >> 64) as u64 as i64);
+                        let value = fx.bcx.ins().iconcat(lsb, msb);
+                        let is_eq = fx.bcx.ins().icmp(IntCC::Equal, discr, value);
+                        fx.bcx.ins().brif(is_eq, block, &[], next_block, &[]);
+                        fx.bcx.switch_to_block(next_block);
+                    }
+                    let otherwise_block = fx.get_block(targets.otherwise());
+                    fx.bcx.ins().jump(otherwise_block, &[]);
+                } else if targets.iter().count() == 1 {
+                    // Compare against the single value directly rather than using `Switch`, so
+                    // that a discriminant read doesn't need to be extended before comparing it.
+                    let (value, then_block) = targets.iter().next().unwrap();
+                    let then_block = fx.get_block(then_block);
+                    let else_block = fx.get_block(targets.otherwise());
+                    let is_eq = crate::optimize::peephole::icmp_imm_narrowed(
+                        &mut fx.bcx,
+                        IntCC::Equal,
+                        discr,
+                        value as i64,
+                    );
+                    fx.bcx.ins().brif(is_eq, then_block, &[], else_block, &[]);
                 } else {
                     let mut switch = ::cranelift_frontend::Switch::new();
                     for (value, block) in targets.iter() {
@@ -418,6 +509,19 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                         switch.set_entry(value, block);
                     }
                     let otherwise_block = fx.get_block(targets.otherwise());
+                    // `Switch` emits a separate jump table for every contiguous range of values
+                    // and a binary search to select between them. For dense switches fill the
+                    // holes with the otherwise block, so that a single jump table covers all
+                    // values.
+                    if let Some((min, max)) = dense_switch_range(targets) {
+                        let values =
+                            targets.iter().map(|(value, _)| value).collect::<FxHashSet<_>>();
+                        for value in min..=max {
+                            if !values.contains(&value) {
+                                switch.set_entry(value, otherwise_block);
+                            }
+                        }
+                    }
                     switch.emit(&mut fx.bcx, discr, otherwise_block);
                 }
             }
@@ -427,6 +531,8 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                 destination,
                 target,
                 fn_span,
+                // FIXME `UnwindAction::Terminate` should abort when a foreign exception unwinds
+                // out of an `extern "C-unwind"` call, but that requires landing pads.
                 unwind: _,
                 call_source: _,
             } => {
@@ -439,7 +545,7 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                         *destination,
                         *target,
                     )
-                });
+                })?;
             }
             TerminatorKind::InlineAsm {
                 template,
@@ -450,10 +556,10 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                 unwind: _,
             } => {
                 if options.contains(InlineAsmOptions::MAY_UNWIND) {
-                    fx.tcx.sess.span_fatal(
+                    return Err(fx.unsupported(
                         source_info.span,
                         "cranelift doesn't support unwinding from inline assembly.",
-                    );
+                    ));
                 }
 
                 crate::inline_asm::codegen_inline_asm_terminator(
@@ -490,13 +596,34 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
             }
         };
     }
+
+    Ok(())
+}
+
+/// Returns the range of values covered by a `SwitchInt` if it is dense enough to be lowered to a
+/// single jump table.
+fn dense_switch_range(targets: &SwitchTargets) -> Option<(u128, u128)> {
+    let count = targets.iter().count() as u128;
+    if count < 4 {
+        return None;
+    }
+
+    let min = targets.iter().map(|(value, _)| value).min().unwrap();
+    let max = targets.iter().map(|(value, _)| value).max().unwrap();
+    let span = max - min + 1;
+    if span == count || span > count * 2 {
+        // Either already contiguous or too sparse for a jump table.
+        return None;
+    }
+
+    Some((min, max))
 }
 
 fn codegen_stmt<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     #[allow(unused_variables)] cur_block: Block,
     stmt: &Statement<'tcx>,
-) {
+) -> CodegenResult {
     let _print_guard = crate::PrintOnPanic(|| format!("stmt {:?}", stmt));
 
     fx.set_debug_loc(stmt.source_info);
@@ -812,33 +939,34 @@ fn is_fat_ptr<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, ty: Ty<'tcx>) -> bool {
         | StatementKind::PlaceMention(..)
         | StatementKind::AscribeUserType(..) => {}
 
-        StatementKind::Coverage { .. } => fx.tcx.sess.fatal("-Zcoverage is unimplemented"),
+        StatementKind::Coverage { .. } => {
+            return Err(fx.unsupported(stmt.source_info.span, "-Zcoverage is unimplemented"));
+        }
         StatementKind::Intrinsic(ref intrinsic) => match &**intrinsic {
-            // We ignore `assume` intrinsics, they are only useful for optimizations
-            NonDivergingIntrinsic::Assume(_) => {}
+            // Cranelift can't use `assume` for optimizations. When debug assertions are enabled,
+            // check the assumption instead to catch the UB of violating it early.
+            NonDivergingIntrinsic::Assume(cond) => {
+                if fx.tcx.sess.opts.debug_assertions {
+                    let cond = codegen_operand(fx, cond).load_scalar(fx);
+                    fx.bcx.ins().trapz(cond, TrapCode::UnreachableCodeReached);
+                }
+            }
             NonDivergingIntrinsic::CopyNonOverlapping(mir::CopyNonOverlapping {
                 src,
                 dst,
                 count,
             }) => {
                 let dst = codegen_operand(fx, dst);
-                let pointee = dst
-                    .layout()
-                    .pointee_info_at(fx, rustc_target::abi::Size::ZERO)
-                    .expect("Expected pointer");
+                let pointee_ty = dst.layout().ty.builtin_deref(true).expect("Expected pointer").ty;
+                let elem_layout = fx.layout_of(pointee_ty);
                 let dst = dst.load_scalar(fx);
                 let src = codegen_operand(fx, src).load_scalar(fx);
-                let count = codegen_operand(fx, count).load_scalar(fx);
-                let elem_size: u64 = pointee.size.bytes();
-                let bytes = if elem_size != 1 {
-                    fx.bcx.ins().imul_imm(count, elem_size as i64)
-                } else {
-                    count
-                };
-                fx.bcx.call_memcpy(fx.target_config, dst, src, bytes);
+                crate::intrinsics::codegen_copy(fx, dst, src, count, elem_layout, true);
             }
         },
     }
+
+    Ok(())
 }
 
 fn codegen_array_len<'tcx>(fx: &mut FunctionCx<'_, '_, 'tcx>, place: CPlace<'tcx>) -> Value {
@@ -869,18 +997,24 @@ pub(crate) fn codegen_place<'tcx>(
                 cplace = cplace.place_field(fx, field);
             }
             PlaceElem::Index(local) => {
-                let index = fx.get_local_place(local).to_cvalue(fx).load_scalar(fx);
+                // Use the constant directly rather than the variable holding it, so that
+                // `place_index` can fold the offset into the pointer.
+                let index = if let Some(&constant) = fx.constant_locals.get(&local) {
+                    crate::constant::codegen_constant_operand(fx, constant).load_scalar(fx)
+                } else {
+                    fx.get_local_place(local).to_cvalue(fx).load_scalar(fx)
+                };
                 cplace = cplace.place_index(fx, index);
             }
             PlaceElem::ConstantIndex { offset, min_length: _, from_end } => {
                 let offset: u64 = offset;
-                let index = if !from_end {
-                    fx.bcx.ins().iconst(fx.pointer_type, offset as i64)
+                if !from_end {
+                    cplace = cplace.place_constant_index(fx, offset);
                 } else {
                     let len = codegen_array_len(fx, cplace);
-                    fx.bcx.ins().iadd_imm(len, -(offset as i64))
-                };
-                cplace = cplace.place_index(fx, index);
+                    let index = fx.bcx.ins().iadd_imm(len, -(offset as i64));
+                    cplace = cplace.place_index(fx, index);
+                }
             }
             PlaceElem::Subslice { from, to, from_end } => {
                 // These indices are generated by slice patterns.
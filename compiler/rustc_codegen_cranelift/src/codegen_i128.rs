@@ -1,6 +1,6 @@
 //! Replaces 128-bit operators with lang item calls where necessary
 
-use cranelift_codegen::ir::ArgumentPurpose;
+use cranelift_codegen::ir::{InstructionData, Opcode};
 
 use crate::prelude::*;
 
@@ -24,17 +24,23 @@ pub(crate) fn maybe_codegen<'tcx>(
         BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => None,
         BinOp::Add | BinOp::AddUnchecked | BinOp::Sub | BinOp::SubUnchecked => None,
         BinOp::Mul | BinOp::MulUnchecked => {
-            let args = [lhs.load_scalar(fx), rhs.load_scalar(fx)];
-            let ret_val = fx.lib_call(
-                "__multi3",
-                vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
-                vec![AbiParam::new(types::I128)],
-                &args,
-            )[0];
-            Some(CValue::by_val(
-                ret_val,
-                fx.layout_of(if is_signed { fx.tcx.types.i128 } else { fx.tcx.types.u128 }),
-            ))
+            // Widening multiplications like `u64::widening_mul` only need the low and high half of
+            // a single 64bit multiplication.
+            if let (Some(lhs_64), Some(rhs_64)) =
+                (extended_from_64bit(fx, lhs, is_signed), extended_from_64bit(fx, rhs, is_signed))
+            {
+                let lo = fx.bcx.ins().imul(lhs_64, rhs_64);
+                let hi = if is_signed {
+                    fx.bcx.ins().smulhi(lhs_64, rhs_64)
+                } else {
+                    fx.bcx.ins().umulhi(lhs_64, rhs_64)
+                };
+                let val = fx.bcx.ins().iconcat(lo, hi);
+                return Some(CValue::by_val(val, lhs.layout()));
+            }
+
+            let ret_ty = if is_signed { fx.tcx.types.i128 } else { fx.tcx.types.u128 };
+            Some(fx.easy_call("__multi3", &[lhs, rhs], ret_ty))
         }
         BinOp::Offset => unreachable!("offset should only be used on pointers, not 128bit ints"),
         BinOp::Div | BinOp::Rem => {
@@ -58,14 +64,7 @@ pub(crate) fn maybe_codegen<'tcx>(
                 ret_place.to_ptr().store(fx, ret, MemFlags::trusted());
                 Some(ret_place.to_cvalue(fx))
             } else {
-                let args = [lhs.load_scalar(fx), rhs.load_scalar(fx)];
-                let ret_val = fx.lib_call(
-                    name,
-                    vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
-                    vec![AbiParam::new(types::I128)],
-                    &args,
-                )[0];
-                Some(CValue::by_val(ret_val, lhs.layout()))
+                Some(fx.call_runtime(name, &[lhs, rhs], lhs.layout()))
             }
         }
         BinOp::Lt | BinOp::Le | BinOp::Eq | BinOp::Ge | BinOp::Gt | BinOp::Ne => None,
@@ -73,6 +72,35 @@ pub(crate) fn maybe_codegen<'tcx>(
     }
 }
 
+/// If `val` is the zero extension (or sign extension if `signed`) of an integer of at most 64 bits,
+/// returns that integer extended to 64 bits.
+fn extended_from_64bit<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    val: CValue<'tcx>,
+    signed: bool,
+) -> Option<Value> {
+    if val.try_to_ptr().is_some() {
+        return None;
+    }
+    let val = val.load_scalar(fx);
+    let inst = fx.bcx.func.dfg.value_def(val).inst()?;
+    let extend_opcode = if signed { Opcode::Sextend } else { Opcode::Uextend };
+    let InstructionData::Unary { opcode, arg } = fx.bcx.func.dfg.insts[inst] else {
+        return None;
+    };
+    if opcode != extend_opcode {
+        return None;
+    }
+    match fx.bcx.func.dfg.value_type(arg) {
+        types::I64 => Some(arg),
+        types::I8 | types::I16 | types::I32 if signed => {
+            Some(fx.bcx.ins().sextend(types::I64, arg))
+        }
+        types::I8 | types::I16 | types::I32 => Some(fx.bcx.ins().uextend(types::I64, arg)),
+        _ => None,
+    }
+}
+
 pub(crate) fn maybe_codegen_checked<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     bin_op: BinOp,
@@ -91,6 +119,9 @@ pub(crate) fn maybe_codegen_checked<'tcx>(
 
     match bin_op {
         BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => unreachable!(),
+        // Cranelift supports 128bit addition, subtraction and comparisons, so the overflow check
+        // can be done inline rather than through a libcall returning the result via memory.
+        BinOp::Add | BinOp::Sub => None,
         BinOp::Mul if is_signed => {
             let out_ty = Ty::new_tup(fx.tcx, &[lhs.layout().ty, fx.tcx.types.bool]);
             let oflow = CPlace::new_stack_slot(fx, fx.layout_of(fx.tcx.types.i32));
@@ -111,25 +142,9 @@ pub(crate) fn maybe_codegen_checked<'tcx>(
             let oflow = fx.bcx.ins().ireduce(types::I8, oflow);
             Some(CValue::by_val_pair(res, oflow, fx.layout_of(out_ty)))
         }
-        BinOp::Add | BinOp::Sub | BinOp::Mul => {
+        BinOp::Mul => {
             let out_ty = Ty::new_tup(fx.tcx, &[lhs.layout().ty, fx.tcx.types.bool]);
-            let out_place = CPlace::new_stack_slot(fx, fx.layout_of(out_ty));
-            let param_types = vec![
-                AbiParam::special(fx.pointer_type, ArgumentPurpose::StructReturn),
-                AbiParam::new(types::I128),
-                AbiParam::new(types::I128),
-            ];
-            let args = [out_place.to_ptr().get_addr(fx), lhs.load_scalar(fx), rhs.load_scalar(fx)];
-            let name = match (bin_op, is_signed) {
-                (BinOp::Add, false) => "__rust_u128_addo",
-                (BinOp::Add, true) => "__rust_i128_addo",
-                (BinOp::Sub, false) => "__rust_u128_subo",
-                (BinOp::Sub, true) => "__rust_i128_subo",
-                (BinOp::Mul, false) => "__rust_u128_mulo",
-                _ => unreachable!(),
-            };
-            fx.lib_call(name, param_types, vec![], &args);
-            Some(out_place.to_cvalue(fx))
+            Some(fx.easy_call("__rust_u128_mulo", &[lhs, rhs], out_ty))
         }
         BinOp::AddUnchecked | BinOp::SubUnchecked | BinOp::MulUnchecked => unreachable!(),
         BinOp::Offset => unreachable!("offset should only be used on pointers, not 128bit ints"),
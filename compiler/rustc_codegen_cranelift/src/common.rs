@@ -1,4 +1,5 @@
 use cranelift_codegen::isa::TargetFrontendConfig;
+use cranelift_module::DataId;
 use gimli::write::FileId;
 use rustc_data_structures::sync::Lrc;
 use rustc_index::IndexVec;
@@ -291,6 +292,8 @@ pub(crate) struct FunctionCx<'m, 'clif, 'tcx: 'm> {
     pub(crate) bcx: FunctionBuilder<'clif>,
     pub(crate) block_map: IndexVec<BasicBlock, Block>,
     pub(crate) local_map: IndexVec<Local, CPlace<'tcx>>,
+    /// Locals which always contain the same constant. See [`crate::analyze::constant_locals`].
+    pub(crate) constant_locals: FxHashMap<Local, &'tcx ConstOperand<'tcx>>,
 
     /// When `#[track_caller]` is used, the implicit caller location is stored in this variable.
     pub(crate) caller_location: Option<CValue<'tcx>>,
@@ -304,6 +307,14 @@ pub(crate) struct FunctionCx<'m, 'clif, 'tcx: 'm> {
 
     /// This should only be accessed by `CPlace::new_var`.
     pub(crate) next_ssa_var: u32,
+
+    /// The MIR block which is currently being codegened. `None` while codegening the prelude.
+    pub(crate) current_bb: Option<BasicBlock>,
+
+    /// The addresses of all data objects referenced by this function together with the MIR block
+    /// at the start of which they are computed, or `None` for the entry block. This should only be
+    /// accessed by `crate::constant::data_addr`.
+    pub(crate) data_addrs: FxHashMap<DataId, (Value, Option<BasicBlock>)>,
 }
 
 impl<'tcx> LayoutOfHelpers<'tcx> for FunctionCx<'_, '_, 'tcx> {
@@ -445,11 +456,7 @@ pub(crate) fn anonymous_str(&mut self, msg: &str) -> Value {
         // Ignore DuplicateDefinition error, as the data will be the same
         let _ = self.module.define_data(msg_id, &data);
 
-        let local_msg_id = self.module.declare_data_in_func(msg_id, self.bcx.func);
-        if self.clif_comments.enabled() {
-            self.add_comment(local_msg_id, msg);
-        }
-        self.bcx.ins().global_value(self.pointer_type, local_msg_id)
+        crate::constant::data_addr(self, msg_id, || msg.to_owned())
     }
 }
 
@@ -57,6 +57,21 @@ pub struct BackendConfig {
     /// Defaults to true when the `CG_CLIF_DISABLE_INCR_CACHE` env var is set to 1 or false
     /// otherwise. Can be set using `-Cllvm-args=disable_incr_cache=...`.
     pub disable_incr_cache: bool,
+
+    /// Report constructs which are not supported by cg_clif as warnings rather than errors. The
+    /// functions containing them will abort the program when called.
+    ///
+    /// Defaults to true when the `CG_CLIF_ALLOW_UNSUPPORTED` env var is set to 1 or false
+    /// otherwise. Can be set using `-Cllvm-args=allow_unsupported=...`.
+    pub allow_unsupported: bool,
+
+    /// Cranelift settings to apply on top of the ones chosen by cg_clif, as `(name, value)` pairs.
+    ///
+    /// Any `-Cllvm-args=name=value` option not recognized by cg_clif itself is forwarded to
+    /// Cranelift, for example `-Cllvm-args=opt_level=speed_and_size`. Both shared settings and
+    /// settings of the target isa like `has_avx2` can be set. Unknown settings are reported once
+    /// the target isa is built.
+    pub cranelift_flags: Vec<(String, String)>,
 }
 
 impl Default for BackendConfig {
@@ -69,33 +84,43 @@ fn default() -> Self {
             },
             enable_verifier: cfg!(debug_assertions) || bool_env_var("CG_CLIF_ENABLE_VERIFIER"),
             disable_incr_cache: bool_env_var("CG_CLIF_DISABLE_INCR_CACHE"),
+            allow_unsupported: bool_env_var("CG_CLIF_ALLOW_UNSUPPORTED"),
+            cranelift_flags: vec![],
         }
     }
 }
 
 impl BackendConfig {
-    /// Parse the configuration passed in using `-Cllvm-args`.
+    /// Parse the configuration passed in using `-Cllvm-args`. Options are separated by spaces or
+    /// commas, for example `-Cllvm-args=enable_verifier,opt_level=speed`. An option without a
+    /// value is set to `true`.
     pub fn from_opts(opts: &[String]) -> Result<Self, String> {
         fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
             value.parse().map_err(|_| format!("failed to parse value `{}` for {}", value, name))
         }
 
         let mut config = BackendConfig::default();
-        for opt in opts {
+        for opt in opts.iter().flat_map(|opt| opt.split(',')) {
+            if opt.is_empty() {
+                continue;
+            }
             if opt.starts_with("-import-instr-limit") {
                 // Silently ignore -import-instr-limit. It is set by rust's build system even when
                 // testing cg_clif.
                 continue;
             }
-            if let Some((name, value)) = opt.split_once('=') {
-                match name {
-                    "mode" => config.codegen_mode = value.parse()?,
-                    "enable_verifier" => config.enable_verifier = parse_bool(name, value)?,
-                    "disable_incr_cache" => config.disable_incr_cache = parse_bool(name, value)?,
-                    _ => return Err(format!("Unknown option `{}`", name)),
-                }
-            } else {
-                return Err(format!("Invalid option `{}`", opt));
+            // A bare name enables a boolean option.
+            let (name, value) = match opt.split_once('=') {
+                Some((name, value)) => (name, value),
+                None if opt == "mode" => return Err(format!("Invalid option `{}`", opt)),
+                None => (opt, "true"),
+            };
+            match name {
+                "mode" => config.codegen_mode = value.parse()?,
+                "enable_verifier" => config.enable_verifier = parse_bool(name, value)?,
+                "disable_incr_cache" => config.disable_incr_cache = parse_bool(name, value)?,
+                "allow_unsupported" => config.allow_unsupported = parse_bool(name, value)?,
+                _ => config.cranelift_flags.push((name.to_owned(), value.to_owned())),
             }
         }
 
@@ -2,6 +2,7 @@
 
 use std::cmp::Ordering;
 
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
 use cranelift_module::*;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
@@ -135,12 +136,7 @@ pub(crate) fn codegen_const_value<'tcx>(
                             alloc_id,
                             alloc.inner().mutability,
                         );
-                        let local_data_id =
-                            fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
-                        if fx.clif_comments.enabled() {
-                            fx.add_comment(local_data_id, format!("{:?}", alloc_id));
-                        }
-                        fx.bcx.ins().global_value(fx.pointer_type, local_data_id)
+                        data_addr(fx, data_id, || format!("{:?}", alloc_id))
                     }
                     GlobalAlloc::Function(instance) => {
                         let func_id = crate::abi::import_function(fx.tcx, fx.module, instance);
@@ -158,19 +154,12 @@ pub(crate) fn codegen_const_value<'tcx>(
                             alloc_id,
                             alloc.inner().mutability,
                         );
-                        let local_data_id =
-                            fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
-                        fx.bcx.ins().global_value(fx.pointer_type, local_data_id)
+                        data_addr(fx, data_id, || format!("vtable: {:?}", alloc_id))
                     }
                     GlobalAlloc::Static(def_id) => {
                         assert!(fx.tcx.is_static(def_id));
                         let data_id = data_id_for_static(fx.tcx, fx.module, def_id, false);
-                        let local_data_id =
-                            fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
-                        if fx.clif_comments.enabled() {
-                            fx.add_comment(local_data_id, format!("{:?}", def_id));
-                        }
-                        fx.bcx.ins().global_value(fx.pointer_type, local_data_id)
+                        data_addr(fx, data_id, || format!("{:?}", def_id))
                     }
                 };
                 let val = if offset.bytes() != 0 {
@@ -207,12 +196,80 @@ fn pointer_for_allocation<'tcx>(
         alloc.inner().mutability,
     );
 
+    let global_ptr = data_addr(fx, data_id, || format!("{:?}", alloc_id));
+    crate::pointer::Pointer::new(global_ptr)
+}
+
+/// Get the address of the given data object.
+///
+/// The address is computed only once per function, so that it doesn't need to be recomputed for
+/// every reference, for example inside a loop. It is computed at the start of the block containing
+/// the first reference. If a later reference isn't dominated by this block, the computation is
+/// moved to the start of a block dominating both.
+pub(crate) fn data_addr(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    data_id: DataId,
+    comment: impl FnOnce() -> String,
+) -> Value {
+    if let Some(&(addr, def_bb)) = fx.data_addrs.get(&data_id) {
+        let dominators = fx.mir.basic_blocks.dominators();
+        let mut common_bb = def_bb;
+        while let Some(bb) = common_bb {
+            match fx.current_bb {
+                Some(current_bb)
+                    if dominators.is_reachable(current_bb)
+                        && dominators.dominates(bb, current_bb) =>
+                {
+                    break;
+                }
+                _ => common_bb = dominators.immediate_dominator(bb),
+            }
+        }
+        if common_bb == def_bb {
+            return addr;
+        }
+
+        let inst = fx.bcx.func.dfg.value_def(addr).unwrap_inst();
+        fx.bcx.func.layout.remove_inst(inst);
+        let (common_bb, block) = data_addr_block(fx, common_bb);
+        FuncCursor::new(fx.bcx.func).at_first_insertion_point(block).insert_inst(inst);
+        fx.data_addrs.insert(data_id, (addr, common_bb));
+        return addr;
+    }
+
     let local_data_id = fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
     if fx.clif_comments.enabled() {
-        fx.add_comment(local_data_id, format!("{:?}", alloc_id));
+        fx.add_comment(local_data_id, comment());
+    }
+
+    let (bb, block) = data_addr_block(fx, fx.current_bb);
+    let addr = FuncCursor::new(fx.bcx.func)
+        .at_first_insertion_point(block)
+        .ins()
+        .global_value(fx.pointer_type, local_data_id);
+    fx.data_addrs.insert(data_id, (addr, bb));
+    addr
+}
+
+/// The clif block at the start of which the address of a data object referenced in `bb` is
+/// computed. This is the entry block if `bb` is `None` or hasn't been codegened yet.
+fn data_addr_block(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    bb: Option<BasicBlock>,
+) -> (Option<BasicBlock>, Block) {
+    if let Some(bb) = bb {
+        if fx.bcx.func.layout.first_inst(fx.block_map[bb]).is_some() {
+            return (Some(bb), fx.block_map[bb]);
+        }
+    }
+
+    if let Some(entry_block) = fx.bcx.func.layout.entry_block() {
+        (None, entry_block)
+    } else {
+        // Nothing has been codegened yet, so the current block will become the entry block.
+        fx.bcx.ensure_inserted_block();
+        (None, fx.bcx.current_block().unwrap())
     }
-    let global_ptr = fx.bcx.ins().global_value(fx.pointer_type, local_data_id);
-    crate::pointer::Pointer::new(global_ptr)
 }
 
 pub(crate) fn data_id_for_alloc_id(
@@ -3,19 +3,23 @@
 mod emit;
 mod line_info;
 mod object;
+mod types;
 mod unwind;
 
 use cranelift_codegen::ir::Endianness;
 use cranelift_codegen::isa::TargetIsa;
 use gimli::write::{
-    Address, AttributeValue, DwarfUnit, FileId, LineProgram, LineString, Range, RangeList,
-    UnitEntryId,
+    Address, AttributeValue, DwarfUnit, Expression, FileId, LineProgram, LineString, Range,
+    RangeList, UnitEntryId,
 };
 use gimli::{Encoding, Format, LineEncoding, RunTimeEndian};
 use indexmap::IndexSet;
+use rustc_index::IndexVec;
 
 pub(crate) use self::emit::{DebugReloc, DebugRelocName};
+pub(crate) use self::types::TypeDebugContext;
 pub(crate) use self::unwind::UnwindContext;
+use crate::pointer::PointerBase;
 use crate::prelude::*;
 
 pub(crate) fn producer() -> String {
@@ -33,12 +37,20 @@ pub(crate) struct DebugContext {
     unit_range_list: RangeList,
 
     should_remap_filepaths: bool,
+
+    /// The size of the area between the CFA and the saved callee-saved registers containing the
+    /// saved frame pointer and return address if known for the target. Stack slots are addressed
+    /// relative to the CFA using it.
+    frame_setup_area_size: Option<u32>,
 }
 
 pub(crate) struct FunctionDebugContext {
     entry_id: UnitEntryId,
     function_source_loc: (FileId, u64, u64),
     source_loc_set: IndexSet<(FileId, u64, u64)>,
+    /// Variables stored in a stack slot together with their offset in it. Their location is only
+    /// known once the function has been compiled.
+    stack_vars: Vec<(UnitEntryId, StackSlot, i64)>,
 }
 
 impl DebugContext {
@@ -63,6 +75,15 @@ pub(crate) fn new(tcx: TyCtxt<'_>, isa: &dyn TargetIsa) -> Self {
             Endianness::Big => RunTimeEndian::Big,
         };
 
+        // Cranelift always sets up a frame on x86_64. On AArch64 and RISC-V it does so for all
+        // functions with stack slots.
+        let frame_setup_area_size = match isa.triple().architecture {
+            target_lexicon::Architecture::X86_64
+            | target_lexicon::Architecture::Aarch64(_)
+            | target_lexicon::Architecture::Riscv64(_) => Some(16),
+            _ => None,
+        };
+
         let mut dwarf = DwarfUnit::new(encoding);
 
         let should_remap_filepaths = tcx.sess.should_prefer_remapped_for_codegen();
@@ -115,6 +136,7 @@ pub(crate) fn new(tcx: TyCtxt<'_>, isa: &dyn TargetIsa) -> Self {
             dwarf,
             unit_range_list: RangeList(Vec::new()),
             should_remap_filepaths,
+            frame_setup_area_size,
         }
     }
 
@@ -146,11 +168,59 @@ pub(crate) fn define_function(
             entry_id,
             function_source_loc: (file_id, line, column),
             source_loc_set: IndexSet::new(),
+            stack_vars: Vec::new(),
         }
     }
 }
 
 impl FunctionDebugContext {
+    /// Describe the user variables and arguments of a function.
+    ///
+    /// Only variables that live in a stack slot are described for now. Those stored in SSA
+    /// variables can move between registers, which would require location lists.
+    pub(crate) fn define_local_variables<'tcx>(
+        &mut self,
+        debug_context: &mut DebugContext,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        mir: &Body<'tcx>,
+        local_map: &IndexVec<Local, CPlace<'tcx>>,
+    ) {
+        for var in &mir.var_debug_info {
+            let VarDebugInfoContents::Place(place) = var.value else { continue };
+            if !place.projection.is_empty() || var.composite.is_some() {
+                continue;
+            }
+            // The locals are not created when the function body is unreachable.
+            let Some(&place) = local_map.get(place.local) else { continue };
+            let Some(ptr) = place.try_to_ptr() else { continue };
+            let (PointerBase::Stack(stack_slot), offset) = ptr.debug_base_and_offset() else {
+                continue;
+            };
+
+            let type_id = debug_context.debug_type(tcx, type_dbg, place.layout().ty);
+            let (file, line, column) =
+                DebugContext::get_span_loc(tcx, mir.span, var.source_info.span);
+            let file_id = debug_context.add_source_file(&file);
+
+            let tag = if var.argument_index.is_some() {
+                gimli::DW_TAG_formal_parameter
+            } else {
+                gimli::DW_TAG_variable
+            };
+            let var_id = debug_context.dwarf.unit.add(self.entry_id, tag);
+            let var_entry = debug_context.dwarf.unit.get_mut(var_id);
+            let name_id = debug_context.dwarf.strings.add(var.name.as_str());
+            var_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name_id));
+            var_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(type_id));
+            var_entry.set(gimli::DW_AT_decl_file, AttributeValue::FileIndex(Some(file_id)));
+            var_entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+            var_entry.set(gimli::DW_AT_decl_column, AttributeValue::Udata(column));
+
+            self.stack_vars.push((var_id, stack_slot, i64::from(offset)));
+        }
+    }
+
     pub(crate) fn finalize(
         mut self,
         debug_context: &mut DebugContext,
@@ -174,5 +244,29 @@ pub(crate) fn finalize(
         );
         // Using Udata for DW_AT_high_pc requires at least DWARF4
         func_entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(u64::from(end)));
+
+        if let Some(frame_setup_area_size) = debug_context.frame_setup_area_size {
+            // The stack pointer moves in the prologue, the epilogue and around calls passing
+            // arguments on the stack, so address the stack slots relative to the CFA, which the
+            // unwind info describes at every instruction. The stack slot offsets are relative to
+            // the stack pointer after the prologue, which is the frame size plus the setup area
+            // size below the CFA.
+            let compiled_code = context.compiled_code().unwrap();
+            let sp_to_cfa_offset = i64::from(compiled_code.frame_size + frame_setup_area_size);
+
+            let mut frame_base = Expression::new();
+            frame_base.op(gimli::DW_OP_call_frame_cfa);
+            let func_entry = debug_context.dwarf.unit.get_mut(self.entry_id);
+            func_entry.set(gimli::DW_AT_frame_base, AttributeValue::Exprloc(frame_base));
+
+            let stackslot_offsets = &compiled_code.sized_stackslot_offsets;
+            for (var_id, stack_slot, offset) in self.stack_vars {
+                let mut location = Expression::new();
+                location
+                    .op_fbreg(i64::from(stackslot_offsets[stack_slot]) + offset - sp_to_cfa_offset);
+                let var_entry = debug_context.dwarf.unit.get_mut(var_id);
+                var_entry.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+            }
+        }
     }
 }
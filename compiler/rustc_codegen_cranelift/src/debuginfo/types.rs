@@ -0,0 +1,441 @@
+//! Creation of the DWARF type entries describing rust types.
+
+use gimli::write::{AttributeValue, UnitEntryId};
+use rustc_codegen_ssa::debuginfo::type_names;
+use rustc_middle::ty::layout::IntegerExt;
+use rustc_target::abi::{Int, Integer, TagEncoding, Variants};
+
+use crate::prelude::*;
+
+/// The types for which a DWARF entry has already been created in the current codegen unit.
+#[derive(Default)]
+pub(crate) struct TypeDebugContext<'tcx> {
+    type_map: FxHashMap<Ty<'tcx>, UnitEntryId>,
+}
+
+impl DebugContext {
+    pub(crate) fn debug_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> UnitEntryId {
+        if let Some(&type_id) = type_dbg.type_map.get(&ty) {
+            return type_id;
+        }
+
+        let type_id = match ty.kind() {
+            ty::Never | ty::Bool | ty::Char | ty::Int(_) | ty::Uint(_) | ty::Float(_) => {
+                self.basic_type(tcx, ty)
+            }
+            ty::Tuple(elems) if elems.is_empty() => self.basic_type(tcx, ty),
+            ty::RawPtr(TypeAndMut { ty: pointee_ty, mutbl: _ }) | ty::Ref(_, pointee_ty, _) => {
+                self.pointer_type(tcx, type_dbg, ty, *pointee_ty)
+            }
+            ty::Adt(adt_def, _) if adt_def.is_box() => {
+                self.pointer_type(tcx, type_dbg, ty, ty.boxed_ty())
+            }
+            ty::Array(elem_ty, len) => self.array_type(
+                tcx,
+                type_dbg,
+                ty,
+                *elem_ty,
+                len.eval_target_usize(tcx, ParamEnv::reveal_all()),
+            ),
+            ty::Tuple(_) => self.struct_type(tcx, type_dbg, ty),
+            ty::Adt(adt_def, _) if adt_def.is_struct() => self.struct_type(tcx, type_dbg, ty),
+            ty::Adt(adt_def, _) if adt_def.is_union() => self.union_type(tcx, type_dbg, ty),
+            ty::Adt(adt_def, _) if adt_def.is_enum() && !adt_def.variants().is_empty() => {
+                self.enum_type(tcx, type_dbg, ty)
+            }
+            _ => self.placeholder_for_type(tcx, type_dbg, ty),
+        };
+
+        type_dbg.type_map.insert(ty, type_id);
+        type_id
+    }
+
+    fn basic_type<'tcx>(&mut self, tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> UnitEntryId {
+        let (name, encoding) = match ty.kind() {
+            ty::Never => ("!", gimli::DW_ATE_unsigned),
+            ty::Tuple(elems) if elems.is_empty() => ("()", gimli::DW_ATE_unsigned),
+            ty::Bool => ("bool", gimli::DW_ATE_boolean),
+            ty::Char => ("char", gimli::DW_ATE_UTF),
+            ty::Int(int_ty) => (int_ty.name_str(), gimli::DW_ATE_signed),
+            ty::Uint(uint_ty) => (uint_ty.name_str(), gimli::DW_ATE_unsigned),
+            ty::Float(float_ty) => (float_ty.name_str(), gimli::DW_ATE_float),
+            _ => unreachable!(),
+        };
+
+        let type_id = self.dwarf.unit.add(self.dwarf.unit.root(), gimli::DW_TAG_base_type);
+        let type_entry = self.dwarf.unit.get_mut(type_id);
+        type_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        type_entry.set(gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+        type_entry.set(
+            gimli::DW_AT_byte_size,
+            AttributeValue::Udata(RevealAllLayoutCx(tcx).layout_of(ty).size.bytes()),
+        );
+
+        type_id
+    }
+
+    fn pointer_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ptr_type: Ty<'tcx>,
+        pointee_type: Ty<'tcx>,
+    ) -> UnitEntryId {
+        if has_ptr_meta(tcx, pointee_type) {
+            return self.fat_pointer_type(tcx, type_dbg, ptr_type, pointee_type);
+        }
+
+        let pointee_type_id = self.debug_type(tcx, type_dbg, pointee_type);
+        let name = type_names::compute_debuginfo_type_name(tcx, ptr_type, true);
+
+        let type_id = self.dwarf.unit.add(self.dwarf.unit.root(), gimli::DW_TAG_pointer_type);
+        let type_entry = self.dwarf.unit.get_mut(type_id);
+        type_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        type_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(pointee_type_id));
+
+        type_id
+    }
+
+    /// Describe a pointer to an unsized type as a struct of the data pointer and the metadata like
+    /// rustc's LLVM backend does.
+    fn fat_pointer_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ptr_type: Ty<'tcx>,
+        pointee_type: Ty<'tcx>,
+    ) -> UnitEntryId {
+        let layout = RevealAllLayoutCx(tcx).layout_of(ptr_type);
+        let (data_type, metadata_name, metadata_type) =
+            match tcx.struct_tail_erasing_lifetimes(pointee_type, ParamEnv::reveal_all()).kind() {
+                ty::Slice(elem_ty) if pointee_type.is_slice() => {
+                    (Ty::new_imm_ptr(tcx, *elem_ty), "length", tcx.types.usize)
+                }
+                ty::Slice(_) | ty::Str => {
+                    (Ty::new_imm_ptr(tcx, tcx.types.u8), "length", tcx.types.usize)
+                }
+                ty::Dynamic(..) => (
+                    Ty::new_imm_ptr(tcx, tcx.types.unit),
+                    "vtable",
+                    Ty::new_imm_ptr(tcx, Ty::new_array(tcx, tcx.types.usize, 3)),
+                ),
+                _ => bug!("unexpected unsized tail of {pointee_type:?}"),
+            };
+
+        let type_id =
+            self.new_composite_type(tcx, type_dbg, gimli::DW_TAG_structure_type, ptr_type, layout);
+        let data_type_id = self.debug_type(tcx, type_dbg, data_type);
+        let metadata_type_id = self.debug_type(tcx, type_dbg, metadata_type);
+        self.add_member(type_id, "data_ptr", data_type_id, layout.fields.offset(0));
+        self.add_member(type_id, metadata_name, metadata_type_id, layout.fields.offset(1));
+
+        type_id
+    }
+
+    fn array_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        array_ty: Ty<'tcx>,
+        elem_ty: Ty<'tcx>,
+        len: u64,
+    ) -> UnitEntryId {
+        let elem_type_id = self.debug_type(tcx, type_dbg, elem_ty);
+        let name = type_names::compute_debuginfo_type_name(tcx, array_ty, true);
+
+        let type_id = self.dwarf.unit.add(self.dwarf.unit.root(), gimli::DW_TAG_array_type);
+        let type_entry = self.dwarf.unit.get_mut(type_id);
+        type_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        type_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(elem_type_id));
+
+        let subrange_id = self.dwarf.unit.add(type_id, gimli::DW_TAG_subrange_type);
+        let subrange_entry = self.dwarf.unit.get_mut(subrange_id);
+        subrange_entry.set(gimli::DW_AT_lower_bound, AttributeValue::Udata(0));
+        subrange_entry.set(gimli::DW_AT_count, AttributeValue::Udata(len));
+
+        type_id
+    }
+
+    /// Describe a struct or tuple. The fields of tuples and tuple structs are named `__0`, `__1`,
+    /// ... like rustc's LLVM backend does.
+    fn struct_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> UnitEntryId {
+        let layout = RevealAllLayoutCx(tcx).layout_of(ty);
+        let type_id =
+            self.new_composite_type(tcx, type_dbg, gimli::DW_TAG_structure_type, ty, layout);
+        self.add_fields(tcx, type_dbg, type_id, layout, field_names(ty, FIRST_VARIANT));
+        type_id
+    }
+
+    fn union_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> UnitEntryId {
+        let layout = RevealAllLayoutCx(tcx).layout_of(ty);
+        let type_id = self.new_composite_type(tcx, type_dbg, gimli::DW_TAG_union_type, ty, layout);
+        self.add_fields(tcx, type_dbg, type_id, layout, field_names(ty, FIRST_VARIANT));
+        type_id
+    }
+
+    /// Describe an enum. Fieldless enums with an explicit tag become a DWARF enumeration. All
+    /// other enums are described as a struct containing a variant part with one struct per variant
+    /// like rustc's LLVM backend does, which debuggers understand for both tag and niche encoded
+    /// enums.
+    fn enum_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> UnitEntryId {
+        let ty::Adt(adt_def, _) = ty.kind() else { unreachable!() };
+        let layout = RevealAllLayoutCx(tcx).layout_of(ty);
+
+        if let Variants::Multiple { tag, tag_encoding: TagEncoding::Direct, .. } = layout.variants {
+            if adt_def.is_payloadfree() {
+                return self.fieldless_enum_type(tcx, type_dbg, ty, layout, tag);
+            }
+        }
+
+        let type_id =
+            self.new_composite_type(tcx, type_dbg, gimli::DW_TAG_structure_type, ty, layout);
+        let variant_part_id = self.dwarf.unit.add(type_id, gimli::DW_TAG_variant_part);
+
+        // Which variants to describe and the tag value identifying them. The variant without tag
+        // value is the default variant, used when the tag doesn't match any other variant.
+        let variants: Vec<(VariantIdx, Option<u128>)> = match layout.variants {
+            Variants::Single { index } => vec![(index, None)],
+            Variants::Multiple { tag, ref tag_encoding, tag_field, variants: _ } => {
+                let tag_size = tag.size(&tcx);
+                // Niche encoded tags may be pointers, so describe the tag as unsigned integer of
+                // the same size instead of using the type of the tag field.
+                let tag_int = Integer::from_size(tag_size).unwrap();
+                let tag_type_id = self.debug_type(tcx, type_dbg, tag_int.to_ty(tcx, false));
+                let tag_member_id = self.add_member(
+                    variant_part_id,
+                    "<tag>",
+                    tag_type_id,
+                    layout.fields.offset(tag_field),
+                );
+                self.dwarf
+                    .unit
+                    .get_mut(tag_member_id)
+                    .set(gimli::DW_AT_artificial, AttributeValue::Flag(true));
+                self.dwarf
+                    .unit
+                    .get_mut(variant_part_id)
+                    .set(gimli::DW_AT_discr, AttributeValue::UnitRef(tag_member_id));
+
+                match *tag_encoding {
+                    TagEncoding::Direct => adt_def
+                        .variants()
+                        .indices()
+                        .map(|variant_index| {
+                            let discr =
+                                ty.discriminant_for_variant(tcx, variant_index).unwrap().val;
+                            (variant_index, Some(tag_size.truncate(discr)))
+                        })
+                        .collect(),
+                    TagEncoding::Niche { untagged_variant, ref niche_variants, niche_start } => {
+                        let niche_variants = niche_variants
+                            .clone()
+                            .filter(|&variant_index| variant_index != untagged_variant)
+                            .map(|variant_index| {
+                                let relative_discr = u128::from(
+                                    variant_index.as_u32() - niche_variants.start().as_u32(),
+                                );
+                                let tag_value = relative_discr.wrapping_add(niche_start);
+                                (variant_index, Some(tag_size.truncate(tag_value)))
+                            });
+                        std::iter::once((untagged_variant, None)).chain(niche_variants).collect()
+                    }
+                }
+            }
+        };
+
+        for (variant_index, tag_value) in variants {
+            let variant_name = adt_def.variant(variant_index).name.as_str();
+            let variant_layout = layout.for_variant(&RevealAllLayoutCx(tcx), variant_index);
+
+            // The struct describing the fields of the variant. The field offsets are relative to
+            // the start of the enum.
+            let variant_struct_id = self.dwarf.unit.add(type_id, gimli::DW_TAG_structure_type);
+            let variant_struct_entry = self.dwarf.unit.get_mut(variant_struct_id);
+            let name_id = self.dwarf.strings.add(variant_name);
+            variant_struct_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name_id));
+            variant_struct_entry
+                .set(gimli::DW_AT_byte_size, AttributeValue::Udata(layout.size.bytes()));
+            variant_struct_entry
+                .set(gimli::DW_AT_alignment, AttributeValue::Udata(layout.align.abi.bytes()));
+            self.add_fields(
+                tcx,
+                type_dbg,
+                variant_struct_id,
+                variant_layout,
+                field_names(ty, variant_index),
+            );
+
+            let variant_id = self.dwarf.unit.add(variant_part_id, gimli::DW_TAG_variant);
+            if let Some(tag_value) = tag_value {
+                // FIXME support tags that don't fit in 64 bits
+                if let Ok(tag_value) = u64::try_from(tag_value) {
+                    self.dwarf
+                        .unit
+                        .get_mut(variant_id)
+                        .set(gimli::DW_AT_discr_value, AttributeValue::Udata(tag_value));
+                }
+            }
+            self.add_member(variant_id, variant_name, variant_struct_id, Size::ZERO);
+        }
+
+        type_id
+    }
+
+    fn fieldless_enum_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+        layout: TyAndLayout<'tcx>,
+        tag: Scalar,
+    ) -> UnitEntryId {
+        let ty::Adt(adt_def, _) = ty.kind() else { unreachable!() };
+        let Int(tag_int, signed) = tag.primitive() else { unreachable!() };
+        let tag_type_id = self.debug_type(tcx, type_dbg, tag_int.to_ty(tcx, signed));
+
+        let type_id =
+            self.new_composite_type(tcx, type_dbg, gimli::DW_TAG_enumeration_type, ty, layout);
+        self.dwarf
+            .unit
+            .get_mut(type_id)
+            .set(gimli::DW_AT_type, AttributeValue::UnitRef(tag_type_id));
+
+        for (variant_index, variant) in adt_def.variants().iter_enumerated() {
+            let discr = ty.discriminant_for_variant(tcx, variant_index).unwrap().val;
+            let discr = if signed {
+                AttributeValue::Sdata(tag.size(&tcx).sign_extend(discr) as i128 as i64)
+            } else {
+                AttributeValue::Udata(discr as u64)
+            };
+
+            let enumerator_id = self.dwarf.unit.add(type_id, gimli::DW_TAG_enumerator);
+            let enumerator_entry = self.dwarf.unit.get_mut(enumerator_id);
+            let name_id = self.dwarf.strings.add(variant.name.as_str());
+            enumerator_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name_id));
+            enumerator_entry.set(gimli::DW_AT_const_value, discr);
+        }
+
+        type_id
+    }
+
+    /// Create the entry for a struct, union or enum type without any members yet.
+    ///
+    /// The entry is registered in the type map right away to support recursive types like linked
+    /// lists, which refer to themselves through a pointer in one of their fields.
+    fn new_composite_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        tag: gimli::DwTag,
+        ty: Ty<'tcx>,
+        layout: TyAndLayout<'tcx>,
+    ) -> UnitEntryId {
+        let name = type_names::compute_debuginfo_type_name(tcx, ty, true);
+
+        let type_id = self.dwarf.unit.add(self.dwarf.unit.root(), tag);
+        let type_entry = self.dwarf.unit.get_mut(type_id);
+        type_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        type_entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(layout.size.bytes()));
+        type_entry.set(gimli::DW_AT_alignment, AttributeValue::Udata(layout.align.abi.bytes()));
+
+        type_dbg.type_map.insert(ty, type_id);
+        type_id
+    }
+
+    fn add_fields<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        parent_id: UnitEntryId,
+        layout: TyAndLayout<'tcx>,
+        field_names: Vec<String>,
+    ) {
+        for (i, field_name) in field_names.into_iter().enumerate() {
+            let field_layout = layout.field(&RevealAllLayoutCx(tcx), i);
+            let field_type_id = self.debug_type(tcx, type_dbg, field_layout.ty);
+            self.add_member(parent_id, &field_name, field_type_id, layout.fields.offset(i));
+        }
+    }
+
+    fn add_member(
+        &mut self,
+        parent_id: UnitEntryId,
+        name: &str,
+        type_id: UnitEntryId,
+        offset: Size,
+    ) -> UnitEntryId {
+        let member_id = self.dwarf.unit.add(parent_id, gimli::DW_TAG_member);
+        let member_entry = self.dwarf.unit.get_mut(member_id);
+        member_entry
+            .set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        member_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(type_id));
+        member_entry.set(gimli::DW_AT_data_member_location, AttributeValue::Udata(offset.bytes()));
+        member_id
+    }
+
+    /// Describe a type for which no better description exists yet as a named array of bytes, so
+    /// debuggers at least know its name and size.
+    fn placeholder_for_type<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> UnitEntryId {
+        let size = RevealAllLayoutCx(tcx).layout_of(ty).size.bytes();
+        let u8_type_id = self.debug_type(tcx, type_dbg, tcx.types.u8);
+        let name = type_names::compute_debuginfo_type_name(tcx, ty, true);
+
+        let type_id = self.dwarf.unit.add(self.dwarf.unit.root(), gimli::DW_TAG_array_type);
+        let type_entry = self.dwarf.unit.get_mut(type_id);
+        type_entry.set(gimli::DW_AT_name, AttributeValue::StringRef(self.dwarf.strings.add(name)));
+        type_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(u8_type_id));
+
+        let subrange_id = self.dwarf.unit.add(type_id, gimli::DW_TAG_subrange_type);
+        let subrange_entry = self.dwarf.unit.get_mut(subrange_id);
+        subrange_entry.set(gimli::DW_AT_lower_bound, AttributeValue::Udata(0));
+        subrange_entry.set(gimli::DW_AT_count, AttributeValue::Udata(size));
+
+        type_id
+    }
+}
+
+/// The names of the fields of a tuple, struct, union or enum variant.
+fn field_names(ty: Ty<'_>, variant_index: VariantIdx) -> Vec<String> {
+    match ty.kind() {
+        ty::Tuple(elems) => (0..elems.len()).map(|i| format!("__{i}")).collect(),
+        ty::Adt(adt_def, _) => adt_def
+            .variant(variant_index)
+            .fields
+            .iter()
+            .map(|field| {
+                let name = field.name.as_str();
+                if name.starts_with(|c: char| c.is_ascii_digit()) {
+                    format!("__{name}")
+                } else {
+                    name.to_owned()
+                }
+            })
+            .collect(),
+        _ => unreachable!("{ty:?} has no fields"),
+    }
+}
@@ -20,6 +20,7 @@
 use rustc_session::Session;
 
 use crate::concurrency_limiter::{ConcurrencyLimiter, ConcurrencyLimiterToken};
+use crate::debuginfo::TypeDebugContext;
 use crate::global_asm::GlobalAsmConfig;
 use crate::{prelude::*, BackendConfig};
 
@@ -27,6 +28,7 @@ struct ModuleCodegenResult {
     module_regular: CompiledModule,
     module_global_asm: Option<CompiledModule>,
     existing_work_product: Option<(WorkProductId, WorkProduct)>,
+    assembly: Option<String>,
 }
 
 enum OngoingModuleCodegen {
@@ -53,10 +55,12 @@ impl OngoingCodegen {
     pub(crate) fn join(
         self,
         sess: &Session,
+        outputs: &OutputFilenames,
         backend_config: &BackendConfig,
     ) -> (CodegenResults, FxIndexMap<WorkProductId, WorkProduct>) {
         let mut work_products = FxIndexMap::default();
         let mut modules = vec![];
+        let mut assembly = String::new();
 
         for module_codegen in self.modules {
             let module_codegen_result = match module_codegen {
@@ -71,33 +75,50 @@ pub(crate) fn join(
                 Ok(module_codegen_result) => module_codegen_result,
                 Err(err) => sess.fatal(err),
             };
-            let ModuleCodegenResult { module_regular, module_global_asm, existing_work_product } =
-                module_codegen_result;
+            let ModuleCodegenResult {
+                module_regular,
+                module_global_asm,
+                existing_work_product,
+                assembly: module_assembly,
+            } = module_codegen_result;
 
             if let Some((work_product_id, work_product)) = existing_work_product {
                 work_products.insert(work_product_id, work_product);
-            } else {
-                let work_product = if backend_config.disable_incr_cache {
-                    None
-                } else if let Some(module_global_asm) = &module_global_asm {
-                    rustc_incremental::copy_cgu_workproduct_to_incr_comp_cache_dir(
-                        sess,
-                        &module_regular.name,
-                        &[
-                            ("o", &module_regular.object.as_ref().unwrap()),
-                            ("asm.o", &module_global_asm.object.as_ref().unwrap()),
-                        ],
-                    )
-                } else {
+            } else if !backend_config.disable_incr_cache && sess.opts.incremental.is_some() {
+                let mut files = vec![("o", module_regular.object.clone().unwrap())];
+                if let Some(module_global_asm) = &module_global_asm {
+                    files.push(("asm.o", module_global_asm.object.clone().unwrap()));
+                }
+                // Save the listing for `--emit asm` to allow reusing this codegen unit when the
+                // listing is requested again.
+                let listing = module_assembly.as_ref().and_then(|module_assembly| {
+                    let path = outputs.temp_path(OutputType::Assembly, Some(&module_regular.name));
+                    std::fs::write(&path, module_assembly).ok().map(|()| path)
+                });
+                if let Some(path) = &listing {
+                    files.push(("s", path.clone()));
+                }
+
+                let files = files.iter().map(|(ext, path)| (*ext, &**path)).collect::<Vec<_>>();
+                if let Some((work_product_id, work_product)) =
                     rustc_incremental::copy_cgu_workproduct_to_incr_comp_cache_dir(
                         sess,
                         &module_regular.name,
-                        &[("o", &module_regular.object.as_ref().unwrap())],
+                        &files,
                     )
-                };
-                if let Some((work_product_id, work_product)) = work_product {
+                {
                     work_products.insert(work_product_id, work_product);
                 }
+
+                if let Some(path) = listing {
+                    if !sess.opts.cg.save_temps {
+                        let _ = std::fs::remove_file(path);
+                    }
+                }
+            }
+
+            if let Some(module_assembly) = module_assembly {
+                assembly.push_str(&module_assembly);
             }
 
             modules.push(module_regular);
@@ -110,6 +131,12 @@ pub(crate) fn join(
 
         sess.abort_if_errors();
 
+        if sess.opts.output_types.contains_key(&OutputType::Assembly) {
+            // Unlike cg_llvm, the disassembly of all codegen units is written to a single file.
+            // It is only a listing for inspecting the generated code and can't be assembled.
+            outputs.path(OutputType::Assembly).overwrite(&assembly, sess);
+        }
+
         (
             CodegenResults {
                 modules,
@@ -165,6 +192,7 @@ fn emit_cgu(
             bytecode: None,
         }),
         existing_work_product: None,
+        assembly: None,
     })
 }
 
@@ -240,6 +268,21 @@ fn reuse_workproduct_for_cgu(
         false
     };
 
+    let assembly = if tcx.sess.opts.output_types.contains_key(&OutputType::Assembly) {
+        let source_file_assembly = rustc_incremental::in_incr_comp_dir_sess(
+            tcx.sess,
+            work_product.saved_files.get("s").expect("no saved assembly listing in work product"),
+        );
+        match std::fs::read_to_string(&source_file_assembly) {
+            Ok(assembly) => Some(assembly),
+            Err(err) => {
+                return Err(format!("unable to read {}: {}", source_file_assembly.display(), err));
+            }
+        }
+    } else {
+        None
+    };
+
     Ok(ModuleCodegenResult {
         module_regular: CompiledModule {
             name: cgu.name().to_string(),
@@ -256,6 +299,7 @@ fn reuse_workproduct_for_cgu(
             bytecode: None,
         }),
         existing_work_product: Some((cgu.work_product_id(), work_product)),
+        assembly,
     })
 }
 
@@ -282,14 +326,25 @@ fn module_codegen(
                 tcx.sess.opts.debuginfo != DebugInfo::None,
                 cgu_name,
             );
+            let mut type_dbg = TypeDebugContext::default();
             super::predefine_mono_items(tcx, &mut module, &mono_items);
             let mut codegened_functions = vec![];
-            for (mono_item, _) in mono_items {
+            for (mono_item, data) in mono_items {
                 match mono_item {
+                    MonoItem::Fn(inst) if crate::base::is_naked_fn(tcx, inst) => {
+                        let linkage = crate::linkage::get_clif_linkage(
+                            mono_item,
+                            data.linkage,
+                            data.visibility,
+                            tcx.is_compiler_builtins(LOCAL_CRATE),
+                        );
+                        crate::base::codegen_naked_fn(tcx, &mut cx, &mut module, inst, linkage);
+                    }
                     MonoItem::Fn(inst) => {
                         let codegened_function = crate::base::codegen_fn(
                             tcx,
                             &mut cx,
+                            &mut type_dbg,
                             Function::new(),
                             &mut module,
                             inst,
@@ -338,8 +393,16 @@ fn module_codegen(
                 crate::global_asm::compile_global_asm(&global_asm_config, &cgu_name, &cx.global_asm)
             })?;
 
-        let codegen_result =
-            cx.profiler.generic_activity_with_arg("write object file", &*cgu_name).run(|| {
+        // Global asm is already assembly, so include it verbatim.
+        let assembly = cx.assembly.map(|mut assembly| {
+            assembly.push_str(&cx.global_asm);
+            assembly
+        });
+
+        let codegen_result = cx
+            .profiler
+            .generic_activity_with_arg("write object file", &*cgu_name)
+            .run(|| {
                 emit_cgu(
                     &global_asm_config.output_filenames,
                     &cx.profiler,
@@ -349,7 +412,8 @@ fn module_codegen(
                     cx.unwind_context,
                     global_asm_object_file,
                 )
-            });
+            })
+            .map(|codegen_result| ModuleCodegenResult { assembly, ..codegen_result });
         std::mem::drop(token);
         codegen_result
     }))
@@ -391,7 +455,19 @@ pub(crate) fn run_aot(
 
     // Calculate the CGU reuse
     let cgu_reuse = tcx.sess.time("find_cgu_reuse", || {
-        cgus.iter().map(|cgu| determine_cgu_reuse(tcx, &cgu)).collect::<Vec<_>>()
+        cgus.iter()
+            .map(|cgu| {
+                let cgu_reuse = determine_cgu_reuse(tcx, &cgu);
+                if cgu_reuse != CguReuse::No
+                    && tcx.sess.opts.output_types.contains_key(&OutputType::Assembly)
+                    && !cgu.previous_work_product(tcx).saved_files.contains_key("s")
+                {
+                    // The previous session didn't save a listing for `--emit asm`.
+                    return CguReuse::No;
+                }
+                cgu_reuse
+            })
+            .collect::<Vec<_>>()
     });
 
     rustc_codegen_ssa::assert_module_sources::assert_module_sources(tcx, &|cgu_reuse_tracker| {
@@ -27,12 +27,17 @@ fn predefine_mono_items<'tcx>(
                     let _inst_guard = crate::PrintOnPanic(|| format!("{:?} {}", instance, name));
                     let sig =
                         get_function_sig(tcx, module.target_config().default_call_conv, instance);
-                    let linkage = crate::linkage::get_clif_linkage(
-                        mono_item,
-                        data.linkage,
-                        data.visibility,
-                        is_compiler_builtins,
-                    );
+                    // Naked functions are defined in global asm rather than by Cranelift.
+                    let linkage = if crate::base::is_naked_fn(tcx, instance) {
+                        Linkage::Import
+                    } else {
+                        crate::linkage::get_clif_linkage(
+                            mono_item,
+                            data.linkage,
+                            data.visibility,
+                            is_compiler_builtins,
+                        )
+                    };
                     module.declare_function(name, linkage, &sig).unwrap();
                 }
                 MonoItem::Static(_) | MonoItem::GlobalAsm(_) => {}
@@ -98,29 +98,8 @@ pub(crate) fn codegen_inline_asm_terminator<'tcx>(
                         args,
                     )
                     .unwrap();
-                    let symbol = fx.tcx.symbol_name(instance);
-
-                    // Pass a wrapper rather than the function itself as the function itself may not
-                    // be exported from the main codegen unit and may thus be unreachable from the
-                    // object file created by an external assembler.
-                    let inline_asm_index = fx.cx.inline_asm_index.get();
-                    fx.cx.inline_asm_index.set(inline_asm_index + 1);
-                    let wrapper_name = format!(
-                        "__inline_asm_{}_wrapper_n{}",
-                        fx.cx.cgu_name.as_str().replace('.', "__").replace('-', "_"),
-                        inline_asm_index
-                    );
-                    let sig =
-                        get_function_sig(fx.tcx, fx.target_config.default_call_conv, instance);
-                    create_wrapper_function(
-                        fx.module,
-                        &mut fx.cx.unwind_context,
-                        sig,
-                        &wrapper_name,
-                        symbol.name,
-                    );
-
-                    CInlineAsmOperand::Symbol { symbol: wrapper_name }
+                    let symbol = create_sym_fn_wrapper(fx.tcx, fx.cx, fx.module, instance);
+                    CInlineAsmOperand::Symbol { symbol }
                 } else {
                     span_bug!(span, "invalid type for asm sym (fn)");
                 }
@@ -146,6 +125,30 @@ pub(crate) fn codegen_inline_asm_terminator<'tcx>(
     }
 }
 
+/// Pass a wrapper rather than the function itself as the function itself may not be exported from
+/// the main codegen unit and may thus be unreachable from the object file created by an external
+/// assembler.
+fn create_sym_fn_wrapper<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cx: &mut crate::CodegenCx,
+    module: &mut dyn Module,
+    instance: Instance<'tcx>,
+) -> String {
+    let symbol = tcx.symbol_name(instance);
+
+    let inline_asm_index = cx.inline_asm_index.get();
+    cx.inline_asm_index.set(inline_asm_index + 1);
+    let wrapper_name = format!(
+        "__inline_asm_{}_wrapper_n{}",
+        cx.cgu_name.as_str().replace('.', "__").replace('-', "_"),
+        inline_asm_index
+    );
+    let sig = get_function_sig(tcx, module.target_config().default_call_conv, instance);
+    create_wrapper_function(module, &mut cx.unwind_context, sig, &wrapper_name, symbol.name);
+
+    wrapper_name
+}
+
 pub(crate) fn codegen_inline_asm_inner<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     template: &[InlineAsmTemplatePiece],
@@ -206,6 +209,162 @@ pub(crate) fn codegen_inline_asm_inner<'tcx>(
     call_inline_asm(fx, &asm_name, asm_gen.stack_slot_size, inputs, outputs);
 }
 
+pub(crate) fn codegen_naked_asm<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cx: &mut crate::CodegenCx,
+    module: &mut dyn Module,
+    instance: Instance<'tcx>,
+    linkage: Linkage,
+    terminator: &Terminator<'tcx>,
+) {
+    let TerminatorKind::InlineAsm { template, ref operands, options, .. } = terminator.kind else {
+        bug!("naked function body is not a single asm block");
+    };
+    let span = terminator.source_info.span;
+    let symbol_name = tcx.symbol_name(instance).name;
+
+    // Naked functions only allow const and sym operands, so there are no registers to allocate.
+    let operands = operands
+        .iter()
+        .map(|operand| match *operand {
+            InlineAsmOperand::Const { ref value } => {
+                let const_ = instance.instantiate_mir_and_normalize_erasing_regions(
+                    tcx,
+                    ty::ParamEnv::reveal_all(),
+                    ty::EarlyBinder::bind(value.const_),
+                );
+                let const_value = const_
+                    .eval(tcx, ty::ParamEnv::reveal_all(), Some(value.span))
+                    .expect("erroneous constant not captured by required_consts");
+                rustc_codegen_ssa::common::asm_const_to_str(
+                    tcx,
+                    span,
+                    const_value,
+                    RevealAllLayoutCx(tcx).layout_of(const_.ty()),
+                )
+            }
+            InlineAsmOperand::SymFn { ref value } => {
+                if cfg!(not(feature = "inline_asm_sym")) {
+                    tcx.sess
+                        .span_err(span, "asm! and global_asm! sym operands are not yet supported");
+                }
+
+                let const_ = instance.instantiate_mir_and_normalize_erasing_regions(
+                    tcx,
+                    ty::ParamEnv::reveal_all(),
+                    ty::EarlyBinder::bind(value.const_),
+                );
+                if let ty::FnDef(def_id, args) = *const_.ty().kind() {
+                    let instance = ty::Instance::resolve_for_fn_ptr(
+                        tcx,
+                        ty::ParamEnv::reveal_all(),
+                        def_id,
+                        args,
+                    )
+                    .unwrap();
+                    create_sym_fn_wrapper(tcx, cx, module, instance)
+                } else {
+                    span_bug!(span, "invalid type for asm sym (fn)");
+                }
+            }
+            InlineAsmOperand::SymStatic { def_id } => {
+                assert!(tcx.is_static(def_id));
+                let instance = Instance::mono(tcx, def_id).polymorphize(tcx);
+                tcx.symbol_name(instance).name.to_owned()
+            }
+            InlineAsmOperand::In { .. }
+            | InlineAsmOperand::Out { .. }
+            | InlineAsmOperand::InOut { .. } => {
+                span_bug!(span, "invalid operand type for naked function")
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let binary_format = crate::target_triple(tcx.sess).binary_format;
+    let is_x86 = matches!(tcx.sess.asm_arch.unwrap(), InlineAsmArch::X86 | InlineAsmArch::X86_64);
+    let align = tcx.codegen_fn_attrs(instance.def_id()).alignment.unwrap_or(16);
+
+    // The Cranelift module only contains an import of the naked function, so it always needs to be
+    // a global symbol. Functions local to the codegen unit are made hidden and weak instead, as
+    // multiple codegen units may contain a copy of them.
+    let generated_asm = &mut cx.global_asm;
+    match binary_format {
+        BinaryFormat::Elf => {
+            writeln!(generated_asm, ".section .text.{},\"ax\",@progbits", symbol_name).unwrap();
+            writeln!(generated_asm, ".balign {}", align).unwrap();
+            if let Linkage::Local | Linkage::Preemptible = linkage {
+                writeln!(generated_asm, ".weak {}", symbol_name).unwrap();
+            } else {
+                writeln!(generated_asm, ".globl {}", symbol_name).unwrap();
+            }
+            if let Linkage::Local | Linkage::Hidden = linkage {
+                writeln!(generated_asm, ".hidden {}", symbol_name).unwrap();
+            }
+            writeln!(generated_asm, ".type {},@function", symbol_name).unwrap();
+            writeln!(generated_asm, "{}:", symbol_name).unwrap();
+        }
+        BinaryFormat::Macho => {
+            generated_asm.push_str(".text\n");
+            writeln!(generated_asm, ".balign {}", align).unwrap();
+            writeln!(generated_asm, ".globl _{}", symbol_name).unwrap();
+            if let Linkage::Local | Linkage::Preemptible = linkage {
+                writeln!(generated_asm, ".weak_definition _{}", symbol_name).unwrap();
+            }
+            if let Linkage::Local | Linkage::Hidden = linkage {
+                writeln!(generated_asm, ".private_extern _{}", symbol_name).unwrap();
+            }
+            writeln!(generated_asm, "_{}:", symbol_name).unwrap();
+        }
+        BinaryFormat::Coff => {
+            // COFF doesn't have weak definitions. Put functions which may be duplicated into a
+            // COMDAT section of which the linker keeps any one copy instead.
+            if let Linkage::Local | Linkage::Preemptible = linkage {
+                writeln!(generated_asm, ".section .text${},\"xr\"", symbol_name).unwrap();
+                generated_asm.push_str(".linkonce discard\n");
+            } else {
+                generated_asm.push_str(".text\n");
+            }
+            writeln!(generated_asm, ".balign {}", align).unwrap();
+            writeln!(generated_asm, ".globl {}", symbol_name).unwrap();
+            writeln!(generated_asm, "{}:", symbol_name).unwrap();
+        }
+        _ => tcx
+            .sess
+            .fatal(format!("Unsupported binary format for naked functions: {binary_format:?}")),
+    }
+
+    if is_x86 {
+        if options.contains(InlineAsmOptions::ATT_SYNTAX) {
+            generated_asm.push_str(".att_syntax\n");
+        } else {
+            generated_asm.push_str(".intel_syntax noprefix\n");
+        }
+    }
+
+    for piece in template {
+        match piece {
+            InlineAsmTemplatePiece::String(s) => generated_asm.push_str(s),
+            InlineAsmTemplatePiece::Placeholder { operand_idx, modifier: _, span: _ } => {
+                generated_asm.push_str(&operands[*operand_idx])
+            }
+        }
+    }
+    generated_asm.push('\n');
+
+    if is_x86 {
+        generated_asm.push_str(".att_syntax\n");
+    }
+
+    if binary_format == BinaryFormat::Elf {
+        writeln!(generated_asm, ".size {name}, .-{name}", name = symbol_name).unwrap();
+    }
+    if let BinaryFormat::Elf | BinaryFormat::Coff = binary_format {
+        generated_asm.push_str(".text\n");
+    }
+
+    generated_asm.push_str("\n\n");
+}
+
 struct InlineAssemblyGenerator<'a, 'tcx> {
     tcx: TyCtxt<'tcx>,
     arch: InlineAsmArch,
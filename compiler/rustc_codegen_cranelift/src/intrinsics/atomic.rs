@@ -0,0 +1,196 @@
+//! Codegen of atomic memory operations.
+//!
+//! Cranelift natively supports atomics up to the pointer width. 128bit atomics are emulated using
+//! a compare-and-swap loop around `cmpxchg16b`, so they are only supported on x86_64 when the
+//! `cmpxchg16b` target feature is enabled.
+
+use cranelift_codegen::ir::AtomicRmwOp;
+use rustc_ast::ast::{InlineAsmOptions, InlineAsmTemplatePiece};
+use rustc_target::asm::*;
+
+use crate::inline_asm::{codegen_inline_asm_inner, CInlineAsmOperand};
+use crate::prelude::*;
+
+pub(super) fn codegen_atomic_load(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ty: Type,
+    ptr: Value,
+    span: Span,
+) -> Value {
+    if ty == types::I128 {
+        // Replacing the value with itself doesn't change memory, but still returns the old value.
+        let zero = fx.bcx.ins().iconst(types::I64, 0);
+        let zero = fx.bcx.ins().iconcat(zero, zero);
+        codegen_atomic_cas_128(fx, ptr, zero, zero, span)
+    } else {
+        fx.bcx.ins().atomic_load(ty, MemFlags::trusted(), ptr)
+    }
+}
+
+pub(super) fn codegen_atomic_store(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ptr: Value,
+    val: Value,
+    span: Span,
+) {
+    if fx.bcx.func.dfg.value_type(val) == types::I128 {
+        codegen_atomic_rmw(fx, types::I128, AtomicRmwOp::Xchg, ptr, val, span);
+    } else {
+        fx.bcx.ins().atomic_store(MemFlags::trusted(), val, ptr);
+    }
+}
+
+pub(super) fn codegen_atomic_cas(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ptr: Value,
+    test_old: Value,
+    new: Value,
+    span: Span,
+) -> Value {
+    if fx.bcx.func.dfg.value_type(new) == types::I128 {
+        codegen_atomic_cas_128(fx, ptr, test_old, new, span)
+    } else {
+        fx.bcx.ins().atomic_cas(MemFlags::trusted(), ptr, test_old, new)
+    }
+}
+
+/// Atomically apply `op` to the value at `ptr` and `src` and return the old value.
+pub(super) fn codegen_atomic_rmw(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ty: Type,
+    op: AtomicRmwOp,
+    ptr: Value,
+    src: Value,
+    span: Span,
+) -> Value {
+    if ty != types::I128 {
+        return fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), op, ptr, src);
+    }
+
+    // Start with a guess for the current value. If it is wrong, the compare-and-swap returns the
+    // actual value, which is used for the next attempt.
+    let loop_block = fx.bcx.create_block();
+    let cur = fx.bcx.append_block_param(loop_block, types::I128);
+    let done_block = fx.bcx.create_block();
+    let guess = fx.bcx.ins().iconst(types::I64, 0);
+    let guess = fx.bcx.ins().iconcat(guess, guess);
+    fx.bcx.ins().jump(loop_block, &[guess]);
+
+    fx.bcx.switch_to_block(loop_block);
+    let new = match op {
+        AtomicRmwOp::Xchg => src,
+        AtomicRmwOp::Add => fx.bcx.ins().iadd(cur, src),
+        AtomicRmwOp::Sub => fx.bcx.ins().isub(cur, src),
+        AtomicRmwOp::And => fx.bcx.ins().band(cur, src),
+        AtomicRmwOp::Nand => {
+            let and = fx.bcx.ins().band(cur, src);
+            fx.bcx.ins().bnot(and)
+        }
+        AtomicRmwOp::Or => fx.bcx.ins().bor(cur, src),
+        AtomicRmwOp::Xor => fx.bcx.ins().bxor(cur, src),
+        // The min and max instructions are not implemented for 128bit integers. A branch is used
+        // rather than a select as the optimizer would turn an icmp+select back into them.
+        AtomicRmwOp::Umin | AtomicRmwOp::Umax | AtomicRmwOp::Smin | AtomicRmwOp::Smax => {
+            let cc = match op {
+                AtomicRmwOp::Umin => IntCC::UnsignedLessThan,
+                AtomicRmwOp::Umax => IntCC::UnsignedGreaterThan,
+                AtomicRmwOp::Smin => IntCC::SignedLessThan,
+                AtomicRmwOp::Smax => IntCC::SignedGreaterThan,
+                _ => unreachable!(),
+            };
+            let keep_cur = fx.bcx.ins().icmp(cc, cur, src);
+
+            let take_src_block = fx.bcx.create_block();
+            let cas_block = fx.bcx.create_block();
+            let new = fx.bcx.append_block_param(cas_block, types::I128);
+            fx.bcx.ins().brif(keep_cur, cas_block, &[cur], take_src_block, &[]);
+
+            fx.bcx.switch_to_block(take_src_block);
+            fx.bcx.seal_block(take_src_block);
+            fx.bcx.ins().jump(cas_block, &[src]);
+
+            fx.bcx.switch_to_block(cas_block);
+            fx.bcx.seal_block(cas_block);
+            new
+        }
+    };
+    let old = codegen_atomic_cas_128(fx, ptr, cur, new, span);
+    let is_eq = fx.bcx.ins().icmp(IntCC::Equal, old, cur);
+    fx.bcx.ins().brif(is_eq, done_block, &[], loop_block, &[old]);
+    fx.bcx.seal_block(loop_block);
+
+    fx.bcx.switch_to_block(done_block);
+    fx.bcx.seal_block(done_block);
+    cur
+}
+
+/// Atomically replace the value at `ptr` with `new` if it is equal to `expected` and return the
+/// old value.
+fn codegen_atomic_cas_128(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ptr: Value,
+    expected: Value,
+    new: Value,
+    span: Span,
+) -> Value {
+    // Inline asm can't be used in jit mode.
+    let has_cmpxchg16b = fx.tcx.sess.target.arch == "x86_64"
+        && fx
+            .tcx
+            .asm_target_features(fx.instance.def_id())
+            .contains(&rustc_span::Symbol::intern("cmpxchg16b"));
+    if !has_cmpxchg16b || fx.cx.is_jit {
+        fx.tcx.sess.span_fatal(
+            span,
+            "128bit atomics are only supported on x86_64 with the `cmpxchg16b` target feature \
+            and not in jit mode",
+        );
+    }
+
+    let (expected_lo, expected_hi) = fx.bcx.ins().isplit(expected);
+    let (new_lo, new_hi) = fx.bcx.ins().isplit(new);
+    let u64_layout = fx.layout_of(fx.tcx.types.u64);
+    let old_lo = CPlace::new_stack_slot(fx, u64_layout);
+    let old_hi = CPlace::new_stack_slot(fx, u64_layout);
+
+    let reg = |reg| InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(reg));
+    codegen_inline_asm_inner(
+        fx,
+        &[InlineAsmTemplatePiece::String(
+            "
+                // rbx is used by the inline asm wrapper and can't be an operand
+                xchg rsi, rbx
+                lock cmpxchg16b xmmword ptr [rdi]
+                mov rbx, rsi
+                "
+            .to_string(),
+        )],
+        &[
+            CInlineAsmOperand::In { reg: reg(X86InlineAsmReg::di), value: ptr },
+            CInlineAsmOperand::InOut {
+                reg: reg(X86InlineAsmReg::si),
+                _late: true,
+                in_value: new_lo,
+                out_place: None,
+            },
+            CInlineAsmOperand::In { reg: reg(X86InlineAsmReg::cx), value: new_hi },
+            CInlineAsmOperand::InOut {
+                reg: reg(X86InlineAsmReg::ax),
+                _late: true,
+                in_value: expected_lo,
+                out_place: Some(old_lo),
+            },
+            CInlineAsmOperand::InOut {
+                reg: reg(X86InlineAsmReg::dx),
+                _late: true,
+                in_value: expected_hi,
+                out_place: Some(old_hi),
+            },
+        ],
+        InlineAsmOptions::NOSTACK,
+    );
+
+    let old_lo = old_lo.to_cvalue(fx).load_scalar(fx);
+    let old_hi = old_hi.to_cvalue(fx).load_scalar(fx);
+    fx.bcx.ins().iconcat(old_lo, old_hi)
+}
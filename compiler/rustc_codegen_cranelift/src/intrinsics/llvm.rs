@@ -13,9 +13,9 @@ pub(crate) fn codegen_llvm_intrinsic_call<'tcx>(
     ret: CPlace<'tcx>,
     target: Option<BasicBlock>,
     span: Span,
-) {
+) -> CodegenResult {
     if intrinsic.starts_with("llvm.aarch64") {
-        return llvm_aarch64::codegen_aarch64_llvm_intrinsic_call(
+        llvm_aarch64::codegen_aarch64_llvm_intrinsic_call(
             fx,
             intrinsic,
             generic_args,
@@ -23,6 +23,7 @@ pub(crate) fn codegen_llvm_intrinsic_call<'tcx>(
             ret,
             target,
         );
+        return Ok(());
     }
     if intrinsic.starts_with("llvm.x86") {
         return llvm_x86::codegen_x86_llvm_intrinsic_call(
@@ -73,11 +74,13 @@ pub(crate) fn codegen_llvm_intrinsic_call<'tcx>(
                 .sess
                 .warn(format!("unsupported llvm intrinsic {}; replacing with trap", intrinsic));
             crate::trap::trap_unimplemented(fx, intrinsic);
-            return;
+            return Ok(());
         }
     }
 
     let dest = target.expect("all llvm intrinsics used by stdlib should return");
     let ret_block = fx.get_block(dest);
     fx.bcx.ins().jump(ret_block, &[]);
+
+    Ok(())
 }
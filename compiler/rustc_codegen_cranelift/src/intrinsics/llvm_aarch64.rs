@@ -244,22 +244,191 @@ pub(crate) fn codegen_aarch64_llvm_intrinsic_call<'tcx>(
             );
         }
 
-        // FIXME generalize vector types
-        "llvm.aarch64.neon.tbl1.v16i8" => {
-            intrinsic_args!(fx, args => (t, idx); intrinsic);
+        _ if intrinsic.starts_with("llvm.aarch64.neon.tbl1.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.tbx1.v") =>
+        {
+            // Out of range indices result in zero for tbl and leave the destination lane
+            // unchanged for tbx.
+            let (fallback, t, idx) = match args {
+                [t, idx] => (None, t, idx),
+                [fallback, t, idx] => (Some(codegen_operand(fx, fallback)), t, idx),
+                _ => bug!("wrong number of args for intrinsic {intrinsic}"),
+            };
+            let t = codegen_operand(fx, t);
+            let idx = codegen_operand(fx, idx);
 
+            let (lane_count, _lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
             let zero = fx.bcx.ins().iconst(types::I8, 0);
-            for i in 0..16 {
+            for i in 0..lane_count {
                 let idx_lane = idx.value_lane(fx, i).load_scalar(fx);
-                let is_zero =
+                let is_out_of_range =
                     fx.bcx.ins().icmp_imm(IntCC::UnsignedGreaterThanOrEqual, idx_lane, 16);
                 let t_idx = fx.bcx.ins().uextend(fx.pointer_type, idx_lane);
                 let t_lane = t.value_lane_dyn(fx, t_idx).load_scalar(fx);
-                let res = fx.bcx.ins().select(is_zero, zero, t_lane);
+                let fallback_lane = match fallback {
+                    Some(fallback) => fallback.value_lane(fx, i).load_scalar(fx),
+                    None => zero,
+                };
+                let res = fx.bcx.ins().select(is_out_of_range, fallback_lane, t_lane);
                 ret.place_lane(fx, i).to_ptr().store(fx, res, MemFlags::trusted());
             }
         }
 
+        _ if intrinsic.starts_with("llvm.aarch64.neon.uaddv.i")
+            || intrinsic.starts_with("llvm.aarch64.neon.saddv.i") =>
+        {
+            intrinsic_args!(fx, args => (v); intrinsic);
+
+            simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| fx.bcx.ins().iadd(a, b));
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.uaddlv.i")
+            || intrinsic.starts_with("llvm.aarch64.neon.saddlv.i") =>
+        {
+            intrinsic_args!(fx, args => (v); intrinsic);
+
+            // The lanes are widened to the return type before adding them up.
+            let (lane_count, _lane_ty) = v.layout().ty.simd_size_and_type(fx.tcx);
+            let ret_ty = fx.clif_type(ret.layout().ty).unwrap();
+            let is_signed = intrinsic.starts_with("llvm.aarch64.neon.saddlv.i");
+            let mut res = fx.bcx.ins().iconst(ret_ty, 0);
+            for lane_idx in 0..lane_count {
+                let lane = v.value_lane(fx, lane_idx).load_scalar(fx);
+                let lane = if is_signed {
+                    fx.bcx.ins().sextend(ret_ty, lane)
+                } else {
+                    fx.bcx.ins().uextend(ret_ty, lane)
+                };
+                res = fx.bcx.ins().iadd(res, lane);
+            }
+            ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.fmaxv.f")
+            || intrinsic.starts_with("llvm.aarch64.neon.fminv.f") =>
+        {
+            intrinsic_args!(fx, args => (v); intrinsic);
+
+            let is_max = intrinsic.starts_with("llvm.aarch64.neon.fmaxv.f");
+            simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| {
+                if is_max { fx.bcx.ins().fmax(a, b) } else { fx.bcx.ins().fmin(a, b) }
+            });
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.fmaxnmv.f")
+            || intrinsic.starts_with("llvm.aarch64.neon.fminnmv.f") =>
+        {
+            intrinsic_args!(fx, args => (v); intrinsic);
+
+            // Unlike fmax and fmin, a NaN is only returned when both operands are NaN.
+            let is_max = intrinsic.starts_with("llvm.aarch64.neon.fmaxnmv.f");
+            simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| {
+                let res = if is_max { fx.bcx.ins().fmax(a, b) } else { fx.bcx.ins().fmin(a, b) };
+                let a_is_nan = fx.bcx.ins().fcmp(FloatCC::Unordered, a, a);
+                let b_is_nan = fx.bcx.ins().fcmp(FloatCC::Unordered, b, b);
+                let res = fx.bcx.ins().select(b_is_nan, a, res);
+                fx.bcx.ins().select(a_is_nan, b, res)
+            });
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.uhadd.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.shadd.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.urhadd.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.srhadd.v") =>
+        {
+            intrinsic_args!(fx, args => (x, y); intrinsic);
+
+            // (x + y + round) >> 1 without overflow
+            let is_signed = intrinsic.starts_with("llvm.aarch64.neon.s");
+            let is_rounding = intrinsic.contains(".urhadd.") || intrinsic.contains(".srhadd.");
+            simd_pair_for_each_lane(
+                fx,
+                x,
+                y,
+                ret,
+                &|fx, _lane_ty, _res_lane_ty, x_lane, y_lane| {
+                    let lane_ty = fx.bcx.func.dfg.value_type(x_lane);
+                    let wide_ty = lane_ty.double_width().unwrap();
+                    let (x_lane, y_lane) = if is_signed {
+                        (
+                            fx.bcx.ins().sextend(wide_ty, x_lane),
+                            fx.bcx.ins().sextend(wide_ty, y_lane),
+                        )
+                    } else {
+                        (
+                            fx.bcx.ins().uextend(wide_ty, x_lane),
+                            fx.bcx.ins().uextend(wide_ty, y_lane),
+                        )
+                    };
+                    let sum = fx.bcx.ins().iadd(x_lane, y_lane);
+                    let sum = if is_rounding { fx.bcx.ins().iadd_imm(sum, 1) } else { sum };
+                    let res = if is_signed {
+                        fx.bcx.ins().sshr_imm(sum, 1)
+                    } else {
+                        fx.bcx.ins().ushr_imm(sum, 1)
+                    };
+                    fx.bcx.ins().ireduce(lane_ty, res)
+                },
+            );
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.uabd.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.sabd.v") =>
+        {
+            intrinsic_args!(fx, args => (x, y); intrinsic);
+
+            let cc = if intrinsic.starts_with("llvm.aarch64.neon.sabd.v") {
+                IntCC::SignedGreaterThan
+            } else {
+                IntCC::UnsignedGreaterThan
+            };
+            simd_pair_for_each_lane(
+                fx,
+                x,
+                y,
+                ret,
+                &|fx, _lane_ty, _res_lane_ty, x_lane, y_lane| {
+                    let gt = fx.bcx.ins().icmp(cc, x_lane, y_lane);
+                    let x_minus_y = fx.bcx.ins().isub(x_lane, y_lane);
+                    let y_minus_x = fx.bcx.ins().isub(y_lane, x_lane);
+                    fx.bcx.ins().select(gt, x_minus_y, y_minus_x)
+                },
+            );
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.fabd.v") => {
+            intrinsic_args!(fx, args => (x, y); intrinsic);
+
+            simd_pair_for_each_lane(
+                fx,
+                x,
+                y,
+                ret,
+                &|fx, _lane_ty, _res_lane_ty, x_lane, y_lane| {
+                    let diff = fx.bcx.ins().fsub(x_lane, y_lane);
+                    fx.bcx.ins().fabs(diff)
+                },
+            );
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.umull.v")
+            || intrinsic.starts_with("llvm.aarch64.neon.smull.v") =>
+        {
+            intrinsic_args!(fx, args => (x, y); intrinsic);
+
+            // The result lanes are twice as wide as the input lanes.
+            let is_signed = intrinsic.starts_with("llvm.aarch64.neon.smull.v");
+            simd_pair_for_each_lane(fx, x, y, ret, &|fx, _lane_ty, res_lane_ty, x_lane, y_lane| {
+                let res_ty = fx.clif_type(res_lane_ty).unwrap();
+                let (x_lane, y_lane) = if is_signed {
+                    (fx.bcx.ins().sextend(res_ty, x_lane), fx.bcx.ins().sextend(res_ty, y_lane))
+                } else {
+                    (fx.bcx.ins().uextend(res_ty, x_lane), fx.bcx.ins().uextend(res_ty, y_lane))
+                };
+                fx.bcx.ins().imul(x_lane, y_lane)
+            });
+        }
+
         /*
         _ if intrinsic.starts_with("llvm.aarch64.neon.sshl.v")
             || intrinsic.starts_with("llvm.aarch64.neon.sqshl.v")
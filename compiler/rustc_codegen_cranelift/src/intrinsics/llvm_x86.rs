@@ -16,12 +16,81 @@ pub(crate) fn codegen_x86_llvm_intrinsic_call<'tcx>(
     ret: CPlace<'tcx>,
     target: Option<BasicBlock>,
     span: Span,
-) {
+) -> CodegenResult {
     match intrinsic {
         "llvm.x86.sse2.pause" | "llvm.aarch64.isb" => {
             // Spin loop hint
         }
 
+        "llvm.x86.sse2.lfence" | "llvm.x86.sse2.mfence" => {
+            fx.bcx.ins().fence();
+        }
+
+        "llvm.x86.rdtsc" => {
+            codegen_inline_asm_inner(
+                fx,
+                &[InlineAsmTemplatePiece::String(
+                    "
+                    rdtsc
+                    // out = rdx << 32 | rax
+                    shl rdx, 32
+                    or rax, rdx
+                    "
+                    .to_string(),
+                )],
+                &[
+                    CInlineAsmOperand::Out {
+                        reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::ax)),
+                        late: true,
+                        place: Some(ret),
+                    },
+                    CInlineAsmOperand::Out {
+                        reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::dx)),
+                        late: true,
+                        place: None,
+                    },
+                ],
+                InlineAsmOptions::NOSTACK | InlineAsmOptions::NOMEM,
+            );
+        }
+        "llvm.x86.rdtscp" => {
+            intrinsic_args!(fx, args => (aux); intrinsic);
+
+            // The contents of the IA32_TSC_AUX register are written to `aux`.
+            let aux = aux.load_scalar(fx);
+            let aux = CPlace::for_ptr(Pointer::new(aux), fx.layout_of(fx.tcx.types.u32));
+            codegen_inline_asm_inner(
+                fx,
+                &[InlineAsmTemplatePiece::String(
+                    "
+                    rdtscp
+                    // out = rdx << 32 | rax
+                    shl rdx, 32
+                    or rax, rdx
+                    "
+                    .to_string(),
+                )],
+                &[
+                    CInlineAsmOperand::Out {
+                        reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::ax)),
+                        late: true,
+                        place: Some(ret),
+                    },
+                    CInlineAsmOperand::Out {
+                        reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::cx)),
+                        late: true,
+                        place: Some(aux),
+                    },
+                    CInlineAsmOperand::Out {
+                        reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::dx)),
+                        late: true,
+                        place: None,
+                    },
+                ],
+                InlineAsmOptions::NOSTACK | InlineAsmOptions::NOMEM,
+            );
+        }
+
         // Used by is_x86_feature_detected!();
         "llvm.x86.xgetbv" => {
             intrinsic_args!(fx, args => (xcr_no); intrinsic);
@@ -181,6 +250,98 @@ pub(crate) fn codegen_x86_llvm_intrinsic_call<'tcx>(
                 bool_to_zero_or_max_uint(fx, res_lane_ty, res_lane)
             });
         }
+        "llvm.x86.sse2.min.pd" | "llvm.x86.sse2.max.pd" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_min_pd&ig_expand=4560
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            // The second operand is returned if either operand is NaN or both are zero, so this
+            // can't use fmin and fmax.
+            let flt_cc = if intrinsic == "llvm.x86.sse2.min.pd" {
+                FloatCC::LessThan
+            } else {
+                FloatCC::GreaterThan
+            };
+            simd_pair_for_each_lane(
+                fx,
+                a,
+                b,
+                ret,
+                &|fx, _lane_ty, _res_lane_ty, a_lane, b_lane| {
+                    let use_a = fx.bcx.ins().fcmp(flt_cc, a_lane, b_lane);
+                    fx.bcx.ins().select(use_a, a_lane, b_lane)
+                },
+            );
+        }
+        "llvm.x86.sse2.min.sd" | "llvm.x86.sse2.max.sd" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_min_sd&ig_expand=4566
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            let flt_cc = if intrinsic == "llvm.x86.sse2.min.sd" {
+                FloatCC::LessThan
+            } else {
+                FloatCC::GreaterThan
+            };
+            let a_lane = a.value_lane(fx, 0).load_scalar(fx);
+            let b_lane = b.value_lane(fx, 0).load_scalar(fx);
+            let use_a = fx.bcx.ins().fcmp(flt_cc, a_lane, b_lane);
+            let res = fx.bcx.ins().select(use_a, a_lane, b_lane);
+
+            ret.write_cvalue(fx, a);
+            let res_lane = CValue::by_val(res, fx.layout_of(fx.tcx.types.f64));
+            ret.place_lane(fx, 0).write_cvalue(fx, res_lane);
+        }
+        "llvm.x86.sse2.sqrt.pd" => {
+            intrinsic_args!(fx, args => (a); intrinsic);
+
+            simd_for_each_lane(fx, a, ret, &|fx, _lane_ty, _res_lane_ty, a_lane| {
+                fx.bcx.ins().sqrt(a_lane)
+            });
+        }
+        "llvm.x86.sse2.sqrt.sd" => {
+            intrinsic_args!(fx, args => (a); intrinsic);
+
+            let a_lane = a.value_lane(fx, 0).load_scalar(fx);
+            let res = fx.bcx.ins().sqrt(a_lane);
+
+            ret.write_cvalue(fx, a);
+            let res_lane = CValue::by_val(res, fx.layout_of(fx.tcx.types.f64));
+            ret.place_lane(fx, 0).write_cvalue(fx, res_lane);
+        }
+        "llvm.x86.sse2.comieq.sd"
+        | "llvm.x86.sse2.comineq.sd"
+        | "llvm.x86.sse2.comilt.sd"
+        | "llvm.x86.sse2.comile.sd"
+        | "llvm.x86.sse2.comigt.sd"
+        | "llvm.x86.sse2.comige.sd"
+        | "llvm.x86.sse2.ucomieq.sd"
+        | "llvm.x86.sse2.ucomineq.sd"
+        | "llvm.x86.sse2.ucomilt.sd"
+        | "llvm.x86.sse2.ucomile.sd"
+        | "llvm.x86.sse2.ucomigt.sd"
+        | "llvm.x86.sse2.ucomige.sd" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_comieq_sd&ig_expand=1191
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            // The comi and ucomi variants only differ in whether a quiet NaN raises an exception.
+            let flt_cc =
+                match intrinsic.trim_start_matches("llvm.x86.sse2.").trim_start_matches('u') {
+                    "comieq.sd" => FloatCC::Equal,
+                    "comineq.sd" => FloatCC::NotEqual,
+                    "comilt.sd" => FloatCC::LessThan,
+                    "comile.sd" => FloatCC::LessThanOrEqual,
+                    "comigt.sd" => FloatCC::GreaterThan,
+                    "comige.sd" => FloatCC::GreaterThanOrEqual,
+                    _ => unreachable!(),
+                };
+            let a_lane = a.value_lane(fx, 0).load_scalar(fx);
+            let b_lane = b.value_lane(fx, 0).load_scalar(fx);
+            let res = fx.bcx.ins().fcmp(flt_cc, a_lane, b_lane);
+            let res = CValue::by_val(
+                fx.bcx.ins().uextend(types::I32, res),
+                fx.layout_of(fx.tcx.types.i32),
+            );
+            ret.write_cvalue(fx, res);
+        }
         "llvm.x86.ssse3.pshuf.b.128" | "llvm.x86.avx2.pshuf.b" => {
             let (a, b) = match args {
                 [a, b] => (a, b),
@@ -333,7 +494,7 @@ fn select4(
                 },
             );
         }
-        "llvm.x86.sse2.psra.w" => {
+        "llvm.x86.sse2.psra.w" | "llvm.x86.sse2.psra.d" => {
             intrinsic_args!(fx, args => (a, count); intrinsic);
 
             let count_lane = count.force_stack(fx).0.load(fx, types::I64, MemFlags::trusted());
@@ -346,6 +507,37 @@ fn select4(
                 fx.bcx.ins().sshr(a_lane, saturated_count)
             });
         }
+        "llvm.x86.sse2.psll.w"
+        | "llvm.x86.sse2.psll.d"
+        | "llvm.x86.sse2.psll.q"
+        | "llvm.x86.sse2.psrl.w"
+        | "llvm.x86.sse2.psrl.d"
+        | "llvm.x86.sse2.psrl.q" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_sll_epi16&ig_expand=6326
+            intrinsic_args!(fx, args => (a, count); intrinsic);
+
+            let count_lane = count.force_stack(fx).0.load(fx, types::I64, MemFlags::trusted());
+            let lane_ty = fx.clif_type(a.layout().ty.simd_size_and_type(fx.tcx).1).unwrap();
+            // Unlike the Cranelift shift instructions, shifting by at least the lane width
+            // results in zero rather than masking the shift amount.
+            let out_of_range = fx.bcx.ins().icmp_imm(
+                IntCC::UnsignedGreaterThanOrEqual,
+                count_lane,
+                i64::from(lane_ty.bits()),
+            );
+            let is_left_shift = intrinsic.starts_with("llvm.x86.sse2.psll");
+
+            // FIXME use vector instructions when possible
+            simd_for_each_lane(fx, a, ret, &|fx, _lane_ty, _res_lane_ty, a_lane| {
+                let shifted = if is_left_shift {
+                    fx.bcx.ins().ishl(a_lane, count_lane)
+                } else {
+                    fx.bcx.ins().ushr(a_lane, count_lane)
+                };
+                let zero = fx.bcx.ins().iconst(lane_ty, 0);
+                fx.bcx.ins().select(out_of_range, zero, shifted)
+            });
+        }
         "llvm.x86.sse2.psad.bw" | "llvm.x86.avx2.psad.bw" => {
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_sad_epu8&ig_expand=5770
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_sad_epu8&ig_expand=5771
@@ -526,6 +718,44 @@ fn select4(
             }
         }
 
+        "llvm.x86.sse2.packsswb.128" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_packs_epi16&ig_expand=4883
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            assert_eq!(a.layout(), b.layout());
+            let layout = a.layout();
+
+            let (lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
+            let (ret_lane_count, ret_lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
+            assert_eq!(lane_ty, fx.tcx.types.i16);
+            assert_eq!(ret_lane_ty, fx.tcx.types.i8);
+            assert_eq!(lane_count * 2, ret_lane_count);
+
+            let min_i8 = fx.bcx.ins().iconst(types::I16, i64::from(i8::MIN as u16));
+            let max_i8 = fx.bcx.ins().iconst(types::I16, i64::from(i8::MAX as u16));
+            let ret_lane_layout = fx.layout_of(fx.tcx.types.i8);
+
+            for idx in 0..lane_count {
+                let lane = a.value_lane(fx, idx).load_scalar(fx);
+                let sat = fx.bcx.ins().smax(lane, min_i8);
+                let sat = fx.bcx.ins().smin(sat, max_i8);
+                let res = fx.bcx.ins().ireduce(types::I8, sat);
+
+                let res_lane = CValue::by_val(res, ret_lane_layout);
+                ret.place_lane(fx, idx).write_cvalue(fx, res_lane);
+            }
+
+            for idx in 0..lane_count {
+                let lane = b.value_lane(fx, idx).load_scalar(fx);
+                let sat = fx.bcx.ins().smax(lane, min_i8);
+                let sat = fx.bcx.ins().smin(sat, max_i8);
+                let res = fx.bcx.ins().ireduce(types::I8, sat);
+
+                let res_lane = CValue::by_val(res, ret_lane_layout);
+                ret.place_lane(fx, lane_count + idx).write_cvalue(fx, res_lane);
+            }
+        }
+
         "llvm.x86.avx2.packuswb" => {
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_packus_epi16&ig_expand=4906
             intrinsic_args!(fx, args => (a, b); intrinsic);
@@ -597,14 +827,14 @@ fn select4(
             assert_eq!(ret_lane_ty, fx.tcx.types.i16);
             assert_eq!(lane_count * 2, ret_lane_count);
 
-            let min_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MIN as u16));
-            let max_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MAX as u16));
+            let min_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MIN as u32));
+            let max_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MAX as u32));
             let ret_lane_layout = fx.layout_of(fx.tcx.types.i16);
 
             for idx in 0..lane_count {
                 let lane = a.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -614,7 +844,7 @@ fn select4(
             for idx in 0..lane_count {
                 let lane = b.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -673,14 +903,14 @@ fn select4(
             assert_eq!(ret_lane_ty, fx.tcx.types.i16);
             assert_eq!(lane_count * 2, ret_lane_count);
 
-            let min_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MIN as u16));
-            let max_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MAX as u16));
+            let min_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MIN as u32));
+            let max_i16 = fx.bcx.ins().iconst(types::I32, i64::from(i16::MAX as u32));
             let ret_lane_layout = fx.layout_of(fx.tcx.types.i16);
 
             for idx in 0..lane_count / 2 {
                 let lane = a.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -690,7 +920,7 @@ fn select4(
             for idx in 0..lane_count / 2 {
                 let lane = b.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -700,7 +930,7 @@ fn select4(
             for idx in 0..lane_count / 2 {
                 let lane = a.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -710,7 +940,7 @@ fn select4(
             for idx in 0..lane_count / 2 {
                 let lane = b.value_lane(fx, idx).load_scalar(fx);
                 let sat = fx.bcx.ins().smax(lane, min_i16);
-                let sat = fx.bcx.ins().umin(sat, max_i16);
+                let sat = fx.bcx.ins().smin(sat, max_i16);
                 let res = fx.bcx.ins().ireduce(types::I16, sat);
 
                 let res_lane = CValue::by_val(res, ret_lane_layout);
@@ -1037,13 +1267,15 @@ fn select4(
                 .sess
                 .warn(format!("unsupported x86 llvm intrinsic {}; replacing with trap", intrinsic));
             crate::trap::trap_unimplemented(fx, intrinsic);
-            return;
+            return Ok(());
         }
     }
 
     let dest = target.expect("all llvm intrinsics used by stdlib should return");
     let ret_block = fx.get_block(dest);
     fx.bcx.ins().jump(ret_block, &[]);
+
+    Ok(())
 }
 
 // llvm.x86.avx2.vperm2i128
@@ -12,6 +12,7 @@ macro_rules! intrinsic_args {
     }
 }
 
+mod atomic;
 mod llvm;
 mod llvm_aarch64;
 mod llvm_x86;
@@ -48,15 +49,6 @@ fn report_atomic_type_validation_error<'tcx>(
     fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
 }
 
-pub(crate) fn clif_vector_type<'tcx>(tcx: TyCtxt<'tcx>, layout: TyAndLayout<'tcx>) -> Type {
-    let (element, count) = match layout.abi {
-        Abi::Vector { element, count } => (element, count),
-        _ => unreachable!(),
-    };
-
-    scalar_to_clif_type(tcx, element).by(u32::try_from(count).unwrap()).unwrap()
-}
-
 fn simd_for_each_lane<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     val: CValue<'tcx>,
@@ -267,7 +259,7 @@ pub(crate) fn codegen_intrinsic_call<'tcx>(
     destination: CPlace<'tcx>,
     target: Option<BasicBlock>,
     source_info: mir::SourceInfo,
-) {
+) -> CodegenResult {
     let intrinsic = fx.tcx.item_name(instance.def_id());
     let instance_args = instance.args;
 
@@ -280,10 +272,11 @@ pub(crate) fn codegen_intrinsic_call<'tcx>(
             destination,
             target.expect("target for simd intrinsic"),
             source_info.span,
-        );
+        )
     } else if codegen_float_intrinsic_call(fx, intrinsic, args, destination) {
         let ret_block = fx.get_block(target.expect("target for float intrinsic"));
         fx.bcx.ins().jump(ret_block, &[]);
+        Ok(())
     } else {
         codegen_regular_intrinsic_call(
             fx,
@@ -294,7 +287,7 @@ pub(crate) fn codegen_intrinsic_call<'tcx>(
             destination,
             target,
             source_info,
-        );
+        )
     }
 }
 
@@ -304,45 +297,46 @@ fn codegen_float_intrinsic_call<'tcx>(
     args: &[mir::Operand<'tcx>],
     ret: CPlace<'tcx>,
 ) -> bool {
-    let (name, arg_count, ty, clif_ty) = match intrinsic {
-        sym::expf32 => ("expf", 1, fx.tcx.types.f32, types::F32),
-        sym::expf64 => ("exp", 1, fx.tcx.types.f64, types::F64),
-        sym::exp2f32 => ("exp2f", 1, fx.tcx.types.f32, types::F32),
-        sym::exp2f64 => ("exp2", 1, fx.tcx.types.f64, types::F64),
-        sym::sqrtf32 => ("sqrtf", 1, fx.tcx.types.f32, types::F32),
-        sym::sqrtf64 => ("sqrt", 1, fx.tcx.types.f64, types::F64),
-        sym::powif32 => ("__powisf2", 2, fx.tcx.types.f32, types::F32), // compiler-builtins
-        sym::powif64 => ("__powidf2", 2, fx.tcx.types.f64, types::F64), // compiler-builtins
-        sym::powf32 => ("powf", 2, fx.tcx.types.f32, types::F32),
-        sym::powf64 => ("pow", 2, fx.tcx.types.f64, types::F64),
-        sym::logf32 => ("logf", 1, fx.tcx.types.f32, types::F32),
-        sym::logf64 => ("log", 1, fx.tcx.types.f64, types::F64),
-        sym::log2f32 => ("log2f", 1, fx.tcx.types.f32, types::F32),
-        sym::log2f64 => ("log2", 1, fx.tcx.types.f64, types::F64),
-        sym::log10f32 => ("log10f", 1, fx.tcx.types.f32, types::F32),
-        sym::log10f64 => ("log10", 1, fx.tcx.types.f64, types::F64),
-        sym::fabsf32 => ("fabsf", 1, fx.tcx.types.f32, types::F32),
-        sym::fabsf64 => ("fabs", 1, fx.tcx.types.f64, types::F64),
-        sym::fmaf32 => ("fmaf", 3, fx.tcx.types.f32, types::F32),
-        sym::fmaf64 => ("fma", 3, fx.tcx.types.f64, types::F64),
-        sym::copysignf32 => ("copysignf", 2, fx.tcx.types.f32, types::F32),
-        sym::copysignf64 => ("copysign", 2, fx.tcx.types.f64, types::F64),
-        sym::floorf32 => ("floorf", 1, fx.tcx.types.f32, types::F32),
-        sym::floorf64 => ("floor", 1, fx.tcx.types.f64, types::F64),
-        sym::ceilf32 => ("ceilf", 1, fx.tcx.types.f32, types::F32),
-        sym::ceilf64 => ("ceil", 1, fx.tcx.types.f64, types::F64),
-        sym::truncf32 => ("truncf", 1, fx.tcx.types.f32, types::F32),
-        sym::truncf64 => ("trunc", 1, fx.tcx.types.f64, types::F64),
-        sym::rintf32 => ("rintf", 1, fx.tcx.types.f32, types::F32),
-        sym::rintf64 => ("rint", 1, fx.tcx.types.f64, types::F64),
-        sym::roundf32 => ("roundf", 1, fx.tcx.types.f32, types::F32),
-        sym::roundf64 => ("round", 1, fx.tcx.types.f64, types::F64),
-        sym::roundevenf32 => ("roundevenf", 1, fx.tcx.types.f32, types::F32),
-        sym::roundevenf64 => ("roundeven", 1, fx.tcx.types.f64, types::F64),
-        sym::sinf32 => ("sinf", 1, fx.tcx.types.f32, types::F32),
-        sym::sinf64 => ("sin", 1, fx.tcx.types.f64, types::F64),
-        sym::cosf32 => ("cosf", 1, fx.tcx.types.f32, types::F32),
-        sym::cosf64 => ("cos", 1, fx.tcx.types.f64, types::F64),
+    let (name, arg_count, ty) = match intrinsic {
+        sym::expf32 => ("expf", 1, fx.tcx.types.f32),
+        sym::expf64 => ("exp", 1, fx.tcx.types.f64),
+        sym::exp2f32 => ("exp2f", 1, fx.tcx.types.f32),
+        sym::exp2f64 => ("exp2", 1, fx.tcx.types.f64),
+        sym::sqrtf32 => ("sqrtf", 1, fx.tcx.types.f32),
+        sym::sqrtf64 => ("sqrt", 1, fx.tcx.types.f64),
+        sym::powif32 => ("__powisf2", 2, fx.tcx.types.f32), // compiler-builtins
+        sym::powif64 => ("__powidf2", 2, fx.tcx.types.f64), // compiler-builtins
+        sym::powf32 => ("powf", 2, fx.tcx.types.f32),
+        sym::powf64 => ("pow", 2, fx.tcx.types.f64),
+        sym::logf32 => ("logf", 1, fx.tcx.types.f32),
+        sym::logf64 => ("log", 1, fx.tcx.types.f64),
+        sym::log2f32 => ("log2f", 1, fx.tcx.types.f32),
+        sym::log2f64 => ("log2", 1, fx.tcx.types.f64),
+        sym::log10f32 => ("log10f", 1, fx.tcx.types.f32),
+        sym::log10f64 => ("log10", 1, fx.tcx.types.f64),
+        sym::fabsf32 => ("fabsf", 1, fx.tcx.types.f32),
+        sym::fabsf64 => ("fabs", 1, fx.tcx.types.f64),
+        sym::fmaf32 => ("fmaf", 3, fx.tcx.types.f32),
+        sym::fmaf64 => ("fma", 3, fx.tcx.types.f64),
+        sym::copysignf32 => ("copysignf", 2, fx.tcx.types.f32),
+        sym::copysignf64 => ("copysign", 2, fx.tcx.types.f64),
+        sym::floorf32 => ("floorf", 1, fx.tcx.types.f32),
+        sym::floorf64 => ("floor", 1, fx.tcx.types.f64),
+        sym::ceilf32 => ("ceilf", 1, fx.tcx.types.f32),
+        sym::ceilf64 => ("ceil", 1, fx.tcx.types.f64),
+        sym::truncf32 => ("truncf", 1, fx.tcx.types.f32),
+        sym::truncf64 => ("trunc", 1, fx.tcx.types.f64),
+        // Both are lowered to `nearest` below, so no `nearbyint` libcall is needed.
+        sym::rintf32 | sym::nearbyintf32 => ("rintf", 1, fx.tcx.types.f32),
+        sym::rintf64 | sym::nearbyintf64 => ("rint", 1, fx.tcx.types.f64),
+        sym::roundf32 => ("roundf", 1, fx.tcx.types.f32),
+        sym::roundf64 => ("round", 1, fx.tcx.types.f64),
+        sym::roundevenf32 => ("roundevenf", 1, fx.tcx.types.f32),
+        sym::roundevenf64 => ("roundeven", 1, fx.tcx.types.f64),
+        sym::sinf32 => ("sinf", 1, fx.tcx.types.f32),
+        sym::sinf64 => ("sin", 1, fx.tcx.types.f64),
+        sym::cosf32 => ("cosf", 1, fx.tcx.types.f32),
+        sym::cosf64 => ("cos", 1, fx.tcx.types.f64),
         _ => return false,
     };
 
@@ -350,34 +344,20 @@ fn codegen_float_intrinsic_call<'tcx>(
         bug!("wrong number of args for intrinsic {:?}", intrinsic);
     }
 
-    let (a, b, c);
-    let args = match args {
-        [x] => {
-            a = [codegen_operand(fx, x).load_scalar(fx)];
-            &a as &[_]
-        }
-        [x, y] => {
-            b = [codegen_operand(fx, x).load_scalar(fx), codegen_operand(fx, y).load_scalar(fx)];
-            &b
-        }
-        [x, y, z] => {
-            c = [
-                codegen_operand(fx, x).load_scalar(fx),
-                codegen_operand(fx, y).load_scalar(fx),
-                codegen_operand(fx, z).load_scalar(fx),
-            ];
-            &c
-        }
-        _ => unreachable!(),
-    };
+    let args = args.iter().map(|arg| codegen_operand(fx, arg)).collect::<Vec<_>>();
 
     let layout = fx.layout_of(ty);
     let res = match intrinsic {
         sym::fmaf32 | sym::fmaf64 => {
-            CValue::by_val(fx.bcx.ins().fma(args[0], args[1], args[2]), layout)
+            let a = args[0].load_scalar(fx);
+            let b = args[1].load_scalar(fx);
+            let c = args[2].load_scalar(fx);
+            CValue::by_val(fx.bcx.ins().fma(a, b, c), layout)
         }
         sym::copysignf32 | sym::copysignf64 => {
-            CValue::by_val(fx.bcx.ins().fcopysign(args[0], args[1]), layout)
+            let a = args[0].load_scalar(fx);
+            let b = args[1].load_scalar(fx);
+            CValue::by_val(fx.bcx.ins().fcopysign(a, b), layout)
         }
         sym::fabsf32
         | sym::fabsf64
@@ -386,12 +366,32 @@ fn codegen_float_intrinsic_call<'tcx>(
         | sym::ceilf32
         | sym::ceilf64
         | sym::truncf32
-        | sym::truncf64 => {
+        | sym::truncf64
+        | sym::rintf32
+        | sym::rintf64
+        | sym::nearbyintf32
+        | sym::nearbyintf64
+        | sym::roundevenf32
+        | sym::roundevenf64
+        | sym::sqrtf32
+        | sym::sqrtf64 => {
+            // Cranelift falls back to a libcall itself for the rounding instructions when the
+            // target doesn't support them. (e.g. x86_64 without SSE4.1)
+            let arg = args[0].load_scalar(fx);
             let val = match intrinsic {
-                sym::fabsf32 | sym::fabsf64 => fx.bcx.ins().fabs(args[0]),
-                sym::floorf32 | sym::floorf64 => fx.bcx.ins().floor(args[0]),
-                sym::ceilf32 | sym::ceilf64 => fx.bcx.ins().ceil(args[0]),
-                sym::truncf32 | sym::truncf64 => fx.bcx.ins().trunc(args[0]),
+                sym::fabsf32 | sym::fabsf64 => fx.bcx.ins().fabs(arg),
+                sym::floorf32 | sym::floorf64 => fx.bcx.ins().floor(arg),
+                sym::ceilf32 | sym::ceilf64 => fx.bcx.ins().ceil(arg),
+                sym::truncf32 | sym::truncf64 => fx.bcx.ins().trunc(arg),
+                // These round half way cases to even assuming the default rounding mode, which
+                // is the only one supported by Rust.
+                sym::rintf32
+                | sym::rintf64
+                | sym::nearbyintf32
+                | sym::nearbyintf64
+                | sym::roundevenf32
+                | sym::roundevenf64 => fx.bcx.ins().nearest(arg),
+                sym::sqrtf32 | sym::sqrtf64 => fx.bcx.ins().sqrt(arg),
                 _ => unreachable!(),
             };
 
@@ -400,16 +400,7 @@ fn codegen_float_intrinsic_call<'tcx>(
 
         // These intrinsics aren't supported natively by Cranelift.
         // Lower them to a libcall.
-        sym::powif32 | sym::powif64 => {
-            let input_tys: Vec<_> = vec![AbiParam::new(clif_ty), AbiParam::new(types::I32)];
-            let ret_val = fx.lib_call(name, input_tys, vec![AbiParam::new(clif_ty)], &args)[0];
-            CValue::by_val(ret_val, fx.layout_of(ty))
-        }
-        _ => {
-            let input_tys: Vec<_> = args.iter().map(|_| AbiParam::new(clif_ty)).collect();
-            let ret_val = fx.lib_call(name, input_tys, vec![AbiParam::new(clif_ty)], &args)[0];
-            CValue::by_val(ret_val, fx.layout_of(ty))
-        }
+        _ => fx.call_runtime(name, &args, layout),
     };
 
     ret.write_cvalue(fx, res);
@@ -417,6 +408,165 @@ fn codegen_float_intrinsic_call<'tcx>(
     true
 }
 
+/// Copies `count` elements of `elem_layout` from `src` to `dst`. If the amount of bytes is known
+/// at compile time, small copies are emitted inline instead of calling `memcpy` or `memmove`.
+pub(crate) fn codegen_copy<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    dst: Value,
+    src: Value,
+    count: &mir::Operand<'tcx>,
+    elem_layout: TyAndLayout<'tcx>,
+    non_overlapping: bool,
+) {
+    let elem_size = elem_layout.size.bytes();
+    let const_size = crate::constant::mir_operand_get_const_val(fx, count)
+        .and_then(|count| count.try_to_target_usize(fx.tcx).ok())
+        .and_then(|count| count.checked_mul(elem_size));
+    if let Some(size) = const_size {
+        let align = small_mem_op_align(elem_layout);
+        let mut flags = MemFlags::new();
+        flags.set_notrap();
+        fx.bcx.emit_small_memory_copy(
+            fx.target_config,
+            dst,
+            src,
+            size,
+            align,
+            align,
+            non_overlapping,
+            flags,
+        );
+        return;
+    }
+
+    let count = codegen_operand(fx, count).load_scalar(fx);
+    let byte_amount =
+        if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
+    if non_overlapping {
+        fx.bcx.call_memcpy(fx.target_config, dst, src, byte_amount);
+    } else {
+        fx.bcx.call_memmove(fx.target_config, dst, src, byte_amount);
+    }
+}
+
+/// Sets `count` elements of `elem_layout` at `dst` to the byte `val`. If both the byte and the
+/// amount of bytes are known at compile time, small memsets are emitted inline instead of calling
+/// `memset`.
+pub(crate) fn codegen_write_bytes<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    dst: Value,
+    val: &mir::Operand<'tcx>,
+    count: &mir::Operand<'tcx>,
+    elem_layout: TyAndLayout<'tcx>,
+) {
+    let elem_size = elem_layout.size.bytes();
+    let const_val =
+        crate::constant::mir_operand_get_const_val(fx, val).and_then(|val| val.try_to_u8().ok());
+    let const_size = crate::constant::mir_operand_get_const_val(fx, count)
+        .and_then(|count| count.try_to_target_usize(fx.tcx).ok())
+        .and_then(|count| count.checked_mul(elem_size));
+    if let (Some(val), Some(size)) = (const_val, const_size) {
+        let align = small_mem_op_align(elem_layout);
+        let mut flags = MemFlags::new();
+        flags.set_notrap();
+        fx.bcx.emit_small_memset(fx.target_config, dst, val, size, align, flags);
+        return;
+    }
+
+    let val = codegen_operand(fx, val).load_scalar(fx);
+    let count = codegen_operand(fx, count).load_scalar(fx);
+    let byte_amount =
+        if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
+    fx.bcx.call_memset(fx.target_config, dst, val, byte_amount);
+}
+
+/// The alignment to pass to `emit_small_memory_copy` and `emit_small_memset`. These only use the
+/// alignment to decide if the loads and stores they emit, which are at most 8 bytes large, are
+/// aligned.
+fn small_mem_op_align(layout: TyAndLayout<'_>) -> u8 {
+    layout.align.abi.bytes().min(8) as u8
+}
+
+/// Divides `val` by the non-zero constant `divisor`, assuming that the division is exact. Like an
+/// `exact` division in LLVM, this avoids an actual division: the power of two factor of the
+/// divisor is shifted out, after which dividing by the remaining odd factor is the same as
+/// multiplying by its multiplicative inverse modulo 2^n.
+fn codegen_exact_div_imm(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    val: Value,
+    divisor: u64,
+    signed: bool,
+) -> Value {
+    let shift = divisor.trailing_zeros();
+    let val = match (shift, signed) {
+        (0, _) => val,
+        (_, true) => fx.bcx.ins().sshr_imm(val, i64::from(shift)),
+        (_, false) => fx.bcx.ins().ushr_imm(val, i64::from(shift)),
+    };
+
+    let odd = divisor >> shift;
+    if odd == 1 {
+        return val;
+    }
+    // Newton's method doubles the number of correct low bits of the inverse every iteration. `odd`
+    // is its own inverse modulo 8, so five iterations give an inverse modulo 2^64.
+    let mut inverse = odd;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(odd.wrapping_mul(inverse)));
+    }
+    let bits = fx.bcx.func.dfg.value_type(val).bits();
+    if bits < 64 {
+        inverse &= (1 << bits) - 1;
+    }
+    fx.bcx.ins().imul_imm(val, inverse as i64)
+}
+
+/// Cranelift doesn't have volatile loads. Its alias analysis replaces a load with the result of
+/// an earlier load from or store to the same address unless a call, fence or atomic operation is
+/// in between. Atomic loads are never replaced, so naturally aligned scalars are loaded using an
+/// atomic load, which is a regular load on x86_64. All other loads are preceded by a fence.
+fn codegen_volatile_load<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    ptr: Value,
+    layout: TyAndLayout<'tcx>,
+    unaligned: bool,
+) -> CValue<'tcx> {
+    if let Abi::Scalar(scalar) = layout.abi {
+        let ty = scalar_to_clif_type(fx.tcx, scalar);
+        if !unaligned
+            && ty.bits() <= fx.pointer_type.bits()
+            && layout.align.abi.bytes() >= layout.size.bytes()
+        {
+            let int_ty = Type::int(ty.bits() as u16).unwrap();
+            let val = fx.bcx.ins().atomic_load(int_ty, MemFlags::trusted(), ptr);
+            let val =
+                if ty.is_float() { fx.bcx.ins().bitcast(ty, MemFlags::new(), val) } else { val };
+            return CValue::by_val(val, layout);
+        }
+    }
+
+    fx.bcx.ins().fence();
+    if unaligned {
+        let place = CPlace::new_stack_slot(fx, layout);
+        let dst = place.to_ptr().get_addr(fx);
+        let mut flags = MemFlags::new();
+        flags.set_notrap();
+        fx.bcx.emit_small_memory_copy(
+            fx.target_config,
+            dst,
+            ptr,
+            layout.size.bytes(),
+            small_mem_op_align(layout),
+            1,
+            true,
+            flags,
+        );
+        place.to_cvalue(fx)
+    } else {
+        CValue::by_ref(Pointer::new(ptr), layout)
+    }
+}
+
 fn codegen_regular_intrinsic_call<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     instance: Instance<'tcx>,
@@ -426,38 +576,44 @@ fn codegen_regular_intrinsic_call<'tcx>(
     ret: CPlace<'tcx>,
     destination: Option<BasicBlock>,
     source_info: mir::SourceInfo,
-) {
+) -> CodegenResult {
     let usize_layout = fx.layout_of(fx.tcx.types.usize);
 
     match intrinsic {
         sym::abort => {
             fx.bcx.ins().trap(TrapCode::User(0));
-            return;
+            return Ok(());
         }
+        // Cranelift doesn't support branch weights, so these are only pass-throughs.
         sym::likely | sym::unlikely => {
             intrinsic_args!(fx, args => (a); intrinsic);
 
             ret.write_cvalue(fx, a);
         }
+        // Prefetches are only a hint. Emitting them would require inline asm, so they are lowered
+        // to a no-op.
+        sym::prefetch_read_data
+        | sym::prefetch_write_data
+        | sym::prefetch_read_instruction
+        | sym::prefetch_write_instruction => {
+            intrinsic_args!(fx, args => (_ptr, _locality); intrinsic);
+        }
         sym::breakpoint => {
             intrinsic_args!(fx, args => (); intrinsic);
 
             fx.bcx.ins().debugtrap();
         }
         sym::copy => {
-            intrinsic_args!(fx, args => (src, dst, count); intrinsic);
-            let src = src.load_scalar(fx);
-            let dst = dst.load_scalar(fx);
-            let count = count.load_scalar(fx);
-
-            let elem_ty = generic_args.type_at(0);
-            let elem_size: u64 = fx.layout_of(elem_ty).size.bytes();
-            assert_eq!(args.len(), 3);
-            let byte_amount =
-                if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
+            // The count is passed to `codegen_copy` as operand to check if it is a constant.
+            let (src, dst, count) = match args {
+                [src, dst, count] => (src, dst, count),
+                _ => bug_on_incorrect_arg_count(intrinsic),
+            };
+            let src = codegen_operand(fx, src).load_scalar(fx);
+            let dst = codegen_operand(fx, dst).load_scalar(fx);
 
-            // FIXME emit_small_memmove
-            fx.bcx.call_memmove(fx.target_config, dst, src, byte_amount);
+            let elem_layout = fx.layout_of(generic_args.type_at(0));
+            codegen_copy(fx, dst, src, count, elem_layout, false);
         }
         sym::volatile_copy_memory | sym::volatile_copy_nonoverlapping_memory => {
             // NOTE: the volatile variants have src and dst swapped
@@ -472,12 +628,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let byte_amount =
                 if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
 
-            // FIXME make the copy actually volatile when using emit_small_mem{cpy,move}
+            // The inline loads and stores of emit_small_memory_copy can't be marked as volatile, so
+            // always call memcpy or memmove.
             if intrinsic == sym::volatile_copy_nonoverlapping_memory {
-                // FIXME emit_small_memcpy
                 fx.bcx.call_memcpy(fx.target_config, dst, src, byte_amount);
             } else {
-                // FIXME emit_small_memmove
                 fx.bcx.call_memmove(fx.target_config, dst, src, byte_amount);
             }
         }
@@ -529,10 +684,24 @@ fn codegen_regular_intrinsic_call<'tcx>(
         }
 
         sym::exact_div => {
+            let const_y = crate::constant::mir_operand_get_const_val(fx, &args[1]);
             intrinsic_args!(fx, args => (x, y); intrinsic);
 
-            // FIXME trap on inexact
-            let res = crate::num::codegen_int_binop(fx, BinOp::Div, x, y);
+            // An inexact division is UB, so a constant positive divisor doesn't need an actual
+            // division.
+            let layout = x.layout();
+            let signed = type_sign(layout.ty);
+            let const_y = const_y
+                .filter(|_| layout.size.bits() <= 64)
+                .and_then(|y| y.to_bits(layout.size).ok())
+                .filter(|&y| if signed { layout.size.sign_extend(y) as i128 > 0 } else { y != 0 });
+            let res = if let Some(const_y) = const_y {
+                let x = x.load_scalar(fx);
+                let res = codegen_exact_div_imm(fx, x, const_y as u64, signed);
+                CValue::by_val(res, layout)
+            } else {
+                crate::num::codegen_int_binop(fx, BinOp::Div, x, y)
+            };
             ret.write_cvalue(fx, res);
         }
         sym::saturating_add | sym::saturating_sub => {
@@ -592,7 +761,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
             fx.bcx.ins().band(ptr, mask);
         }
 
-        sym::write_bytes | sym::volatile_set_memory => {
+        sym::write_bytes => {
+            // The byte and count are passed to `codegen_write_bytes` as operands to check if they
+            // are constants.
+            let (dst, val, count) = match args {
+                [dst, val, count] => (dst, val, count),
+                _ => bug_on_incorrect_arg_count(intrinsic),
+            };
+            let dst = codegen_operand(fx, dst);
+
+            let pointee_ty = dst.layout().ty.builtin_deref(true).unwrap().ty;
+            let elem_layout = fx.layout_of(pointee_ty);
+            let dst = dst.load_scalar(fx);
+            codegen_write_bytes(fx, dst, val, count, elem_layout);
+        }
+        sym::volatile_set_memory => {
             intrinsic_args!(fx, args => (dst, val, count); intrinsic);
             let val = val.load_scalar(fx);
             let count = count.load_scalar(fx);
@@ -605,8 +788,8 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 count
             };
             let dst_ptr = dst.load_scalar(fx);
-            // FIXME make the memset actually volatile when switching to emit_small_memset
-            // FIXME use emit_small_memset
+            // The inline stores of emit_small_memset can't be marked as volatile, so always call
+            // memset.
             fx.bcx.call_memset(fx.target_config, dst_ptr, val, count);
         }
         sym::ctlz | sym::ctlz_nonzero => {
@@ -689,7 +872,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
                         })
                     });
                     crate::base::codegen_panic_nounwind(fx, &msg_str, source_info);
-                    return;
+                    return Ok(());
                 }
             }
         }
@@ -697,18 +880,43 @@ fn codegen_regular_intrinsic_call<'tcx>(
         sym::volatile_load | sym::unaligned_volatile_load => {
             intrinsic_args!(fx, args => (ptr); intrinsic);
 
-            // Cranelift treats loads as volatile by default
-            // FIXME correctly handle unaligned_volatile_load
             let inner_layout = fx.layout_of(ptr.layout().ty.builtin_deref(true).unwrap().ty);
-            let val = CValue::by_ref(Pointer::new(ptr.load_scalar(fx)), inner_layout);
+            let ptr = ptr.load_scalar(fx);
+            let val = codegen_volatile_load(
+                fx,
+                ptr,
+                inner_layout,
+                intrinsic == sym::unaligned_volatile_load,
+            );
             ret.write_cvalue(fx, val);
         }
-        sym::volatile_store | sym::unaligned_volatile_store | sym::nontemporal_store => {
+        sym::unaligned_volatile_store => {
+            intrinsic_args!(fx, args => (ptr, val); intrinsic);
+            let ptr = ptr.load_scalar(fx);
+
+            // Cranelift never removes stores, so only the alignment needs to be taken care of.
+            let size = val.layout().size.bytes();
+            let src_align = small_mem_op_align(val.layout());
+            let (src, _) = val.force_stack(fx);
+            let src = src.get_addr(fx);
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+            fx.bcx.emit_small_memory_copy(
+                fx.target_config,
+                ptr,
+                src,
+                size,
+                1,
+                src_align,
+                true,
+                flags,
+            );
+        }
+        sym::volatile_store | sym::nontemporal_store => {
             intrinsic_args!(fx, args => (ptr, val); intrinsic);
             let ptr = ptr.load_scalar(fx);
 
-            // Cranelift treats stores as volatile by default
-            // FIXME correctly handle unaligned_volatile_store
+            // Cranelift never removes stores, so volatile stores can be regular stores.
             // FIXME actually do nontemporal stores if requested
             let dest = CPlace::for_ptr(Pointer::new(ptr), val.layout());
             dest.write_cvalue(fx, val);
@@ -734,18 +942,16 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let ty = generic_args.type_at(0);
 
             let pointee_size: u64 = fx.layout_of(ty).size.bytes();
+            if pointee_size == 0 {
+                // `offset_from` panics for zero sized types before calling the intrinsic.
+                fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
+                return Ok(());
+            }
             let diff_bytes = fx.bcx.ins().isub(ptr, base);
-            // FIXME this can be an exact division.
-            let val = if intrinsic == sym::ptr_offset_from_unsigned {
-                let usize_layout = fx.layout_of(fx.tcx.types.usize);
-                // Because diff_bytes ULE isize::MAX, this would be fine as signed,
-                // but unsigned is slightly easier to codegen, so might as well.
-                CValue::by_val(fx.bcx.ins().udiv_imm(diff_bytes, pointee_size as i64), usize_layout)
-            } else {
-                let isize_layout = fx.layout_of(fx.tcx.types.isize);
-                CValue::by_val(fx.bcx.ins().sdiv_imm(diff_bytes, pointee_size as i64), isize_layout)
-            };
-            ret.write_cvalue(fx, val);
+            let signed = intrinsic == sym::ptr_offset_from;
+            let val = codegen_exact_div_imm(fx, diff_bytes, pointee_size, signed);
+            let layout = fx.layout_of(if signed { fx.tcx.types.isize } else { fx.tcx.types.usize });
+            ret.write_cvalue(fx, CValue::by_val(val, layout));
         }
 
         sym::ptr_guaranteed_cmp => {
@@ -762,6 +968,9 @@ fn codegen_regular_intrinsic_call<'tcx>(
             ret.write_cvalue(fx, caller_location);
         }
 
+        // Cranelift atomic instructions are always sequentially consistent, which is a valid
+        // implementation of every weaker memory ordering. The ordering suffix of the atomic
+        // intrinsics is thus ignored.
         _ if intrinsic.as_str().starts_with("atomic_fence") => {
             intrinsic_args!(fx, args => (); intrinsic);
 
@@ -770,7 +979,9 @@ fn codegen_regular_intrinsic_call<'tcx>(
         _ if intrinsic.as_str().starts_with("atomic_singlethreadfence") => {
             intrinsic_args!(fx, args => (); intrinsic);
 
-            // FIXME use a compiler fence once Cranelift supports it
+            // Cranelift doesn't have a compiler-only fence. It can't be a no-op either as the
+            // alias analysis of Cranelift would then be allowed to move memory accesses across it,
+            // so emit a full fence instead.
             fx.bcx.ins().fence();
         }
         _ if intrinsic.as_str().starts_with("atomic_load") => {
@@ -779,27 +990,22 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let ty = generic_args.type_at(0);
             match ty.kind() {
-                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128) => {
-                    // FIXME implement 128bit atomics
-                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) {
-                        // special case for compiler-builtins to avoid having to patch it
-                        crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
-                        return;
-                    } else {
-                        fx.tcx
-                            .sess
-                            .span_fatal(source_info.span, "128bit atomics not yet supported");
-                    }
+                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128)
+                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) =>
+                {
+                    // special case for compiler-builtins to avoid having to patch it
+                    crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
+                    return Ok(());
                 }
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, ty);
-                    return;
+                    return Ok(());
                 }
             }
             let clif_ty = fx.clif_type(ty).unwrap();
 
-            let val = fx.bcx.ins().atomic_load(clif_ty, MemFlags::trusted(), ptr);
+            let val = self::atomic::codegen_atomic_load(fx, clif_ty, ptr, source_info.span);
 
             let val = CValue::by_val(val, fx.layout_of(ty));
             ret.write_cvalue(fx, val);
@@ -810,28 +1016,23 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let ty = generic_args.type_at(0);
             match ty.kind() {
-                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128) => {
-                    // FIXME implement 128bit atomics
-                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) {
-                        // special case for compiler-builtins to avoid having to patch it
-                        crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
-                        return;
-                    } else {
-                        fx.tcx
-                            .sess
-                            .span_fatal(source_info.span, "128bit atomics not yet supported");
-                    }
+                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128)
+                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) =>
+                {
+                    // special case for compiler-builtins to avoid having to patch it
+                    crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
+                    return Ok(());
                 }
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, ty);
-                    return;
+                    return Ok(());
                 }
             }
 
             let val = val.load_scalar(fx);
 
-            fx.bcx.ins().atomic_store(MemFlags::trusted(), val, ptr);
+            self::atomic::codegen_atomic_store(fx, ptr, val, source_info.span);
         }
         _ if intrinsic.as_str().starts_with("atomic_xchg") => {
             intrinsic_args!(fx, args => (ptr, new); intrinsic);
@@ -842,14 +1043,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let new = new.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Xchg, ptr, new);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Xchg,
+                ptr,
+                new,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -864,14 +1072,14 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
 
             let test_old = test_old.load_scalar(fx);
             let new = new.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_cas(MemFlags::trusted(), ptr, test_old, new);
+            let old = self::atomic::codegen_atomic_cas(fx, ptr, test_old, new, source_info.span);
             let is_eq = fx.bcx.ins().icmp(IntCC::Equal, old, test_old);
 
             let ret_val = CValue::by_val_pair(old, is_eq, ret.layout());
@@ -887,15 +1095,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let amount = amount.load_scalar(fx);
 
-            let old =
-                fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Add, ptr, amount);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Add,
+                ptr,
+                amount,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -909,15 +1123,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let amount = amount.load_scalar(fx);
 
-            let old =
-                fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Sub, ptr, amount);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Sub,
+                ptr,
+                amount,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -931,14 +1151,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::And, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::And,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -952,14 +1179,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Or, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Or,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -973,14 +1207,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Xor, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Xor,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -994,14 +1235,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Nand, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Nand,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1015,14 +1263,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Smax, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Smax,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1036,14 +1291,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Umax, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Umax,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1057,14 +1319,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Smin, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Smin,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1078,14 +1347,21 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, layout.ty);
-                    return;
+                    return Ok(());
                 }
             }
             let ty = fx.clif_type(layout.ty).unwrap();
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Umin, ptr, src);
+            let old = self::atomic::codegen_atomic_rmw(
+                fx,
+                ty,
+                AtomicRmwOp::Umin,
+                ptr,
+                src,
+                source_info.span,
+            );
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1134,6 +1410,9 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let data = data.load_scalar(fx);
             let _catch_fn = catch_fn.load_scalar(fx);
 
+            // Everything is compiled with panic=abort, as Cranelift doesn't support landing pads
+            // yet. A panic inside `f` thus aborts before it could be caught, so calling `f` and
+            // reporting that no panic happened is correct.
             // FIXME once unwinding is supported, change this to actually catch panics
             let f_sig = fx.bcx.func.import_signature(Signature {
                 call_conv: fx.target_config.default_call_conv,
@@ -1148,6 +1427,8 @@ fn codegen_regular_intrinsic_call<'tcx>(
             ret.write_cvalue(fx, ret_val);
         }
 
+        // Cranelift doesn't have fast-math flags, so these are codegened like regular float
+        // operations, which is a valid refinement of the fast-math semantics.
         sym::fadd_fast | sym::fsub_fast | sym::fmul_fast | sym::fdiv_fast | sym::frem_fast => {
             intrinsic_args!(fx, args => (x, y); intrinsic);
 
@@ -1185,44 +1466,33 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let lhs_ref = lhs_ref.load_scalar(fx);
             let rhs_ref = rhs_ref.load_scalar(fx);
 
-            let size = fx.layout_of(generic_args.type_at(0)).layout.size();
-            // FIXME add and use emit_small_memcmp
-            let is_eq_value = if size == Size::ZERO {
-                // No bytes means they're trivially equal
-                fx.bcx.ins().iconst(types::I8, 1)
-            } else if let Some(clty) = size.bits().try_into().ok().and_then(Type::int) {
-                // Can't use `trusted` for these loads; they could be unaligned.
-                let mut flags = MemFlags::new();
-                flags.set_notrap();
-                let lhs_val = fx.bcx.ins().load(clty, flags, lhs_ref, 0);
-                let rhs_val = fx.bcx.ins().load(clty, flags, rhs_ref, 0);
-                fx.bcx.ins().icmp(IntCC::Equal, lhs_val, rhs_val)
-            } else {
-                // Just call `memcmp` (like slices do in core) when the
-                // size is too large or it's not a power-of-two.
-                let signed_bytes = i64::try_from(size.bytes()).unwrap();
-                let bytes_val = fx.bcx.ins().iconst(fx.pointer_type, signed_bytes);
-                let params = vec![AbiParam::new(fx.pointer_type); 3];
-                let returns = vec![AbiParam::new(types::I32)];
-                let args = &[lhs_ref, rhs_ref, bytes_val];
-                let cmp = fx.lib_call("memcmp", params, returns, args)[0];
-                fx.bcx.ins().icmp_imm(IntCC::Equal, cmp, 0)
-            };
+            let layout = fx.layout_of(generic_args.type_at(0));
+            // Small power of two sizes are compared with a single load from each side, everything
+            // else calls `memcmp` (like slices do in core).
+            let align = std::num::NonZeroU8::new(layout.align.abi.bytes().min(128) as u8).unwrap();
+            // The loads are marked as aligned by `emit_small_memory_compare` when the alignment of
+            // the type allows it.
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+            let is_eq_value = fx.bcx.emit_small_memory_compare(
+                fx.target_config,
+                IntCC::Equal,
+                lhs_ref,
+                rhs_ref,
+                layout.size.bytes(),
+                align,
+                align,
+                flags,
+            );
             ret.write_cvalue(fx, CValue::by_val(is_eq_value, ret.layout()));
         }
 
         sym::compare_bytes => {
             intrinsic_args!(fx, args => (lhs_ptr, rhs_ptr, bytes_val); intrinsic);
-            let lhs_ptr = lhs_ptr.load_scalar(fx);
-            let rhs_ptr = rhs_ptr.load_scalar(fx);
-            let bytes_val = bytes_val.load_scalar(fx);
 
-            let params = vec![AbiParam::new(fx.pointer_type); 3];
-            let returns = vec![AbiParam::new(types::I32)];
-            let args = &[lhs_ptr, rhs_ptr, bytes_val];
             // Here we assume that the `memcmp` provided by the target is a NOP for size 0.
-            let cmp = fx.lib_call("memcmp", params, returns, args)[0];
-            ret.write_cvalue(fx, CValue::by_val(cmp, ret.layout()));
+            let cmp = fx.call_runtime("memcmp", &[lhs_ptr, rhs_ptr, bytes_val], ret.layout());
+            ret.write_cvalue(fx, cmp);
         }
 
         sym::const_allocate => {
@@ -1241,25 +1511,48 @@ fn codegen_regular_intrinsic_call<'tcx>(
         sym::black_box => {
             intrinsic_args!(fx, args => (a); intrinsic);
 
-            // FIXME implement black_box semantics
-            ret.write_cvalue(fx, a);
+            if a.layout().is_zst() {
+                ret.write_cvalue(fx, a);
+            } else {
+                // Round-trip the value through a stack slot. Cranelift doesn't forward stores to
+                // loads across an atomic operation, so the value can't be constant folded
+                // afterwards. On x86_64 an atomic load is a regular load.
+                let place = CPlace::new_stack_slot(fx, a.layout());
+                place.write_cvalue(fx, a);
+                let addr = place.to_ptr().get_addr(fx);
+                fx.bcx.ins().atomic_load(types::I8, MemFlags::trusted(), addr);
+                let val = place.to_cvalue(fx);
+                ret.write_cvalue(fx, val);
+            }
         }
 
-        // FIXME implement variadics in cranelift
-        sym::va_copy | sym::va_arg | sym::va_end => {
-            fx.tcx.sess.span_fatal(
-                source_info.span,
-                "Defining variadic functions is not yet supported by Cranelift",
-            );
+        // See abi/varargs.rs for the layout of `va_list` and the targets supporting it.
+        sym::va_copy => {
+            intrinsic_args!(fx, args => (dest, src); intrinsic);
+            let va_list_layout = fx.layout_of(src.layout().ty.builtin_deref(true).unwrap().ty);
+            let dest = dest.load_scalar(fx);
+            let src = src.load_scalar(fx);
+            CPlace::for_ptr(Pointer::new(dest), va_list_layout)
+                .write_cvalue(fx, CValue::by_ref(Pointer::new(src), va_list_layout));
+        }
+        sym::va_arg => {
+            intrinsic_args!(fx, args => (va_list); intrinsic);
+            let va_list = va_list.load_scalar(fx);
+            crate::abi::codegen_va_arg(fx, source_info.span, va_list, ret)?;
+        }
+        sym::va_end => {
+            // Nothing to do as `va_list` doesn't own any resources.
         }
 
         _ => {
-            fx.tcx
-                .sess
-                .span_fatal(source_info.span, format!("unsupported intrinsic {}", intrinsic));
+            return Err(
+                fx.unsupported(source_info.span, format!("unsupported intrinsic {}", intrinsic))
+            );
         }
     }
 
     let ret_block = fx.get_block(destination.unwrap());
     fx.bcx.ins().jump(ret_block, &[]);
+
+    Ok(())
 }
@@ -26,14 +26,14 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
     ret: CPlace<'tcx>,
     target: BasicBlock,
     span: Span,
-) {
+) -> CodegenResult {
     match intrinsic {
         sym::simd_as | sym::simd_cast => {
             intrinsic_args!(fx, args => (a); intrinsic);
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, ret_lane_ty, lane| {
@@ -51,7 +51,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !x.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, x.layout().ty);
-                return;
+                return Ok(());
             }
 
             // FIXME use vector instructions when possible
@@ -127,7 +127,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !x.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, x.layout().ty);
-                return;
+                return Ok(());
             }
 
             let idx = generic_args[2]
@@ -178,7 +178,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !x.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, x.layout().ty);
-                return;
+                return Ok(());
             }
 
             // Make sure this is actually an array, since typeck only checks the length-suffixed
@@ -199,7 +199,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                     );
                     // Prevent verifier error
                     fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
-                    return;
+                    return Ok(());
                 }
             };
 
@@ -273,28 +273,31 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             let base = codegen_operand(fx, base);
             let val = codegen_operand(fx, val);
 
-            // FIXME validate
-            let idx_const = if let Some(idx_const) =
-                crate::constant::mir_operand_get_const_val(fx, idx)
-            {
-                idx_const
-            } else {
-                fx.tcx.sess.span_fatal(span, "Index argument for `simd_insert` is not a constant");
-            };
-
-            let idx: u32 = idx_const
-                .try_to_u32()
-                .unwrap_or_else(|_| panic!("kind not scalar: {:?}", idx_const));
-            let (lane_count, _lane_ty) = base.layout().ty.simd_size_and_type(fx.tcx);
-            if u64::from(idx) >= lane_count {
-                fx.tcx.sess.span_fatal(
-                    fx.mir.span,
-                    format!("[simd_insert] idx {} >= lane_count {}", idx, lane_count),
-                );
+            if !base.layout().ty.is_simd() {
+                report_simd_type_validation_error(fx, intrinsic, span, base.layout().ty);
+                return Ok(());
             }
 
             ret.write_cvalue(fx, base);
-            let ret_lane = ret.place_lane(fx, idx.try_into().unwrap());
+            let ret_lane =
+                if let Some(idx_const) = crate::constant::mir_operand_get_const_val(fx, idx) {
+                    let idx: u32 = idx_const
+                        .try_to_u32()
+                        .unwrap_or_else(|_| panic!("kind not scalar: {:?}", idx_const));
+                    let (lane_count, _lane_ty) = base.layout().ty.simd_size_and_type(fx.tcx);
+                    if u64::from(idx) >= lane_count {
+                        fx.tcx.sess.span_fatal(
+                            fx.mir.span,
+                            format!("[simd_insert] idx {} >= lane_count {}", idx, lane_count),
+                        );
+                    }
+                    ret.place_lane(fx, idx.into())
+                } else {
+                    // Out of bounds indices are UB, so no bounds check is necessary.
+                    let idx = codegen_operand(fx, idx).load_scalar(fx);
+                    let idx = clif_intcast(fx, idx, fx.pointer_type, false);
+                    ret.place_lane_dyn(fx, idx)
+                };
             ret_lane.write_cvalue(fx, val);
         }
 
@@ -309,39 +312,28 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
-            }
-
-            let idx_const = if let Some(idx_const) =
-                crate::constant::mir_operand_get_const_val(fx, idx)
-            {
-                idx_const
-            } else {
-                fx.tcx.sess.span_warn(span, "Index argument for `simd_extract` is not a constant");
-                let trap_block = fx.bcx.create_block();
-                let true_ = fx.bcx.ins().iconst(types::I8, 1);
-                let ret_block = fx.get_block(target);
-                fx.bcx.ins().brif(true_, trap_block, &[], ret_block, &[]);
-                fx.bcx.switch_to_block(trap_block);
-                crate::trap::trap_unimplemented(
-                    fx,
-                    "Index argument for `simd_extract` is not a constant",
-                );
-                return;
-            };
-
-            let idx = idx_const
-                .try_to_u32()
-                .unwrap_or_else(|_| panic!("kind not scalar: {:?}", idx_const));
-            let (lane_count, _lane_ty) = v.layout().ty.simd_size_and_type(fx.tcx);
-            if u64::from(idx) >= lane_count {
-                fx.tcx.sess.span_fatal(
-                    fx.mir.span,
-                    format!("[simd_extract] idx {} >= lane_count {}", idx, lane_count),
-                );
-            }
-
-            let ret_lane = v.value_lane(fx, idx.try_into().unwrap());
+                return Ok(());
+            }
+
+            let ret_lane =
+                if let Some(idx_const) = crate::constant::mir_operand_get_const_val(fx, idx) {
+                    let idx = idx_const
+                        .try_to_u32()
+                        .unwrap_or_else(|_| panic!("kind not scalar: {:?}", idx_const));
+                    let (lane_count, _lane_ty) = v.layout().ty.simd_size_and_type(fx.tcx);
+                    if u64::from(idx) >= lane_count {
+                        fx.tcx.sess.span_fatal(
+                            fx.mir.span,
+                            format!("[simd_extract] idx {} >= lane_count {}", idx, lane_count),
+                        );
+                    }
+                    v.value_lane(fx, idx.into())
+                } else {
+                    // Out of bounds indices are UB, so no bounds check is necessary.
+                    let idx = codegen_operand(fx, idx).load_scalar(fx);
+                    let idx = clif_intcast(fx, idx, fx.pointer_type, false);
+                    v.value_lane_dyn(fx, idx)
+                };
             ret.write_cvalue(fx, ret_lane);
         }
 
@@ -354,7 +346,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, _ret_lane_ty, lane| match (
@@ -405,18 +397,15 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                     (ty::Float(_), sym::simd_sub) => fx.bcx.ins().fsub(x_lane, y_lane),
                     (ty::Float(_), sym::simd_mul) => fx.bcx.ins().fmul(x_lane, y_lane),
                     (ty::Float(_), sym::simd_div) => fx.bcx.ins().fdiv(x_lane, y_lane),
-                    (ty::Float(FloatTy::F32), sym::simd_rem) => fx.lib_call(
-                        "fmodf",
-                        vec![AbiParam::new(types::F32), AbiParam::new(types::F32)],
-                        vec![AbiParam::new(types::F32)],
-                        &[x_lane, y_lane],
-                    )[0],
-                    (ty::Float(FloatTy::F64), sym::simd_rem) => fx.lib_call(
-                        "fmod",
-                        vec![AbiParam::new(types::F64), AbiParam::new(types::F64)],
-                        vec![AbiParam::new(types::F64)],
-                        &[x_lane, y_lane],
-                    )[0],
+                    (ty::Float(float_ty), sym::simd_rem) => {
+                        let name = match float_ty {
+                            FloatTy::F32 => "fmodf",
+                            FloatTy::F64 => "fmod",
+                        };
+                        let layout = fx.layout_of(lane_ty);
+                        let args = [CValue::by_val(x_lane, layout), CValue::by_val(y_lane, layout)];
+                        fx.easy_call(name, &args, lane_ty).load_scalar(fx)
+                    }
 
                     (ty::Uint(_), sym::simd_shl) => fx.bcx.ins().ishl(x_lane, y_lane),
                     (ty::Uint(_), sym::simd_shr) => fx.bcx.ins().ushr(x_lane, y_lane),
@@ -440,7 +429,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
             assert_eq!(a.layout(), b.layout());
             assert_eq!(a.layout(), c.layout());
@@ -467,7 +456,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !x.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, x.layout().ty);
-                return;
+                return Ok(());
             }
 
             // FIXME use vector instructions when possible
@@ -489,57 +478,38 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_pair_for_each_lane(fx, a, b, ret, &|fx, lane_ty, _ret_lane_ty, a_lane, b_lane| {
-                match lane_ty.kind() {
-                    ty::Float(FloatTy::F32) => fx.lib_call(
-                        "powf",
-                        vec![AbiParam::new(types::F32), AbiParam::new(types::F32)],
-                        vec![AbiParam::new(types::F32)],
-                        &[a_lane, b_lane],
-                    )[0],
-                    ty::Float(FloatTy::F64) => fx.lib_call(
-                        "pow",
-                        vec![AbiParam::new(types::F64), AbiParam::new(types::F64)],
-                        vec![AbiParam::new(types::F64)],
-                        &[a_lane, b_lane],
-                    )[0],
+                let name = match lane_ty.kind() {
+                    ty::Float(FloatTy::F32) => "powf",
+                    ty::Float(FloatTy::F64) => "pow",
                     _ => unreachable!("{:?}", lane_ty),
-                }
+                };
+                let layout = fx.layout_of(lane_ty);
+                let args = [CValue::by_val(a_lane, layout), CValue::by_val(b_lane, layout)];
+                fx.easy_call(name, &args, lane_ty).load_scalar(fx)
             });
         }
 
         sym::simd_fpowi => {
             intrinsic_args!(fx, args => (a, exp); intrinsic);
-            let exp = exp.load_scalar(fx);
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
-            simd_for_each_lane(
-                fx,
-                a,
-                ret,
-                &|fx, lane_ty, _ret_lane_ty, lane| match lane_ty.kind() {
-                    ty::Float(FloatTy::F32) => fx.lib_call(
-                        "__powisf2", // compiler-builtins
-                        vec![AbiParam::new(types::F32), AbiParam::new(types::I32)],
-                        vec![AbiParam::new(types::F32)],
-                        &[lane, exp],
-                    )[0],
-                    ty::Float(FloatTy::F64) => fx.lib_call(
-                        "__powidf2", // compiler-builtins
-                        vec![AbiParam::new(types::F64), AbiParam::new(types::I32)],
-                        vec![AbiParam::new(types::F64)],
-                        &[lane, exp],
-                    )[0],
+            simd_for_each_lane(fx, a, ret, &|fx, lane_ty, _ret_lane_ty, lane| {
+                let name = match lane_ty.kind() {
+                    ty::Float(FloatTy::F32) => "__powisf2", // compiler-builtins
+                    ty::Float(FloatTy::F64) => "__powidf2", // compiler-builtins
                     _ => unreachable!("{:?}", lane_ty),
-                },
-            );
+                };
+                let args = [CValue::by_val(lane, fx.layout_of(lane_ty)), exp];
+                fx.easy_call(name, &args, lane_ty).load_scalar(fx)
+            });
         }
 
         sym::simd_fsin
@@ -554,16 +524,16 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, _ret_lane_ty, lane| {
-                let lane_ty = match lane_ty.kind() {
+                let clif_ty = match lane_ty.kind() {
                     ty::Float(FloatTy::F32) => types::F32,
                     ty::Float(FloatTy::F64) => types::F64,
                     _ => unreachable!("{:?}", lane_ty),
                 };
-                let name = match (intrinsic, lane_ty) {
+                let name = match (intrinsic, clif_ty) {
                     (sym::simd_fsin, types::F32) => "sinf",
                     (sym::simd_fsin, types::F64) => "sin",
                     (sym::simd_fcos, types::F32) => "cosf",
@@ -582,12 +552,8 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                     (sym::simd_round, types::F64) => "round",
                     _ => unreachable!("{:?}", intrinsic),
                 };
-                fx.lib_call(
-                    name,
-                    vec![AbiParam::new(lane_ty)],
-                    vec![AbiParam::new(lane_ty)],
-                    &[lane],
-                )[0]
+                let arg = CValue::by_val(lane, fx.layout_of(lane_ty));
+                fx.easy_call(name, &[arg], lane_ty).load_scalar(fx)
             });
         }
 
@@ -596,7 +562,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, _ret_lane_ty, lane| {
@@ -622,7 +588,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             // FIXME there must be no acc param for integer vectors
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, Some(acc), ret, &|fx, lane_ty, a, b| {
@@ -640,7 +606,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             // FIXME there must be no acc param for integer vectors
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, lane_ty, a, b| {
@@ -659,7 +625,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             // FIXME there must be no acc param for integer vectors
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, Some(acc), ret, &|fx, lane_ty, a, b| {
@@ -677,7 +643,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             // FIXME there must be no acc param for integer vectors
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, lane_ty, a, b| {
@@ -694,7 +660,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce_bool(fx, v, ret, &|fx, a, b| fx.bcx.ins().band(a, b));
@@ -705,7 +671,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce_bool(fx, v, ret, &|fx, a, b| fx.bcx.ins().bor(a, b));
@@ -716,7 +682,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| fx.bcx.ins().band(a, b));
@@ -727,7 +693,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| fx.bcx.ins().bor(a, b));
@@ -738,7 +704,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, _ty, a, b| fx.bcx.ins().bxor(a, b));
@@ -749,7 +715,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, ty, a, b| {
@@ -768,7 +734,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !v.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, v.layout().ty);
-                return;
+                return Ok(());
             }
 
             simd_reduce(fx, v, None, ret, &|fx, ty, a, b| {
@@ -787,11 +753,11 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !m.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, m.layout().ty);
-                return;
+                return Ok(());
             }
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
             assert_eq!(a.layout(), b.layout());
 
@@ -816,7 +782,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
 
             if !a.layout().ty.is_simd() {
                 report_simd_type_validation_error(fx, intrinsic, span, a.layout().ty);
-                return;
+                return Ok(());
             }
             assert_eq!(a.layout(), b.layout());
 
@@ -1041,9 +1007,11 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             fx.tcx.sess.span_err(span, format!("Unknown SIMD intrinsic {}", intrinsic));
             // Prevent verifier error
             fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
-            return;
+            return Ok(());
         }
     }
     let ret_block = fx.get_block(target);
     fx.bcx.ins().jump(ret_block, &[]);
+
+    Ok(())
 }
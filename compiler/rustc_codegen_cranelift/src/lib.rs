@@ -40,7 +40,7 @@
 use rustc_errors::ErrorGuaranteed;
 use rustc_metadata::EncodedMetadata;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
-use rustc_session::config::OutputFilenames;
+use rustc_session::config::{OutputFilenames, OutputType};
 use rustc_session::Session;
 use rustc_span::Symbol;
 
@@ -74,6 +74,7 @@
 mod toolchain;
 mod trap;
 mod unsize;
+mod unsupported;
 mod value_and_place;
 mod vtable;
 
@@ -108,6 +109,7 @@ mod prelude {
     pub(crate) use crate::common::*;
     pub(crate) use crate::debuginfo::{DebugContext, UnwindContext};
     pub(crate) use crate::pointer::Pointer;
+    pub(crate) use crate::unsupported::CodegenResult;
     pub(crate) use crate::value_and_place::{CPlace, CValue};
 }
 
@@ -126,8 +128,17 @@ struct CodegenCx {
     profiler: SelfProfilerRef,
     output_filenames: Arc<OutputFilenames>,
     should_write_ir: bool,
+    /// The disassembly of all functions in the codegen unit when `--emit asm` is passed.
+    assembly: Option<String>,
     global_asm: String,
     inline_asm_index: Cell<usize>,
+    /// Inline asm can't be used in jit mode, so internally generated inline asm must only be
+    /// emitted when this is false.
+    is_jit: bool,
+    /// Report unsupported constructs as warnings rather than errors.
+    allow_unsupported: bool,
+    /// Runtime library functions declared by [`FunctionCx::call_runtime`](common::FunctionCx::call_runtime).
+    runtime_functions: FxHashMap<String, FuncId>,
     debug_context: Option<DebugContext>,
     unwind_context: UnwindContext,
     cgu_name: Symbol,
@@ -154,8 +165,17 @@ fn new(
             profiler: tcx.prof.clone(),
             output_filenames: tcx.output_filenames(()).clone(),
             should_write_ir: crate::pretty_clif::should_write_ir(tcx),
+            assembly: tcx
+                .sess
+                .opts
+                .output_types
+                .contains_key(&OutputType::Assembly)
+                .then(String::new),
             global_asm: String::new(),
             inline_asm_index: Cell::new(0),
+            is_jit: !matches!(backend_config.codegen_mode, CodegenMode::Aot),
+            allow_unsupported: backend_config.allow_unsupported,
+            runtime_functions: FxHashMap::default(),
             debug_context,
             unwind_context,
             cgu_name,
@@ -188,8 +208,50 @@ fn init(&self, sess: &Session) {
         }
     }
 
-    fn target_features(&self, _sess: &Session, _allow_unstable: bool) -> Vec<rustc_span::Symbol> {
-        vec![] // FIXME necessary for #[cfg(target_feature]
+    fn target_features(&self, sess: &Session, allow_unstable: bool) -> Vec<rustc_span::Symbol> {
+        let isa =
+            build_isa(sess, self.config.borrow().as_ref().unwrap_or(&BackendConfig::default()));
+        let isa_flags = isa.isa_flags();
+
+        // Cranelift requires these features for the respective architecture, so it doesn't have
+        // flags for them.
+        let mut features: Vec<Symbol> =
+            required_target_features(sess).iter().map(|&feature| Symbol::intern(feature)).collect();
+        // When targeting the generic x86-64 cpu `build_isa` uses nehalem instead, while the LLVM
+        // backend only enables the features above. Don't report the features implied by nehalem
+        // unless they are explicitly enabled, so `cfg(target_feature)` matches between both
+        // backends. Cranelift may still use them for codegen.
+        let implicit_features: &[&str] = if targets_nehalem_for_x86_64_cpu(sess) {
+            &["sse3", "ssse3", "sse4.1", "sse4.2", "popcnt"]
+        } else {
+            &[]
+        };
+        let explicit_features = sess
+            .opts
+            .cg
+            .target_feature
+            .split(',')
+            .filter_map(|feature| feature.strip_prefix('+'))
+            .collect::<Vec<_>>();
+        for &(feature, gate) in rustc_codegen_ssa::target_features::supported_target_features(sess)
+        {
+            // Unstable features are only reported on nightly like in the LLVM backend.
+            if !(sess.is_nightly_build() || allow_unstable || gate.is_stable()) {
+                continue;
+            }
+            if implicit_features.contains(&feature) && !explicit_features.contains(&feature) {
+                continue;
+            }
+            let isa_flag = clif_isa_flag_for_target_feature(feature);
+            if isa_flags.iter().any(|flag| flag.name == isa_flag && flag.as_bool() == Some(true)) {
+                features.push(Symbol::intern(feature));
+            }
+        }
+        // Cranelift has no flag for `cmpxchg16b`. cg_clif only uses it for 128bit atomics.
+        if sess.target.arch == "x86_64" && explicit_features.contains(&"cmpxchg16b") {
+            features.push(Symbol::intern("cmpxchg16b"));
+        }
+        features
     }
 
     fn print_version(&self) {
@@ -220,12 +282,13 @@ fn join_codegen(
         &self,
         ongoing_codegen: Box<dyn Any>,
         sess: &Session,
-        _outputs: &OutputFilenames,
+        outputs: &OutputFilenames,
     ) -> Result<(CodegenResults, FxIndexMap<WorkProductId, WorkProduct>), ErrorGuaranteed> {
-        Ok(ongoing_codegen
-            .downcast::<driver::aot::OngoingCodegen>()
-            .unwrap()
-            .join(sess, self.config.borrow().as_ref().unwrap()))
+        Ok(ongoing_codegen.downcast::<driver::aot::OngoingCodegen>().unwrap().join(
+            sess,
+            outputs,
+            self.config.borrow().as_ref().unwrap(),
+        ))
     }
 
     fn link(
@@ -300,9 +363,21 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn isa::Tar
         flags_builder.set("enable_probestack", "false").unwrap();
     }
 
+    // Settings which are not shared between all targets are applied to the isa builder below.
+    let mut isa_flags = vec![];
+    for (name, value) in &backend_config.cranelift_flags {
+        match flags_builder.set(name, value) {
+            Ok(()) => {}
+            Err(settings::SetError::BadName(_)) => isa_flags.push((name, value)),
+            Err(err) => {
+                sess.fatal(format!("invalid Cranelift setting `{}={}`: {}", name, value, err))
+            }
+        }
+    }
+
     let flags = settings::Flags::new(flags_builder);
 
-    let isa_builder = match sess.opts.cg.target_cpu.as_deref() {
+    let mut isa_builder = match sess.opts.cg.target_cpu.as_deref() {
         Some("native") => {
             let builder = cranelift_native::builder_with_options(true).unwrap();
             builder
@@ -323,20 +398,72 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn isa::Tar
                     sess.fatal(format!("can't compile for {}: {}", target_triple, err));
                 });
             if target_triple.architecture == target_lexicon::Architecture::X86_64 {
-                // Don't use "haswell" as the default, as it implies `has_lzcnt`.
+                // Use the cpu of the target spec if Cranelift knows it, except for the generic
+                // x86-64 cpu. Don't use "haswell" as the default, as it implies `has_lzcnt`.
                 // macOS CI is still at Ivy Bridge EP, so `lzcnt` is interpreted as `bsr`.
-                builder.enable("nehalem").unwrap();
+                if targets_nehalem_for_x86_64_cpu(sess) || builder.enable(&sess.target.cpu).is_err()
+                {
+                    builder.enable("nehalem").unwrap();
+                }
             }
             builder
         }
     };
 
+    for feature in sess.opts.cg.target_feature.split(',') {
+        let (enable, feature) = match feature.strip_prefix('+') {
+            Some(feature) => (true, feature),
+            None => match feature.strip_prefix('-') {
+                Some(feature) => (false, feature),
+                None => continue,
+            },
+        };
+        let value = if enable { "true" } else { "false" };
+        if isa_builder.set(&clif_isa_flag_for_target_feature(feature), value).is_err()
+            && !required_target_features(sess).contains(&feature)
+            && !(sess.target.arch == "x86_64" && feature == "cmpxchg16b")
+        {
+            sess.struct_warn(format!(
+                "unknown feature specified for `-Ctarget-feature`: `{}`",
+                feature
+            ))
+            .note("Cranelift doesn't support this feature, so it doesn't affect the generated code")
+            .emit();
+        }
+    }
+
+    for (name, value) in isa_flags {
+        if let Err(err) = isa_builder.set(name, value) {
+            sess.fatal(format!("invalid Cranelift setting `{}={}`: {}", name, value, err));
+        }
+    }
+
     match isa_builder.finish(flags) {
         Ok(target_isa) => target_isa,
         Err(err) => sess.fatal(format!("failed to build TargetIsa: {}", err)),
     }
 }
 
+/// Target features which Cranelift requires for the target architecture and thus has no isa flags
+/// for.
+fn required_target_features(sess: &Session) -> &'static [&'static str] {
+    match &*sess.target.arch {
+        "x86_64" => &["fxsr", "sse", "sse2"],
+        "aarch64" => &["neon"],
+        _ => &[],
+    }
+}
+
+/// Whether `build_isa` targets nehalem in place of the generic x86-64 cpu of the target spec.
+fn targets_nehalem_for_x86_64_cpu(sess: &Session) -> bool {
+    sess.target.arch == "x86_64" && sess.opts.cg.target_cpu.is_none() && sess.target.cpu == "x86-64"
+}
+
+/// Map a rust target feature like `sse4.1` to the Cranelift isa flag (`has_sse41`) enabling it.
+fn clif_isa_flag_for_target_feature(feature: &str) -> String {
+    format!("has_{}", feature.replace('.', ""))
+}
+
 /// This is the entrypoint for a hot plugged rustc_codegen_cranelift
 #[no_mangle]
 pub fn __rustc_codegen_backend() -> Box<dyn CodegenBackend> {
@@ -11,9 +11,18 @@ pub(crate) fn get_clif_linkage(
     match (linkage, visibility) {
         (RLinkage::External, Visibility::Default) if is_compiler_builtins => Linkage::Hidden,
         (RLinkage::External, Visibility::Default) => Linkage::Export,
-        (RLinkage::Internal, Visibility::Default) => Linkage::Local,
         (RLinkage::External, Visibility::Hidden) => Linkage::Hidden,
-        (RLinkage::WeakAny, Visibility::Default) => Linkage::Preemptible,
+        (RLinkage::External, Visibility::Protected) => Linkage::Export,
+        // Internalized instances (including shared generics that were not exported) are only
+        // referenced from within the codegen unit that defines them.
+        (RLinkage::Internal | RLinkage::Private, _) => Linkage::Local,
+        // Instances which may be instantiated in multiple codegen units get weak linkage, so the
+        // linker deduplicates them instead of reporting duplicate symbol errors. Cranelift has no
+        // way to express weak hidden symbols, so the visibility is dropped in that case.
+        (
+            RLinkage::WeakAny | RLinkage::WeakODR | RLinkage::LinkOnceAny | RLinkage::LinkOnceODR,
+            _,
+        ) => Linkage::Preemptible,
         _ => panic!("{:?} = {:?} {:?}", mono_item, linkage, visibility),
     }
 }
@@ -24,8 +33,12 @@ pub(crate) fn get_static_linkage(tcx: TyCtxt<'_>, def_id: DefId) -> Linkage {
     if let Some(linkage) = fn_attrs.linkage {
         match linkage {
             RLinkage::External => Linkage::Export,
-            RLinkage::Internal => Linkage::Local,
-            RLinkage::ExternalWeak | RLinkage::WeakAny => Linkage::Preemptible,
+            RLinkage::Internal | RLinkage::Private => Linkage::Local,
+            RLinkage::ExternalWeak
+            | RLinkage::WeakAny
+            | RLinkage::WeakODR
+            | RLinkage::LinkOnceAny
+            | RLinkage::LinkOnceODR => Linkage::Preemptible,
             _ => panic!("{:?}", linkage),
         }
     } else if tcx.is_reachable_non_generic(def_id) {
@@ -64,10 +64,10 @@ fn create_entry_fn(
             ],
             returns: vec![AbiParam::new(m.target_config().pointer_type() /*isize*/)],
             call_conv: crate::conv_to_call_conv(
-                tcx.sess,
                 tcx.sess.target.options.entry_abi,
                 m.target_config().default_call_conv,
-            ),
+            )
+            .unwrap_or_else(|err| tcx.sess.fatal(err)),
         };
 
         let entry_name = tcx.sess.target.options.entry_name.as_ref();
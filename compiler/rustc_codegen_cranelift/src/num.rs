@@ -48,7 +48,16 @@ fn codegen_compare_bin_op<'tcx>(
     rhs: Value,
 ) -> CValue<'tcx> {
     let intcc = crate::num::bin_op_to_intcc(bin_op, signed).unwrap();
-    let val = fx.bcx.ins().icmp(intcc, lhs, rhs);
+    let known_rhs = if fx.bcx.func.dfg.value_type(lhs) == types::I128 {
+        None
+    } else {
+        crate::optimize::peephole::maybe_known_iconst(&fx.bcx, rhs)
+    };
+    let val = if let Some(rhs) = known_rhs {
+        crate::optimize::peephole::icmp_imm_narrowed(&mut fx.bcx, intcc, lhs, rhs)
+    } else {
+        fx.bcx.ins().icmp(intcc, lhs, rhs)
+    };
     CValue::by_val(val, fx.layout_of(fx.tcx.types.bool))
 }
 
@@ -128,7 +137,9 @@ pub(crate) fn codegen_int_binop<'tcx>(
     let rhs = in_rhs.load_scalar(fx);
 
     let b = fx.bcx.ins();
-    // FIXME trap on overflow for the Unchecked versions
+    // Overflow and too large shift amounts are UB for the Unchecked versions, so they can share the
+    // wrapping instructions with their checked counterparts. Cranelift masks the shift amount to
+    // the bit width, which matches the semantics of Shl and Shr.
     let val = match bin_op {
         BinOp::Add | BinOp::AddUnchecked => b.iadd(lhs, rhs),
         BinOp::Sub | BinOp::SubUnchecked => b.isub(lhs, rhs),
@@ -328,20 +339,15 @@ pub(crate) fn codegen_float_binop<'tcx>(
         BinOp::Mul => b.fmul(lhs, rhs),
         BinOp::Div => b.fdiv(lhs, rhs),
         BinOp::Rem => {
-            let (name, ty) = match in_lhs.layout().ty.kind() {
-                ty::Float(FloatTy::F32) => ("fmodf", types::F32),
-                ty::Float(FloatTy::F64) => ("fmod", types::F64),
+            let name = match in_lhs.layout().ty.kind() {
+                ty::Float(FloatTy::F32) => "fmodf",
+                ty::Float(FloatTy::F64) => "fmod",
                 _ => bug!(),
             };
 
-            let ret_val = fx.lib_call(
-                name,
-                vec![AbiParam::new(ty), AbiParam::new(ty)],
-                vec![AbiParam::new(ty)],
-                &[lhs, rhs],
-            )[0];
-
-            return CValue::by_val(ret_val, in_lhs.layout());
+            let layout = in_lhs.layout();
+            let args = [CValue::by_val(lhs, layout), CValue::by_val(rhs, layout)];
+            return fx.easy_call(name, &args, layout.ty);
         }
         BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
             let fltcc = match bin_op {
@@ -1,6 +1,8 @@
 //! Peephole optimizations that can be performed while creating clif ir.
 
-use cranelift_codegen::ir::{condcodes::IntCC, InstructionData, Opcode, Value, ValueDef};
+use cranelift_codegen::ir::{
+    condcodes::IntCC, InstBuilder, InstructionData, Opcode, Value, ValueDef,
+};
 use cranelift_frontend::FunctionBuilder;
 
 /// If the given value was produced by the lowering of `Rvalue::Not` return the input and true,
@@ -22,6 +24,60 @@ pub(crate) fn maybe_unwrap_bool_not(bcx: &mut FunctionBuilder<'_>, arg: Value) -
     }
 }
 
+/// If the given value was produced by an `iconst` instruction return the constant.
+pub(crate) fn maybe_known_iconst(bcx: &FunctionBuilder<'_>, arg: Value) -> Option<i64> {
+    if let ValueDef::Result(arg_inst, 0) = bcx.func.dfg.value_def(arg) {
+        match bcx.func.dfg.insts[arg_inst] {
+            InstructionData::UnaryImm { opcode: Opcode::Iconst, imm } => Some(imm.bits()),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Emit `icmp_imm` comparing `lhs` against `imm`. If `lhs` is a zero or sign extension of a
+/// narrower value and the comparison gives the same result on the narrower value, it is performed
+/// on the narrower value instead. This avoids materializing the extended value, for example when
+/// comparing an enum discriminant against a constant, and allows the backend to fold the
+/// comparison into a conditional branch using it. `lhs` must not be wider than 64 bits.
+pub(crate) fn icmp_imm_narrowed(
+    bcx: &mut FunctionBuilder<'_>,
+    cc: IntCC,
+    lhs: Value,
+    imm: i64,
+) -> Value {
+    let lhs_bits = bcx.func.dfg.value_type(lhs).bits();
+    // The immediate of `icmp_imm` is sign extended for 128bit values.
+    assert!(lhs_bits <= 64, "{lhs_bits}");
+    if let ValueDef::Result(lhs_inst, 0) = bcx.func.dfg.value_def(lhs) {
+        if let InstructionData::Unary {
+            opcode: opcode @ (Opcode::Uextend | Opcode::Sextend),
+            arg,
+        } = bcx.func.dfg.insts[lhs_inst]
+        {
+            let narrow_bits = bcx.func.dfg.value_type(arg).bits();
+            let is_signed_cc = cc != cc.unsigned();
+            let is_eq_cc = matches!(cc, IntCC::Equal | IntCC::NotEqual);
+            let unsigned_imm = truncate_imm(imm, lhs_bits);
+            let signed_imm = (unsigned_imm << (64 - lhs_bits)) as i64 >> (64 - lhs_bits);
+            let fits = if opcode == Opcode::Uextend {
+                (is_eq_cc || !is_signed_cc) && unsigned_imm >> narrow_bits == 0
+            } else {
+                (is_eq_cc || is_signed_cc) && matches!(signed_imm >> (narrow_bits - 1), 0 | -1)
+            };
+            if fits {
+                return bcx.ins().icmp_imm(cc, arg, truncate_imm(imm, narrow_bits) as i64);
+            }
+        }
+    }
+    bcx.ins().icmp_imm(cc, lhs, imm)
+}
+
+fn truncate_imm(imm: i64, bits: u32) -> u64 {
+    if bits >= 64 { imm as u64 } else { imm as u64 & ((1 << bits) - 1) }
+}
+
 /// Returns whether the branch is statically known to be taken or `None` if it isn't statically known.
 pub(crate) fn maybe_known_branch_taken(
     bcx: &FunctionBuilder<'_>,
@@ -32,7 +32,8 @@ pub(crate) fn trap_unimplemented(fx: &mut FunctionCx<'_, '_, '_>, msg: impl AsRe
     codegen_print(fx, msg.as_ref());
 
     let one = fx.bcx.ins().iconst(types::I32, 1);
-    fx.lib_call("exit", vec![AbiParam::new(types::I32)], vec![], &[one]);
+    let one = CValue::by_val(one, fx.layout_of(fx.tcx.types.i32));
+    fx.easy_call("exit", &[one], fx.tcx.types.unit);
 
     fx.bcx.ins().trap(TrapCode::User(!0));
 }
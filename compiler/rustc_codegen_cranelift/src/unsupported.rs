@@ -0,0 +1,76 @@
+//! Reporting of constructs which are not supported by cg_clif.
+//!
+//! Instead of aborting the whole compilation on the first unsupported construct, an error is
+//! reported and codegen of just the function containing it is aborted by returning
+//! [`Unsupported`] all the way up to [`codegen_fn`](crate::base::codegen_fn). The body of this
+//! function is then replaced with one that prints the error and aborts when called. This way all
+//! unsupported constructs in a crate can be reported by a single compilation.
+
+use crate::prelude::*;
+
+/// Codegen of the current function was aborted because of an unsupported construct. The error has
+/// already been reported by [`FunctionCx::unsupported`].
+#[must_use]
+pub(crate) struct Unsupported {
+    msg: String,
+}
+
+/// The result of codegening part of a function body.
+pub(crate) type CodegenResult<T = ()> = Result<T, Unsupported>;
+
+impl FunctionCx<'_, '_, '_> {
+    /// Report that `msg` is not supported. The returned [`Unsupported`] must be propagated to abort
+    /// codegen of the current function.
+    ///
+    /// This emits an error unless the `allow_unsupported` option is set, in which case only a
+    /// warning is emitted and the compilation will succeed.
+    pub(crate) fn unsupported(&self, span: Span, msg: impl Into<String>) -> Unsupported {
+        let msg = msg.into();
+        let def_span = self.tcx.def_span(self.instance.def_id());
+        if self.cx.allow_unsupported {
+            self.tcx
+                .sess
+                .struct_span_warn(span, msg.clone())
+                .span_note(def_span, "calling this function will abort the program")
+                .emit();
+        } else {
+            self.tcx
+                .sess
+                .struct_span_err(span, msg.clone())
+                .span_note(def_span, "codegen of this function was skipped")
+                .emit();
+        }
+
+        Unsupported { msg }
+    }
+}
+
+/// Replace the partially codegened body of the current function with one that prints the error
+/// message of `unsupported` and aborts.
+pub(crate) fn replace_body_with_trap(fx: &mut FunctionCx<'_, '_, '_>, unsupported: Unsupported) {
+    // Codegen may have been aborted in the middle of a block. Terminate it, as `FunctionBuilder`
+    // requires all blocks to be filled.
+    if let Some(block) = fx.bcx.current_block() {
+        let is_terminated = fx
+            .bcx
+            .func
+            .layout
+            .last_inst(block)
+            .is_some_and(|inst| fx.bcx.func.dfg.insts[inst].opcode().is_terminator());
+        if !is_terminated {
+            fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
+        }
+    }
+
+    // The partially codegened body may jump to blocks which haven't been codegened yet, so remove
+    // it entirely. Blocks need to be sealed first as sealing may insert instructions into them.
+    fx.bcx.seal_all_blocks();
+    fx.bcx.func.layout.clear();
+    fx.current_bb = None;
+    fx.data_addrs.clear();
+
+    let block = fx.bcx.create_block();
+    fx.bcx.switch_to_block(block);
+    fx.bcx.append_block_params_for_function_params(block);
+    crate::trap::trap_unimplemented(fx, unsupported.msg);
+}
@@ -387,12 +387,6 @@ pub(crate) fn new_stack_slot(
             };
         }
 
-        if layout.size.bytes() >= u64::from(u32::MAX - 16) {
-            fx.tcx
-                .sess
-                .fatal(format!("values of type {} are too big to store on the stack", layout.ty));
-        }
-
         let stack_slot = fx.create_stack_slot(
             u32::try_from(layout.size.bytes()).unwrap(),
             u32::try_from(layout.align.pref.bytes()).unwrap(),
@@ -762,6 +756,29 @@ pub(crate) fn place_lane(
         }
     }
 
+    /// Like [`CPlace::place_lane`] except allowing a dynamically calculated lane index.
+    pub(crate) fn place_lane_dyn(
+        self,
+        fx: &mut FunctionCx<'_, '_, 'tcx>,
+        lane_idx: Value,
+    ) -> CPlace<'tcx> {
+        let layout = self.layout();
+        assert!(layout.ty.is_simd());
+        let (_lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
+        let lane_layout = fx.layout_of(lane_ty);
+
+        match self.inner {
+            CPlaceInner::Var(_, _) => unreachable!(),
+            CPlaceInner::VarPair(_, _, _) => unreachable!(),
+            CPlaceInner::Addr(ptr, None) => {
+                let field_offset = fx.bcx.ins().imul_imm(lane_idx, lane_layout.size.bytes() as i64);
+                let field_ptr = ptr.offset_value(fx, field_offset);
+                CPlace::for_ptr(field_ptr, lane_layout)
+            }
+            CPlaceInner::Addr(_, Some(_)) => unreachable!(),
+        }
+    }
+
     /// Like [`CPlace::place_field`] except using the passed type as lane type instead of the one
     /// specified by the vector type.
     pub(crate) fn place_typed_lane(
@@ -795,7 +812,41 @@ pub(crate) fn place_index(
         fx: &mut FunctionCx<'_, '_, 'tcx>,
         index: Value,
     ) -> CPlace<'tcx> {
-        let (elem_layout, ptr) = match self.layout().ty.kind() {
+        let (elem_layout, ptr) = self.elem_layout_and_ptr(fx);
+
+        // Out of bounds constant indices may still be codegened after a failing bounds check, so
+        // only fold the offset when it is representable.
+        if let Some(offset) = crate::optimize::peephole::maybe_known_iconst(&fx.bcx, index)
+            .and_then(|index| elem_layout.size.bytes().checked_mul(index as u64))
+            .and_then(|offset| i32::try_from(offset).ok())
+        {
+            return CPlace::for_ptr(ptr.offset_i64(fx, offset.into()), elem_layout);
+        }
+
+        let offset = fx.bcx.ins().imul_imm(index, elem_layout.size.bytes() as i64);
+
+        CPlace::for_ptr(ptr.offset_value(fx, offset), elem_layout)
+    }
+
+    /// Like [`CPlace::place_index`] except that the offset of a statically known index is folded
+    /// into the offset of the resulting pointer instead of being computed at runtime.
+    pub(crate) fn place_constant_index(
+        self,
+        fx: &mut FunctionCx<'_, '_, 'tcx>,
+        index: u64,
+    ) -> CPlace<'tcx> {
+        let (elem_layout, ptr) = self.elem_layout_and_ptr(fx);
+
+        let offset = elem_layout.size.bytes().checked_mul(index).unwrap();
+
+        CPlace::for_ptr(ptr.offset_i64(fx, i64::try_from(offset).unwrap()), elem_layout)
+    }
+
+    fn elem_layout_and_ptr(
+        self,
+        fx: &mut FunctionCx<'_, '_, 'tcx>,
+    ) -> (TyAndLayout<'tcx>, Pointer) {
+        match self.layout().ty.kind() {
             ty::Array(elem_ty, _) => {
                 let elem_layout = fx.layout_of(*elem_ty);
                 match self.inner {
@@ -807,11 +858,7 @@ pub(crate) fn place_index(
             }
             ty::Slice(elem_ty) => (fx.layout_of(*elem_ty), self.to_ptr_unsized().0),
             _ => bug!("place_index({:?})", self.layout().ty),
-        };
-
-        let offset = fx.bcx.ins().imul_imm(index, elem_layout.size.bytes() as i64);
-
-        CPlace::for_ptr(ptr.offset_value(fx, offset), elem_layout)
+        }
     }
 
     pub(crate) fn place_deref(self, fx: &mut FunctionCx<'_, '_, 'tcx>) -> CPlace<'tcx> {
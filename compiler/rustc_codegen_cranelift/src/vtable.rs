@@ -46,6 +46,7 @@ pub(crate) fn get_ptr_and_method_ref<'tcx>(
     mut arg: CValue<'tcx>,
     idx: usize,
 ) -> (Pointer, Value) {
+    let receiver_ty = arg.layout().ty;
     let (ptr, vtable) = 'block: {
         if let Abi::Scalar(_) = arg.layout().abi {
             while !arg.layout().ty.is_unsafe_ptr() && !arg.layout().ty.is_ref() {
@@ -69,6 +70,9 @@ pub(crate) fn get_ptr_and_method_ref<'tcx>(
         }
 
         if let Abi::ScalarPair(_, _) = arg.layout().abi {
+            // Newtype wrappers around a fat pointer like `Box<dyn Trait>` or
+            // `#[repr(transparent)]` structs share the `ScalarPair` abi of the wrapped fat
+            // pointer, so the data pointer and vtable are always the first and second scalar.
             let (ptr, vtable) = arg.load_scalar_pair(fx);
             (Pointer::new(ptr), vtable)
         } else {
@@ -84,6 +88,10 @@ pub(crate) fn get_ptr_and_method_ref<'tcx>(
         vtable,
         (idx * usize_size as usize) as i32,
     );
+    if fx.clif_comments.enabled() {
+        let load_inst = fx.bcx.func.dfg.value_def(func_ref).unwrap_inst();
+        fx.add_comment(load_inst, format!("vtable method {idx} of receiver {:?}", receiver_ty));
+    }
     (ptr, func_ref)
 }
 
@@ -95,9 +103,5 @@ pub(crate) fn get_vtable<'tcx>(
     let alloc_id = fx.tcx.vtable_allocation((ty, trait_ref));
     let data_id =
         data_id_for_alloc_id(&mut fx.constants_cx, &mut *fx.module, alloc_id, Mutability::Not);
-    let local_data_id = fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
-    if fx.clif_comments.enabled() {
-        fx.add_comment(local_data_id, format!("vtable: {:?}", alloc_id));
-    }
-    fx.bcx.ins().global_value(fx.pointer_type, local_data_id)
+    crate::constant::data_addr(fx, data_id, || format!("vtable: {:?}", alloc_id))
 }